@@ -28,11 +28,25 @@ struct County {
     id: i32,
 }
 
+#[derive(Debug, Deserialize)]
+struct Olympiad {
+    id: i32,
+    name: String,
+    organizer: String,
+    subjects: String,
+    level: String,
+    months: String,
+    stages: String,
+}
+
 fn main() {
     println!("cargo:rerun-if-changed=build.rs");
     println!("cargo:rerun-if-changed=cities.csv");
     println!("cargo:rerun-if-changed=subjects.csv");
     println!("cargo:rerun-if-changed=counties.csv");
+    println!("cargo:rerun-if-changed=olympiads.csv");
+
+    write_olympiads();
 
     let citiesmap_path =
         Path::new(&env::var("OUT_DIR").unwrap()).join("citiesmap.rs");
@@ -134,3 +148,85 @@ fn main() {
     .unwrap();
     writeln!(&mut citiesmap_file, ";").unwrap();
 }
+
+fn write_olympiads() {
+    let olympiadsmap_path =
+        Path::new(&env::var("OUT_DIR").unwrap()).join("olympiadsmap.rs");
+    let mut olympiadsmap_file =
+        BufWriter::new(File::create(olympiadsmap_path).unwrap());
+
+    let olympiads_file =
+        BufReader::new(File::open(Path::new("olympiads.csv")).unwrap());
+    let mut olympiads_map = &mut phf_codegen::Map::new();
+    let mut olympiads_map_rev = &mut phf_codegen::Map::new();
+    let mut olympiads_rdr = csv::Reader::from_reader(olympiads_file);
+    for result in olympiads_rdr.deserialize() {
+        let olympiad: Olympiad = result.unwrap();
+        let level = match olympiad.level.as_str() {
+            "final" => "Final",
+            "level1" => "Level1",
+            "level2" => "Level2",
+            "level3" => "Level3",
+            level => panic!("unknown level {level} of {}", olympiad.name),
+        };
+        let months = olympiad
+            .months
+            .split_whitespace()
+            .map(|month| {
+                let month: u32 = month.parse().unwrap();
+                assert!((1..=12).contains(&month), "wrong month {month}");
+                month.to_string()
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        let subjects = olympiad
+            .subjects
+            .split_whitespace()
+            .map(|subject| format!("Subject::{subject}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let stages = olympiad
+            .stages
+            .split_whitespace()
+            .map(|stage| match stage {
+                "school" => "OlympiadLevel::School",
+                "municipal" => "OlympiadLevel::Municipal",
+                "regional" => "OlympiadLevel::Regional",
+                "final" => "OlympiadLevel::Final",
+                stage => panic!("unknown stage {stage} of {}", olympiad.name),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            level == "Final",
+            !stages.is_empty(),
+            "only olympiads of the final level have stages: {}",
+            olympiad.name
+        );
+        let stages = stages.join(", ");
+        olympiads_map = olympiads_map.entry(
+            olympiad.id,
+            &format!(
+                "OlympiadInfo {{ name: \"{}\", organizer: \"{}\", \
+                 subjects: &[{subjects}], level: OlympiadLevel::{level}, \
+                 months: &[{months}], stages: &[{stages}] }}",
+                olympiad.name, olympiad.organizer
+            ),
+        );
+        olympiads_map_rev = olympiads_map_rev
+            .entry(olympiad.name.clone(), &olympiad.id.to_string());
+    }
+    write!(
+        &mut olympiadsmap_file,
+        "pub static OLYMPIADS: phf::Map<i32, OlympiadInfo> = {}",
+        olympiads_map.build()
+    )
+    .unwrap();
+    writeln!(&mut olympiadsmap_file, ";").unwrap();
+    write!(
+        &mut olympiadsmap_file,
+        "pub static OLYMPIADS_REV: phf::Map<&str, i32> = {}",
+        olympiads_map_rev.build()
+    )
+    .unwrap();
+    writeln!(&mut olympiadsmap_file, ";").unwrap();
+}
@@ -1,8 +1,11 @@
 //! `SeaORM` Entity. Generated by sea-orm-codegen 0.11.3
 
 use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq)]
+#[derive(
+    Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize,
+)]
 #[sea_orm(table_name = "datings")]
 pub struct Model {
     #[sea_orm(primary_key)]
@@ -13,6 +16,7 @@ pub struct Model {
     pub time: DateTime,
     pub initiator_reaction: Option<bool>,
     pub partner_reaction: Option<bool>,
+    pub initiator_msg: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
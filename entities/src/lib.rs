@@ -2,8 +2,14 @@
 
 pub mod prelude;
 
+pub mod achievements;
+pub mod blocks;
+pub mod broadcasts;
+pub mod campaign_deliveries;
 pub mod datings;
+pub mod events;
 pub mod images;
+pub mod reports;
 pub mod sea_orm_active_enums;
 pub mod states;
 pub mod users;
@@ -1,6 +1,10 @@
 //! `SeaORM` Entity. Generated by sea-orm-codegen 0.11.3
 
 pub use super::{
-    datings::Entity as Datings, images::Entity as Images,
+    achievements::Entity as Achievements, blocks::Entity as Blocks,
+    broadcasts::Entity as Broadcasts,
+    campaign_deliveries::Entity as CampaignDeliveries,
+    datings::Entity as Datings, events::Entity as Events,
+    images::Entity as Images, reports::Entity as Reports,
     states::Entity as States, users::Entity as Users,
 };
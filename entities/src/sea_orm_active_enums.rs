@@ -1,8 +1,58 @@
 //! `SeaORM` Entity. Generated by sea-orm-codegen 0.11.3
 
 use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum)]
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    EnumIter,
+    DeriveActiveEnum,
+    Serialize,
+    Deserialize,
+)]
+#[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "campaign")]
+pub enum Campaign {
+    #[sea_orm(string_value = "deactivated")]
+    Deactivated,
+    #[sea_orm(string_value = "grade_check")]
+    GradeCheck,
+    #[sea_orm(string_value = "inactive")]
+    Inactive,
+    #[sea_orm(string_value = "unfinished")]
+    Unfinished,
+}
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    EnumIter,
+    DeriveActiveEnum,
+    Serialize,
+    Deserialize,
+)]
+#[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "diploma")]
+pub enum Diploma {
+    #[sea_orm(string_value = "participant")]
+    Participant,
+    #[sea_orm(string_value = "prize_winner")]
+    PrizeWinner,
+    #[sea_orm(string_value = "winner")]
+    Winner,
+}
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    EnumIter,
+    DeriveActiveEnum,
+    Serialize,
+    Deserialize,
+)]
 #[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "gender")]
 pub enum Gender {
     #[sea_orm(string_value = "female")]
@@ -10,15 +60,39 @@ pub enum Gender {
     #[sea_orm(string_value = "male")]
     Male,
 }
-#[derive(Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum)]
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    EnumIter,
+    DeriveActiveEnum,
+    Serialize,
+    Deserialize,
+)]
 #[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "image_kind")]
 pub enum ImageKind {
     #[sea_orm(string_value = "image")]
     Image,
     #[sea_orm(string_value = "video")]
     Video,
+    #[sea_orm(string_value = "voice")]
+    Voice,
+    #[sea_orm(string_value = "video_note")]
+    VideoNote,
+    #[sea_orm(string_value = "animation")]
+    Animation,
 }
-#[derive(Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum)]
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    EnumIter,
+    DeriveActiveEnum,
+    Serialize,
+    Deserialize,
+)]
 #[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "location_filter")]
 pub enum LocationFilter {
     #[sea_orm(string_value = "same_city")]
@@ -30,3 +104,76 @@ pub enum LocationFilter {
     #[sea_orm(string_value = "same_subject")]
     SameSubject,
 }
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    EnumIter,
+    DeriveActiveEnum,
+    Serialize,
+    Deserialize,
+)]
+#[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "olympiad_level")]
+pub enum OlympiadLevel {
+    #[sea_orm(string_value = "final")]
+    Final,
+    #[sea_orm(string_value = "level1")]
+    Level1,
+    #[sea_orm(string_value = "level2")]
+    Level2,
+    #[sea_orm(string_value = "level3")]
+    Level3,
+    #[sea_orm(string_value = "municipal")]
+    Municipal,
+    #[sea_orm(string_value = "regional")]
+    Regional,
+    #[sea_orm(string_value = "school")]
+    School,
+}
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    EnumIter,
+    DeriveActiveEnum,
+    Serialize,
+    Deserialize,
+)]
+#[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "report_reason")]
+pub enum ReportReason {
+    #[sea_orm(string_value = "about")]
+    About,
+    #[sea_orm(string_value = "message")]
+    Message,
+    #[sea_orm(string_value = "name")]
+    Name,
+    #[sea_orm(string_value = "other")]
+    Other,
+    #[sea_orm(string_value = "photos")]
+    Photos,
+}
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    EnumIter,
+    DeriveActiveEnum,
+    Serialize,
+    Deserialize,
+)]
+#[sea_orm(
+    rs_type = "String",
+    db_type = "Enum",
+    enum_name = "report_resolution"
+)]
+pub enum ReportResolution {
+    #[sea_orm(string_value = "ban")]
+    Ban,
+    #[sea_orm(string_value = "dismiss")]
+    Dismiss,
+    #[sea_orm(string_value = "hide_photos")]
+    HidePhotos,
+}
@@ -1,10 +1,13 @@
 //! `SeaORM` Entity. Generated by sea-orm-codegen 0.11.3
 
 use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
 
-use super::sea_orm_active_enums::{Gender, LocationFilter};
+use super::sea_orm_active_enums::{Gender, LocationFilter, OlympiadLevel};
 
-#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq)]
+#[derive(
+    Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize,
+)]
 #[sea_orm(table_name = "users")]
 pub struct Model {
     #[sea_orm(primary_key, auto_increment = false)]
@@ -16,21 +19,53 @@ pub struct Model {
     pub active: bool,
     pub last_activity: DateTime,
     pub graduation_year: i16,
+    pub grade_confirmed: i16,
+    pub university: bool,
+    pub show_university: bool,
+    pub achievement_filter: Option<OlympiadLevel>,
     pub grade_up_filter: i16,
     pub grade_down_filter: i16,
-    pub subjects: i32,
-    pub subjects_filter: i32,
+    pub subjects: Vec<i32>,
+    pub subjects_filter: Vec<i32>,
     pub dating_purpose: i16,
     pub city: Option<i32>,
+    pub event_id: Option<i32>,
     pub location_filter: LocationFilter,
+    pub last_likes_reminder: Option<DateTime>,
+    pub banned: bool,
+    pub broadcast_opt_out: bool,
+    pub created: DateTime,
+    pub last_reminded_like: Option<i32>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(has_many = "super::achievements::Entity")]
+    Achievements,
+    #[sea_orm(
+        belongs_to = "super::events::Entity",
+        from = "Column::EventId",
+        to = "super::events::Column::Id",
+        on_update = "NoAction",
+        on_delete = "SetNull"
+    )]
+    Events,
     #[sea_orm(has_many = "super::images::Entity")]
     Images,
 }
 
+impl Related<super::achievements::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Achievements.def()
+    }
+}
+
+impl Related<super::events::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Events.def()
+    }
+}
+
 impl Related<super::images::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::Images.def()
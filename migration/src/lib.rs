@@ -3,6 +3,22 @@ pub use sea_orm_migration::prelude::*;
 mod m20230526_000001_create_users;
 mod m20230610_110326_add_image_type;
 mod m20230610_172346_create_states;
+mod m20230614_193052_add_states_dialogue;
+mod m20230618_142510_add_likes_reminder;
+mod m20230621_181204_create_blocks;
+mod m20230623_094117_create_reports;
+mod m20230626_201533_add_users_banned;
+mod m20230629_173402_create_broadcasts;
+mod m20230702_154019_create_campaign_deliveries;
+mod m20230706_120532_add_grade_confirmed;
+mod m20230709_183650_add_users_university;
+mod m20230712_201417_create_achievements;
+mod m20230715_163020_create_events;
+mod m20230718_094512_subjects_arrays;
+mod m20230720_181244_add_images_position;
+mod m20230723_102741_add_media_kinds;
+mod m20230725_093318_add_images_file_unique_id;
+mod m20230727_110412_add_like_messages;
 
 pub struct Migrator;
 
@@ -13,6 +29,22 @@ impl MigratorTrait for Migrator {
             Box::new(m20230526_000001_create_users::Migration),
             Box::new(m20230610_110326_add_image_type::Migration),
             Box::new(m20230610_172346_create_states::Migration),
+            Box::new(m20230614_193052_add_states_dialogue::Migration),
+            Box::new(m20230618_142510_add_likes_reminder::Migration),
+            Box::new(m20230621_181204_create_blocks::Migration),
+            Box::new(m20230623_094117_create_reports::Migration),
+            Box::new(m20230626_201533_add_users_banned::Migration),
+            Box::new(m20230629_173402_create_broadcasts::Migration),
+            Box::new(m20230702_154019_create_campaign_deliveries::Migration),
+            Box::new(m20230706_120532_add_grade_confirmed::Migration),
+            Box::new(m20230709_183650_add_users_university::Migration),
+            Box::new(m20230712_201417_create_achievements::Migration),
+            Box::new(m20230715_163020_create_events::Migration),
+            Box::new(m20230718_094512_subjects_arrays::Migration),
+            Box::new(m20230720_181244_add_images_position::Migration),
+            Box::new(m20230723_102741_add_media_kinds::Migration),
+            Box::new(m20230725_093318_add_images_file_unique_id::Migration),
+            Box::new(m20230727_110412_add_like_messages::Migration),
         ]
     }
 }
@@ -0,0 +1,140 @@
+use sea_orm::{sea_query::extension::postgres::Type, EnumIter, Iterable};
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_type(
+                Type::create()
+                    .as_enum(ReportReason::Table)
+                    .values(ReportReason::iter().skip(1))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_type(
+                Type::create()
+                    .as_enum(ReportResolution::Table)
+                    .values(ReportResolution::iter().skip(1))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(Reports::Table)
+                    .col(
+                        ColumnDef::new(Reports::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(Reports::ReporterId)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(Reports::Table, Reports::ReporterId)
+                            .to(Users::Table, Users::Id),
+                    )
+                    .col(
+                        ColumnDef::new(Reports::ReportedId)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(Reports::Table, Reports::ReportedId)
+                            .to(Users::Table, Users::Id),
+                    )
+                    .col(
+                        ColumnDef::new(Reports::Reason)
+                            .enumeration(
+                                ReportReason::Table,
+                                ReportReason::iter().skip(1),
+                            )
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(Reports::Text).string().not_null())
+                    .col(
+                        ColumnDef::new(Reports::Time)
+                            .date_time()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .col(ColumnDef::new(Reports::Resolution).enumeration(
+                        ReportResolution::Table,
+                        ReportResolution::iter().skip(1),
+                    ))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .table(Reports::Table)
+                    .col(Reports::ReportedId)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Reports::Table).to_owned())
+            .await?;
+        manager
+            .drop_type(Type::drop().name(ReportResolution::Table).to_owned())
+            .await?;
+        manager
+            .drop_type(Type::drop().name(ReportReason::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden, EnumIter)]
+enum ReportReason {
+    Table,
+    Name,
+    About,
+    Photos,
+    Message,
+    Other,
+}
+
+#[derive(Iden, EnumIter)]
+enum ReportResolution {
+    Table,
+    Ban,
+    HidePhotos,
+    Dismiss,
+}
+
+#[derive(Iden)]
+enum Reports {
+    Table,
+    Id,
+    ReporterId,
+    ReportedId,
+    Reason,
+    Text,
+    Time,
+    Resolution,
+}
+
+#[derive(Iden)]
+enum Users {
+    Table,
+    Id,
+}
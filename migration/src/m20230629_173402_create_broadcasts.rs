@@ -0,0 +1,126 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Broadcasts::Table)
+                    .col(
+                        ColumnDef::new(Broadcasts::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(Broadcasts::AuthorId)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(Broadcasts::Text).string().not_null())
+                    .col(
+                        ColumnDef::new(Broadcasts::OnlyActive)
+                            .boolean()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(Broadcasts::County).integer())
+                    .col(ColumnDef::new(Broadcasts::Region).integer())
+                    .col(
+                        ColumnDef::new(Broadcasts::GraduationYear)
+                            .small_integer(),
+                    )
+                    .col(ColumnDef::new(Broadcasts::Subjects).integer())
+                    .col(
+                        ColumnDef::new(Broadcasts::LastUserId)
+                            .big_integer()
+                            .not_null()
+                            .default(i64::MIN),
+                    )
+                    .col(
+                        ColumnDef::new(Broadcasts::Sent)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(
+                        ColumnDef::new(Broadcasts::Blocked)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(
+                        ColumnDef::new(Broadcasts::Failed)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(
+                        ColumnDef::new(Broadcasts::Created)
+                            .date_time()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .col(ColumnDef::new(Broadcasts::Finished).date_time())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Users::Table)
+                    .add_column(
+                        ColumnDef::new(Users::BroadcastOptOut)
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Users::Table)
+                    .drop_column(Users::BroadcastOptOut)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .drop_table(Table::drop().table(Broadcasts::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum Broadcasts {
+    Table,
+    Id,
+    AuthorId,
+    Text,
+    OnlyActive,
+    County,
+    Region,
+    GraduationYear,
+    Subjects,
+    LastUserId,
+    Sent,
+    Blocked,
+    Failed,
+    Created,
+    Finished,
+}
+
+#[derive(Iden)]
+enum Users {
+    Table,
+    BroadcastOptOut,
+}
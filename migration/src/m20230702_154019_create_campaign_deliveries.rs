@@ -0,0 +1,145 @@
+use sea_orm::{sea_query::extension::postgres::Type, EnumIter, Iterable};
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_type(
+                Type::create()
+                    .as_enum(Campaign::Table)
+                    .values(Campaign::iter().skip(1))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(CampaignDeliveries::Table)
+                    .col(
+                        ColumnDef::new(CampaignDeliveries::UserId)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(CampaignDeliveries::Campaign)
+                            .enumeration(
+                                Campaign::Table,
+                                Campaign::iter().skip(1),
+                            )
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(CampaignDeliveries::Time)
+                            .date_time()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .primary_key(
+                        Index::create()
+                            .col(CampaignDeliveries::UserId)
+                            .col(CampaignDeliveries::Campaign),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Creation time of existing profiles is unknown, the last activity
+        // is the closest guess
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Users::Table)
+                    .add_column(
+                        ColumnDef::new(Users::Created)
+                            .date_time()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .exec_stmt(
+                Query::update()
+                    .table(Users::Table)
+                    .value(Users::Created, Expr::col(Users::LastActivity))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(States::Table)
+                    .add_column(
+                        ColumnDef::new(States::Created)
+                            .date_time()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(States::Table)
+                    .drop_column(States::Created)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Users::Table)
+                    .drop_column(Users::Created)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .drop_table(
+                Table::drop().table(CampaignDeliveries::Table).to_owned(),
+            )
+            .await?;
+        manager
+            .drop_type(Type::drop().name(Campaign::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden, EnumIter)]
+enum Campaign {
+    Table,
+    Inactive,
+    Unfinished,
+    Deactivated,
+}
+
+#[derive(Iden)]
+enum CampaignDeliveries {
+    Table,
+    UserId,
+    Campaign,
+    Time,
+}
+
+#[derive(Iden)]
+enum Users {
+    Table,
+    LastActivity,
+    Created,
+}
+
+#[derive(Iden)]
+enum States {
+    Table,
+    Created,
+}
@@ -0,0 +1,72 @@
+use sea_orm::sea_query::extension::postgres::Type;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_type(
+                Type::alter()
+                    .name(Campaign::Table)
+                    .add_value(Campaign::GradeCheck)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Users::Table)
+                    .add_column(
+                        ColumnDef::new(Users::GradeConfirmed)
+                            .small_integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // The grade was right in the academic year the profile was created
+        manager
+            .exec_stmt(
+                Query::update()
+                    .table(Users::Table)
+                    .value(
+                        Users::GradeConfirmed,
+                        Expr::cust(
+                            "extract(year FROM created - interval '8 months')",
+                        ),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Postgres can't drop enum values, `grade_check` stays
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Users::Table)
+                    .drop_column(Users::GradeConfirmed)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum Campaign {
+    Table,
+    GradeCheck,
+}
+
+#[derive(Iden)]
+enum Users {
+    Table,
+    GradeConfirmed,
+}
@@ -0,0 +1,167 @@
+use sea_orm::{sea_query::extension::postgres::Type, EnumIter, Iterable};
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Levels are compared in the order of the values, from the lowest
+        manager
+            .create_type(
+                Type::create()
+                    .as_enum(OlympiadLevel::Table)
+                    .values(OlympiadLevel::iter().skip(1))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_type(
+                Type::create()
+                    .as_enum(Diploma::Table)
+                    .values(Diploma::iter().skip(1))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(Achievements::Table)
+                    .col(
+                        ColumnDef::new(Achievements::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(Achievements::UserId)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(Achievements::Table, Achievements::UserId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .col(
+                        ColumnDef::new(Achievements::Olympiad)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(Achievements::Subject)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(Achievements::Level)
+                            .enumeration(
+                                OlympiadLevel::Table,
+                                OlympiadLevel::iter().skip(1),
+                            )
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(Achievements::Year)
+                            .small_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(Achievements::Diploma)
+                            .enumeration(
+                                Diploma::Table,
+                                Diploma::iter().skip(1),
+                            )
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .table(Achievements::Table)
+                    .col(Achievements::UserId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Users::Table)
+                    .add_column(
+                        ColumnDef::new(Users::AchievementFilter).enumeration(
+                            OlympiadLevel::Table,
+                            OlympiadLevel::iter().skip(1),
+                        ),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Users::Table)
+                    .drop_column(Users::AchievementFilter)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .drop_table(Table::drop().table(Achievements::Table).to_owned())
+            .await?;
+        manager
+            .drop_type(Type::drop().name(Diploma::Table).to_owned())
+            .await?;
+        manager
+            .drop_type(Type::drop().name(OlympiadLevel::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden, EnumIter)]
+enum OlympiadLevel {
+    Table,
+    School,
+    Municipal,
+    Regional,
+    Level3,
+    Level2,
+    Level1,
+    Final,
+}
+
+#[derive(Iden, EnumIter)]
+enum Diploma {
+    Table,
+    Participant,
+    PrizeWinner,
+    Winner,
+}
+
+#[derive(Iden)]
+enum Achievements {
+    Table,
+    Id,
+    UserId,
+    Olympiad,
+    Subject,
+    Level,
+    Year,
+    Diploma,
+}
+
+#[derive(Iden)]
+enum Users {
+    Table,
+    Id,
+    AchievementFilter,
+}
@@ -0,0 +1,77 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Events::Table)
+                    .col(
+                        ColumnDef::new(Events::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Events::Name).string().not_null())
+                    .col(ColumnDef::new(Events::City).integer())
+                    .col(ColumnDef::new(Events::Starts).date().not_null())
+                    .col(ColumnDef::new(Events::Ends).date().not_null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Users::Table)
+                    .add_column(ColumnDef::new(Users::EventId).integer())
+                    .add_foreign_key(
+                        TableForeignKey::new()
+                            .name("fk-users-event_id")
+                            .from_tbl(Users::Table)
+                            .from_col(Users::EventId)
+                            .to_tbl(Events::Table)
+                            .to_col(Events::Id)
+                            .on_delete(ForeignKeyAction::SetNull),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Users::Table)
+                    .drop_column(Users::EventId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(Events::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum Events {
+    Table,
+    Id,
+    Name,
+    City,
+    Starts,
+    Ends,
+}
+
+#[derive(Iden)]
+enum Users {
+    Table,
+    EventId,
+}
@@ -0,0 +1,70 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+/// Bitmasks of subjects become arrays of subject ids, the id of a subject is
+/// the number of its bit
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        // Transform expressions can't have subqueries
+        db.execute_unprepared(
+            "CREATE FUNCTION subject_ids(bits integer) RETURNS integer[] \
+             LANGUAGE sql IMMUTABLE AS $$ SELECT coalesce(array_agg(id ORDER \
+             BY id), '{}') FROM generate_series(0, 31) AS id WHERE bits & (1 \
+             << id) <> 0 $$",
+        )
+        .await?;
+        db.execute_unprepared(
+            "ALTER TABLE users ALTER COLUMN subjects DROP DEFAULT, ALTER \
+             COLUMN subjects TYPE integer[] USING subject_ids(subjects), \
+             ALTER COLUMN subjects SET DEFAULT '{}', ALTER COLUMN \
+             subjects_filter DROP DEFAULT, ALTER COLUMN subjects_filter TYPE \
+             integer[] USING subject_ids(subjects_filter), ALTER COLUMN \
+             subjects_filter SET DEFAULT '{}'",
+        )
+        .await?;
+        db.execute_unprepared(
+            "ALTER TABLE broadcasts ALTER COLUMN subjects TYPE integer[] \
+             USING subject_ids(subjects)",
+        )
+        .await?;
+        // Achievements have exactly one subject
+        db.execute_unprepared(
+            "UPDATE achievements SET subject = (subject_ids(subject))[1]",
+        )
+        .await?;
+        db.execute_unprepared("DROP FUNCTION subject_ids").await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        db.execute_unprepared(
+            "CREATE FUNCTION subject_bits(ids integer[]) RETURNS integer \
+             LANGUAGE sql IMMUTABLE AS $$ SELECT CAST(coalesce(sum(1 << id), \
+             0) AS integer) FROM unnest(ids) AS id $$",
+        )
+        .await?;
+        db.execute_unprepared(
+            "ALTER TABLE users ALTER COLUMN subjects DROP DEFAULT, ALTER \
+             COLUMN subjects TYPE integer USING subject_bits(subjects), ALTER \
+             COLUMN subjects SET DEFAULT 0, ALTER COLUMN subjects_filter DROP \
+             DEFAULT, ALTER COLUMN subjects_filter TYPE integer USING \
+             subject_bits(subjects_filter), ALTER COLUMN subjects_filter SET \
+             DEFAULT 0",
+        )
+        .await?;
+        db.execute_unprepared(
+            "ALTER TABLE broadcasts ALTER COLUMN subjects TYPE integer USING \
+             subject_bits(subjects)",
+        )
+        .await?;
+        db.execute_unprepared("UPDATE achievements SET subject = 1 << subject")
+            .await?;
+        db.execute_unprepared("DROP FUNCTION subject_bits").await?;
+        Ok(())
+    }
+}
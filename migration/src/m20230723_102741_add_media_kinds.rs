@@ -0,0 +1,44 @@
+use sea_orm::sea_query::extension::postgres::Type;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let kinds =
+            [ImageKind::Voice, ImageKind::VideoNote, ImageKind::Animation];
+        for kind in kinds {
+            manager
+                .alter_type(
+                    Type::alter()
+                        .name(ImageKind::Table)
+                        .add_value(kind)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Postgres can't drop enum values, but the old code can't read them
+        manager
+            .get_connection()
+            .execute_unprepared(
+                "DELETE FROM images WHERE kind IN ('voice', 'video_note', \
+                 'animation')",
+            )
+            .await?;
+        Ok(())
+    }
+}
+
+#[derive(Iden)]
+enum ImageKind {
+    Table,
+    Voice,
+    VideoNote,
+    Animation,
+}
@@ -1,2 +1,2 @@
 #!/bin/sh
-sea-orm-cli generate entity -o entities/src -l
+sea-orm-cli generate entity -o entities/src -l --with-serde both
@@ -0,0 +1,280 @@
+use std::sync::Arc;
+
+use anyhow::Context;
+use teloxide::{
+    prelude::*,
+    types::{BotCommandScope, Recipient},
+    utils::command::{BotCommands, ParseError},
+};
+
+use crate::{
+    broadcasts::Segment,
+    datings::public_profile,
+    db::Database,
+    events::{self, NewEvent},
+    text, Bot,
+};
+
+/// Commands available only to admins from the `ADMIN_IDS` env var (comma
+/// separated user ids)
+#[derive(Debug, BotCommands, Clone, strum::IntoStaticStr)]
+#[command(rename_rule = "lowercase", description = "Команды администратора:")]
+pub enum AdminCommand {
+    #[command(description = "статистика бота")]
+    Stats,
+    #[command(description = "анкета и лайки пользователя: /user <id>")]
+    User(i64),
+    #[command(description = "забанить пользователя: /ban <id>")]
+    Ban(i64),
+    #[command(description = "разбанить пользователя: /unban <id>")]
+    Unban(i64),
+    #[command(description = "выключить анкету: /deactivate <id>")]
+    Deactivate(i64),
+    #[command(
+        description = "написать от имени бота: /say <id> <текст>",
+        parse_with = parse_say
+    )]
+    Say(i64, String),
+    #[command(
+        description = "рассылка: /broadcast <фильтры>, текст со следующей \
+                       строки. Фильтры: all, active, county=<id>, \
+                       region=<id>, year=<год выпуска>, \
+                       subjects=<Math,Physics,...>",
+        parse_with = parse_broadcast
+    )]
+    Broadcast(Segment, String),
+    #[command(description = "текущие и будущие события")]
+    Events,
+    #[command(
+        description = "добавить событие: /addevent <начало> <конец> \
+                       <название>, даты как ДД.ММ.ГГГГ, город со следующей \
+                       строки",
+        parse_with = parse_event
+    )]
+    AddEvent(NewEvent),
+    #[command(description = "удалить событие: /delevent <id>")]
+    DelEvent(i32),
+}
+
+// teloxide requires parsers to take a `String`
+#[allow(clippy::needless_pass_by_value)]
+fn parse_say(input: String) -> Result<(i64, String), ParseError> {
+    let (id, text) = input
+        .trim()
+        .split_once(char::is_whitespace)
+        .ok_or_else(|| ParseError::TooFewArguments {
+            expected: 2,
+            found: 1,
+            message: "/say <id> <текст>".to_owned(),
+        })?;
+    let id = id.parse().map_err(|e| ParseError::IncorrectFormat(Box::new(e)))?;
+    Ok((id, text.trim().to_owned()))
+}
+
+// teloxide requires parsers to take a `String`
+#[allow(clippy::needless_pass_by_value)]
+fn parse_broadcast(input: String) -> Result<(Segment, String), ParseError> {
+    let (segment, text) = input.split_once('\n').unwrap_or((&input, ""));
+    let segment = segment
+        .parse()
+        .map_err(|e: anyhow::Error| ParseError::IncorrectFormat(e.into()))?;
+    let text = text.trim();
+    if text.is_empty() {
+        return Err(ParseError::TooFewArguments {
+            expected: 2,
+            found: 1,
+            message: "/broadcast <фильтры>\n<текст>".to_owned(),
+        });
+    }
+    Ok((segment, text.to_owned()))
+}
+
+// teloxide requires parsers to take a `String`
+#[allow(clippy::needless_pass_by_value)]
+fn parse_event(input: String) -> Result<(NewEvent,), ParseError> {
+    let event = input
+        .parse()
+        .map_err(|e: anyhow::Error| ParseError::IncorrectFormat(e.into()))?;
+    Ok((event,))
+}
+
+pub fn admins() -> Vec<i64> {
+    std::env::var("ADMIN_IDS")
+        .unwrap_or_default()
+        .split(',')
+        .filter_map(|id| id.trim().parse().ok())
+        .collect()
+}
+
+pub fn is_admin(id: i64) -> bool {
+    admins().contains(&id)
+}
+
+/// Register commands in the menu, admins also see admin commands
+pub async fn set_commands(bot: &Bot) -> anyhow::Result<()> {
+    let commands = crate::Command::bot_commands();
+    bot.set_my_commands(commands.clone()).await?;
+
+    let admin_commands: Vec<_> =
+        commands.into_iter().chain(AdminCommand::bot_commands()).collect();
+    for id in admins() {
+        bot.set_my_commands(admin_commands.clone())
+            .scope(BotCommandScope::Chat { chat_id: Recipient::Id(ChatId(id)) })
+            .await?;
+    }
+    Ok(())
+}
+
+const fn yes_no(value: bool) -> &'static str {
+    if value {
+        "да"
+    } else {
+        "нет"
+    }
+}
+
+#[tracing::instrument(err, skip(db, bot))]
+pub async fn answer(
+    db: Arc<Database>,
+    bot: Bot,
+    msg: Message,
+    cmd: AdminCommand,
+) -> anyhow::Result<()> {
+    let chat = msg.chat.id;
+    crate::metrics::UPDATES
+        .with_label_values(&["admin_command", (&cmd).into()])
+        .inc();
+
+    // All commands except /stats are about a user
+    if let AdminCommand::User(id)
+    | AdminCommand::Ban(id)
+    | AdminCommand::Unban(id)
+    | AdminCommand::Deactivate(id)
+    | AdminCommand::Say(id, _) = cmd
+    {
+        if db.get_user(id).await?.is_none() {
+            bot.send_message(chat, text::USER_NOT_FOUND).await?;
+            return Ok(());
+        }
+    }
+
+    match cmd {
+        AdminCommand::Stats => {
+            let stats = db.get_stats().await?;
+            let msg = format!(
+                "Анкет: {}\nВключённых: {}\nАктивных за неделю: {}\n\
+                 Забаненных: {}\n\nРекомендаций: {}\nЛайков: {}\nВзаимных: \
+                 {}\n\nНерассмотренных жалоб: {}",
+                stats.users,
+                stats.active_users,
+                stats.recent_users,
+                stats.banned_users,
+                stats.datings,
+                stats.likes,
+                stats.matches,
+                stats.open_reports
+            );
+            bot.send_message(chat, msg).await?;
+        }
+        AdminCommand::User(id) => {
+            let user = db.get_user(id).await?.context("user not found")?;
+            let images = db.get_images(id).await?.len();
+            let stats = db.get_user_stats(id).await?;
+            let profile = public_profile(&db, &user).await?;
+
+            crate::datings::send_user_photos(&bot, &db, id, chat.0).await?;
+
+            let msg = format!(
+                "{profile}\n\nid: {id}\nВключена: {}\nЗабанен: {}\nПоследняя \
+                 активность: {}\nФото: {images}\n\nПросмотрено анкет: {}\n\
+                 Лайков отправлено: {}\nЛайков получено: {}\nВзаимных: {}",
+                yes_no(user.active),
+                yes_no(user.banned),
+                user.last_activity.format("%d.%m.%Y %H:%M"),
+                stats.recommended,
+                stats.likes_sent,
+                stats.likes_received,
+                stats.matches
+            );
+            bot.send_message(chat, msg).await?;
+        }
+        AdminCommand::Ban(id) => {
+            db.set_banned(id, true).await?;
+            notify(&bot, id, text::PROFILE_BANNED).await;
+            bot.send_message(chat, format!("Пользователь {id} забанен"))
+                .await?;
+        }
+        AdminCommand::Unban(id) => {
+            db.set_banned(id, false).await?;
+            notify(&bot, id, text::PROFILE_UNBANNED).await;
+            bot.send_message(chat, format!("Пользователь {id} разбанен"))
+                .await?;
+        }
+        AdminCommand::Deactivate(id) => {
+            db.create_or_update_user(crate::UserSettings {
+                active: Some(false),
+                ..crate::UserSettings::with_id(id)
+            })
+            .await?;
+            bot.send_message(chat, format!("Анкета {id} выключена")).await?;
+        }
+        AdminCommand::Say(id, text) => {
+            bot.send_message(ChatId(id), text).await?;
+            bot.send_message(chat, "Отправлено").await?;
+        }
+        AdminCommand::Broadcast(segment, text) => {
+            let broadcast =
+                db.create_broadcast(chat.0, text, segment).await?;
+            let recipients = db.count_broadcast_recipients(&broadcast).await?;
+            bot.send_message(
+                chat,
+                format!(
+                    "Рассылка #{} запущена, получателей: {recipients}. \
+                     Статистика придёт после завершения",
+                    broadcast.id
+                ),
+            )
+            .await?;
+        }
+        AdminCommand::Events => {
+            let events = db.get_upcoming_events().await?;
+            let msg = if events.is_empty() {
+                "Событий нет".to_owned()
+            } else {
+                events
+                    .iter()
+                    .map(events::describe)
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            };
+            bot.send_message(chat, msg).await?;
+        }
+        AdminCommand::AddEvent(event) => {
+            let event = db.create_event(event).await?;
+            bot.send_message(
+                chat,
+                format!("Событие добавлено:\n{}", events::describe(&event)),
+            )
+            .await?;
+        }
+        AdminCommand::DelEvent(id) => {
+            let msg = if db.delete_event(id).await? {
+                "Событие удалено"
+            } else {
+                "Событие не найдено"
+            };
+            bot.send_message(chat, msg).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Send a message to the user, ignoring errors if the bot is blocked
+async fn notify(bot: &Bot, id: i64, text: &str) {
+    if let Err(e) = bot.send_message(ChatId(id), text).await {
+        sentry_anyhow::capture_anyhow(
+            &anyhow::Error::from(e).context("error notifying user"),
+        );
+    }
+}
@@ -0,0 +1,105 @@
+use std::path::PathBuf;
+
+use anyhow::{ensure, Context};
+use entities::images;
+use itertools::Itertools;
+use teloxide::{net::Download, requests::Requester};
+
+use crate::Bot;
+
+/// Directory of the local copies of profile media. Telegram file ids die with
+/// the bot token, the copies are uploaded again then. Media isn't archived
+/// without the directory.
+fn dir() -> Option<PathBuf> {
+    std::env::var_os("MEDIA_DIR").map(PathBuf::from)
+}
+
+/// Path of the local copy of the file. Files are named by their
+/// `file_unique_id`, so every file is stored once.
+fn path(unique_id: &str) -> Option<PathBuf> {
+    let safe = !unique_id.is_empty()
+        && unique_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    safe.then(|| Some(dir()?.join(unique_id))).flatten()
+}
+
+/// Download the file from Telegram unless the archive has it
+pub async fn store(
+    bot: &Bot,
+    unique_id: &str,
+    file_path: &str,
+) -> anyhow::Result<()> {
+    let Some(path) = path(unique_id) else {
+        ensure!(dir().is_none(), "bad file_unique_id {unique_id:?}");
+        return Ok(());
+    };
+    if tokio::fs::try_exists(&path).await? {
+        return Ok(());
+    }
+    let dir = path.parent().context("media path has no directory")?;
+    tokio::fs::create_dir_all(dir).await?;
+
+    // The file is downloaded under a unique name, so concurrent downloads of
+    // the same file don't mix, and `part` removes a failed one when dropped
+    let (file, part) = tempfile::Builder::new()
+        .prefix(unique_id)
+        .suffix(".part")
+        .tempfile_in(dir)?
+        .into_parts();
+    let mut file = tokio::fs::File::from_std(file);
+    bot.download_file(file_path, &mut file).await?;
+    file.sync_all().await?;
+    part.persist(&path)?;
+    Ok(())
+}
+
+/// Archive the file in the background, the profile works without the copy
+/// until the file id dies
+fn spawn_store(bot: &Bot, unique_id: String, file_id: String) {
+    if dir().is_none() {
+        return;
+    }
+    let bot = bot.clone();
+    tokio::spawn(async move {
+        let stored = async {
+            let file = bot.get_file(&file_id).await?;
+            store(&bot, &unique_id, &file.path).await
+        };
+        if let Err(e) = stored.await {
+            sentry_anyhow::capture_anyhow(&e.context("can't archive media"));
+        }
+    });
+}
+
+/// Archive the new images of the saved profile and remove the copies of
+/// files no image has anymore
+pub async fn update(bot: &Bot, saved: &[images::Model], unused: &[String]) {
+    let new = saved
+        .iter()
+        .filter(|image| image.id < 0)
+        .filter_map(|image| Some((image.file_unique_id.as_ref()?, image)))
+        .unique_by(|(unique_id, _)| *unique_id);
+    for (unique_id, image) in new {
+        spawn_store(bot, unique_id.clone(), image.telegram_id.clone());
+    }
+    for unique_id in unused {
+        let Some(path) = path(unique_id) else {
+            continue;
+        };
+        match tokio::fs::remove_file(path).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                sentry_anyhow::capture_anyhow(
+                    &anyhow::Error::from(e).context("can't remove media copy"),
+                );
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Local copy of the file if the archive has it
+pub async fn find(unique_id: Option<&str>) -> Option<PathBuf> {
+    let path = path(unique_id?)?;
+    tokio::fs::try_exists(&path).await.ok()?.then_some(path)
+}
@@ -0,0 +1,243 @@
+use std::{str::FromStr, sync::Arc, time::Duration};
+
+use anyhow::{bail, Context};
+use entities::broadcasts;
+use teloxide::{
+    prelude::*,
+    types::{InlineKeyboardButton, InlineKeyboardMarkup},
+    ApiError, RequestError,
+};
+use tokio::time::MissedTickBehavior;
+use tracing::*;
+
+use crate::{
+    callbacks::Callback,
+    db::Database,
+    text,
+    types::{Subjects, UserSettings},
+    Bot,
+};
+
+/// How often to look for new broadcasts
+const CHECK_PERIOD: Duration = Duration::from_secs(10);
+
+/// How many recipients to load from the database at once
+const BATCH_SIZE: u64 = 100;
+
+/// Users the broadcast is sent to. Parsed from space separated filters:
+/// `all`, `active`, `county=<id>`, `region=<id>` (subject of the
+/// federation), `year=<graduation year>` and `subjects=<names>` (users who
+/// have any of the comma separated subjects, like `Math,Physics`).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Segment {
+    pub only_active: bool,
+    pub county: Option<i32>,
+    pub region: Option<i32>,
+    pub graduation_year: Option<i16>,
+    pub subjects: Option<Subjects>,
+}
+
+impl FromStr for Segment {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut segment = Self::default();
+        for filter in s.split_whitespace() {
+            match filter.split_once('=') {
+                None if filter == "all" => {}
+                None if filter == "active" => segment.only_active = true,
+                Some(("county", id)) => segment.county = Some(id.parse()?),
+                Some(("region", id)) => segment.region = Some(id.parse()?),
+                Some(("year", year)) => {
+                    segment.graduation_year = Some(year.parse()?);
+                }
+                Some(("subjects", names)) => {
+                    let subjects = names.split(',').map(str::parse);
+                    segment.subjects =
+                        Some(subjects.collect::<Result<_, _>>()?);
+                }
+                _ => bail!("unknown filter: {filter}"),
+            }
+        }
+        Ok(segment)
+    }
+}
+
+/// Result of sending the broadcast to one user
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Delivery {
+    Sent,
+    /// The user has blocked the bot or deleted the account
+    Blocked,
+    Failed,
+}
+
+impl Delivery {
+    /// Counter of the broadcast to increment
+    pub const fn column(self) -> broadcasts::Column {
+        match self {
+            Self::Sent => broadcasts::Column::Sent,
+            Self::Blocked => broadcasts::Column::Blocked,
+            Self::Failed => broadcasts::Column::Failed,
+        }
+    }
+}
+
+/// Sends broadcasts created by admins. Progress is saved after every user,
+/// so unfinished broadcasts continue after a restart. Messages are paced
+/// below the global Telegram limit with `BROADCAST_RATE` env var (messages
+/// per second), leaving room for replies to users.
+#[derive(Clone, Debug)]
+pub struct Broadcaster {
+    pub rate: u32,
+}
+
+impl Default for Broadcaster {
+    fn default() -> Self {
+        Self { rate: 20 }
+    }
+}
+
+impl Broadcaster {
+    pub fn from_env() -> anyhow::Result<Self> {
+        let rate = std::env::var("BROADCAST_RATE").map_or(
+            Ok(Self::default().rate),
+            |v| v.parse().context("can't parse BROADCAST_RATE"),
+        )?;
+        if rate == 0 {
+            bail!("BROADCAST_RATE must be positive");
+        }
+        Ok(Self { rate })
+    }
+
+    /// Send broadcasts forever
+    pub async fn run(self, bot: Bot, db: Arc<Database>) {
+        let mut interval = tokio::time::interval(CHECK_PERIOD);
+        loop {
+            interval.tick().await;
+            if let Err(e) = self.send_pending(&bot, &db).await {
+                sentry_anyhow::capture_anyhow(
+                    &e.context("error while sending broadcasts"),
+                );
+            }
+        }
+    }
+
+    /// Send all unfinished broadcasts to the end
+    #[instrument(skip(self, bot, db))]
+    pub async fn send_pending(
+        &self,
+        bot: &Bot,
+        db: &Database,
+    ) -> anyhow::Result<()> {
+        for broadcast in db.get_unfinished_broadcasts().await? {
+            self.send(bot, db, broadcast).await?;
+        }
+        Ok(())
+    }
+
+    async fn send(
+        &self,
+        bot: &Bot,
+        db: &Database,
+        mut broadcast: broadcasts::Model,
+    ) -> anyhow::Result<()> {
+        let mut pace =
+            tokio::time::interval(Duration::from_secs(1) / self.rate);
+        pace.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            let recipients =
+                db.get_broadcast_recipients(&broadcast, BATCH_SIZE).await?;
+            if recipients.is_empty() {
+                break;
+            }
+
+            for id in recipients {
+                pace.tick().await;
+                let delivery = deliver(bot, db, &broadcast, id).await?;
+                db.update_broadcast_progress(broadcast.id, id, delivery)
+                    .await?;
+                broadcast.last_user_id = id;
+            }
+        }
+
+        let broadcast = db.finish_broadcast(broadcast.id).await?;
+        info!("broadcast {} is finished", broadcast.id);
+
+        let report = format!(
+            "Рассылка #{} завершена\nДоставлено: {}\nЗаблокировали бота: \
+             {}\nОшибок: {}",
+            broadcast.id, broadcast.sent, broadcast.blocked, broadcast.failed
+        );
+        if let Err(e) =
+            bot.send_message(ChatId(broadcast.author_id), report).await
+        {
+            sentry_anyhow::capture_anyhow(
+                &anyhow::Error::from(e)
+                    .context("error sending broadcast report"),
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Send the broadcast to the user, waiting out flood limits
+async fn deliver(
+    bot: &Bot,
+    db: &Database,
+    broadcast: &broadcasts::Model,
+    id: i64,
+) -> anyhow::Result<Delivery> {
+    let keyboard = vec![vec![InlineKeyboardButton::callback(
+        "Отписаться от рассылки",
+        Callback::BroadcastOptOut.to_string(),
+    )]];
+
+    loop {
+        let result = bot
+            .send_message(ChatId(id), &broadcast.text)
+            .reply_markup(InlineKeyboardMarkup::new(keyboard.clone()))
+            .await;
+        return Ok(match result {
+            Ok(_) => Delivery::Sent,
+            Err(RequestError::RetryAfter(duration)) => {
+                warn!("broadcast is throttled for {duration:?}");
+                tokio::time::sleep(duration).await;
+                continue;
+            }
+            Err(
+                e @ RequestError::Api(
+                    ApiError::BotBlocked | ApiError::UserDeactivated,
+                ),
+            ) => {
+                crate::metrics::api_error(&e);
+                db.create_or_update_user(UserSettings {
+                    active: Some(false),
+                    ..UserSettings::with_id(id)
+                })
+                .await?;
+                Delivery::Blocked
+            }
+            Err(e) => {
+                crate::metrics::api_error(&e);
+                sentry_anyhow::capture_anyhow(
+                    &anyhow::Error::from(e).context("error sending broadcast"),
+                );
+                Delivery::Failed
+            }
+        });
+    }
+}
+
+/// Stop sending broadcasts to the user
+pub async fn opt_out(
+    bot: &Bot,
+    db: &Database,
+    msg: &Message,
+) -> anyhow::Result<()> {
+    db.set_broadcast_opt_out(msg.chat.id.0, true).await?;
+    bot.edit_message_reply_markup(msg.chat.id, msg.id).await?;
+    bot.send_message(msg.chat.id, text::BROADCAST_OPTED_OUT).await?;
+    Ok(())
+}
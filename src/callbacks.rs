@@ -1,9 +1,11 @@
 use std::{fmt::Display, str::FromStr};
 
 use anyhow::{bail, Context};
+use entities::sea_orm_active_enums::{ReportReason, ReportResolution};
 use migration::Write;
+use sea_orm::Iterable;
 
-use crate::types::{DatingPurpose, Subjects};
+use crate::types::{DatingPurpose, Subject, SubjectCategory, Subjects};
 
 #[derive(PartialEq, Eq)]
 pub enum RateCode {
@@ -62,13 +64,210 @@ where
     }
 }
 
+/// Button of the subjects keyboard. Pages are numbered like
+/// `SubjectCategory::ALL`, so the data stays short however many subjects
+/// there are.
+#[derive(PartialEq, Eq)]
+pub enum SubjectsAction {
+    Toggle(Subject),
+    /// Select the whole category of the page or unselect it
+    TogglePage(u64),
+    Page(u64),
+    /// Unselect everything and stay on the page
+    Clear(u64),
+    Continue,
+}
+
+impl SubjectsAction {
+    /// Change the selected subjects, returns the page to show
+    pub fn apply(&self, subjects: &mut Subjects) -> u64 {
+        match self {
+            Self::Toggle(subject) => {
+                subjects.toggle(*subject);
+                subject.category().page()
+            }
+            Self::TogglePage(page) => {
+                if let Some(category) = usize::try_from(*page)
+                    .ok()
+                    .and_then(|page| SubjectCategory::ALL.get(page))
+                {
+                    subjects.toggle_all(category.subjects());
+                }
+                *page
+            }
+            Self::Page(page) => *page,
+            Self::Clear(page) => {
+                subjects.clear();
+                *page
+            }
+            Self::Continue => 0,
+        }
+    }
+}
+
+impl Display for SubjectsAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Toggle(subject) => {
+                f.write_fmt(format_args!("{}", i32::from(*subject)))
+            }
+            Self::TogglePage(page) => f.write_fmt(format_args!("a{page}")),
+            Self::Page(page) => f.write_fmt(format_args!("p{page}")),
+            Self::Clear(page) => f.write_fmt(format_args!("x{page}")),
+            Self::Continue => f.write_str("continue"),
+        }
+    }
+}
+
+impl FromStr for SubjectsAction {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(if s == "continue" {
+            Self::Continue
+        } else if let Some(page) = s.strip_prefix('a') {
+            Self::TogglePage(page.parse()?)
+        } else if let Some(page) = s.strip_prefix('p') {
+            Self::Page(page.parse()?)
+        } else if let Some(page) = s.strip_prefix('x') {
+            Self::Clear(page.parse()?)
+        } else {
+            Self::Toggle(s.parse::<i32>()?.try_into()?)
+        })
+    }
+}
+
+/// Button of the photo manager, images are identified by their id
+#[derive(PartialEq, Eq)]
+pub enum PhotosAction {
+    Up(i32),
+    Down(i32),
+    /// Make the image the first one in the profile
+    First(i32),
+    Delete(i32),
+    Add,
+    Done,
+}
+
+impl Display for PhotosAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Up(id) => f.write_fmt(format_args!("u{id}")),
+            Self::Down(id) => f.write_fmt(format_args!("d{id}")),
+            Self::First(id) => f.write_fmt(format_args!("f{id}")),
+            Self::Delete(id) => f.write_fmt(format_args!("x{id}")),
+            Self::Add => f.write_char('a'),
+            Self::Done => f.write_char('o'),
+        }
+    }
+}
+
+impl FromStr for PhotosAction {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut chars = s.chars();
+        let action = chars.next().context("empty photos action")?;
+        let id = chars.as_str();
+        Ok(match action {
+            'u' => Self::Up(id.parse()?),
+            'd' => Self::Down(id.parse()?),
+            'f' => Self::First(id.parse()?),
+            'x' => Self::Delete(id.parse()?),
+            'a' => Self::Add,
+            'o' => Self::Done,
+            _ => bail!("unknown photos action"),
+        })
+    }
+}
+
+/// Field of the profile that can be changed on its own. The label is the
+/// callback data, so buttons of old menus keep working.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, sea_orm::EnumIter)]
+pub enum EditField {
+    Name,
+    Gender,
+    GenderFilter,
+    Grade,
+    GradeFilter,
+    Subjects,
+    SubjectsFilter,
+    DatingPurpose,
+    City,
+    LocationFilter,
+    About,
+    Achievements,
+    AchievementsFilter,
+    Photos,
+}
+
+impl EditField {
+    pub const fn label(self) -> &'static str {
+        match self {
+            Self::Name => "Имя",
+            Self::Gender => "Пол",
+            Self::GenderFilter => "Пол партнёра",
+            Self::Grade => "Класс",
+            Self::GradeFilter => "Классы",
+            Self::Subjects => "Предметы",
+            Self::SubjectsFilter => "Предметы партнёра",
+            Self::DatingPurpose => "Цель знакомства",
+            Self::City => "Город",
+            Self::LocationFilter => "Где искать",
+            Self::About => "О себе",
+            Self::Achievements => "Олимпиады",
+            Self::AchievementsFilter => "Олимпиады партнёра",
+            Self::Photos => "Фото",
+        }
+    }
+}
+
+#[derive(PartialEq, Eq)]
+pub enum ReportStep {
+    /// Show the reasons to choose from
+    ChooseReason,
+    Reason(ReportReason),
+    /// Bring back the buttons of the reported message
+    Cancel,
+}
+
+/// Position of the enum variant, so it fits into the callback data
+fn variant_index<T: Iterable + PartialEq>(value: &T) -> usize {
+    T::iter().position(|v| v == *value).unwrap_or_default()
+}
+
+fn variant_from_index<T: Iterable>(index: &str) -> anyhow::Result<T> {
+    T::iter().nth(index.parse()?).context("variant index is out of range")
+}
+
 #[derive(PartialEq, Eq)]
 pub enum Callback {
-    SetSubjects(UpdateBitflags<Subjects>),
-    SetSubjectsFilter(UpdateBitflags<Subjects>),
+    SetSubjects(SubjectsAction),
+    SetSubjectsFilter(SubjectsAction),
     SetDatingPurpose(UpdateBitflags<DatingPurpose>),
-    Edit,
+    SetGradeFilter { up: i16, down: i16 },
+    /// Whether a school student sees university students
+    SetUniversityFilter { show: bool },
+    /// Field to change, `None` to finish editing
+    Edit(Option<EditField>),
     Dating { dating_id: i32, code: RateCode },
+    /// Block the other user of the dating
+    Block { dating_id: i32 },
+    /// Report the other user of the dating to moderators
+    Report { dating_id: i32, step: ReportStep },
+    /// Moderator's decision on the report
+    Moderate { report_id: i32, resolution: ReportResolution },
+    /// Page of the incoming likes inbox
+    Likes { page: u64 },
+    /// Page of the mutual likes history
+    Matches { page: u64 },
+    /// Stop receiving broadcasts
+    BroadcastOptOut,
+    /// Answer to the yearly question about the grade
+    ConfirmGrade { confirmed: bool },
+    /// Event the user is at, `None` to leave it
+    SetEvent { event_id: Option<i32> },
+    ManagePhotos(PhotosAction),
     CreateProfile,
     FindPartner,
 }
@@ -79,8 +278,19 @@ impl Callback {
             Self::SetSubjects(_) => 's',
             Self::SetSubjectsFilter(_) => 'd',
             Self::SetDatingPurpose(_) => 'p',
-            Self::Edit => 'e',
+            Self::SetGradeFilter { .. } => 'g',
+            Self::SetUniversityFilter { .. } => 'v',
+            Self::Edit(_) => 'e',
             Self::Dating { code, .. } => code.into(),
+            Self::Block { .. } => '🚫',
+            Self::Report { .. } => '⚠',
+            Self::Moderate { .. } => '🛡',
+            Self::Likes { .. } => 'l',
+            Self::Matches { .. } => 'm',
+            Self::BroadcastOptOut => 'u',
+            Self::ConfirmGrade { .. } => 'y',
+            Self::SetEvent { .. } => '📍',
+            Self::ManagePhotos(_) => '🖼',
             Self::CreateProfile => '✍',
             Self::FindPartner => '🚀',
         }
@@ -95,10 +305,55 @@ impl Display for Callback {
                 f.write_fmt(format_args!("{u}"))?;
             }
             Self::SetDatingPurpose(u) => f.write_fmt(format_args!("{u}"))?,
-            Self::Dating { dating_id, code: _ } => {
+            Self::SetGradeFilter { up, down } => {
+                f.write_fmt(format_args!("{up}:{down}"))?;
+            }
+            Self::Dating { dating_id, code: _ }
+            | Self::Block { dating_id } => {
+                f.write_fmt(format_args!("{dating_id}"))?;
+            }
+            Self::Report { dating_id, step } => {
                 f.write_fmt(format_args!("{dating_id}"))?;
+                match step {
+                    ReportStep::ChooseReason => {}
+                    ReportStep::Reason(reason) => {
+                        f.write_fmt(format_args!(
+                            ":{}",
+                            variant_index(reason)
+                        ))?;
+                    }
+                    ReportStep::Cancel => f.write_str(":c")?,
+                }
+            }
+            Self::Moderate { report_id, resolution } => {
+                f.write_fmt(format_args!(
+                    "{report_id}:{}",
+                    variant_index(resolution)
+                ))?;
+            }
+            Self::Likes { page } | Self::Matches { page } => {
+                f.write_fmt(format_args!("{page}"))?;
+            }
+            Self::SetUniversityFilter { show: flag }
+            | Self::ConfirmGrade { confirmed: flag } => {
+                f.write_char(if *flag { '1' } else { '0' })?;
+            }
+            Self::SetEvent { event_id } => {
+                if let Some(id) = event_id {
+                    f.write_fmt(format_args!("{id}"))?;
+                }
+            }
+            Self::ManagePhotos(action) => {
+                f.write_fmt(format_args!("{action}"))?;
+            }
+            Self::Edit(field) => {
+                if let Some(field) = field {
+                    f.write_str(field.label())?;
+                }
             }
-            Self::Edit | Self::CreateProfile | Self::FindPartner => {}
+            Self::BroadcastOptOut
+            | Self::CreateProfile
+            | Self::FindPartner => {}
         };
         Ok(())
     }
@@ -113,31 +368,8 @@ impl FromStr for Callback {
         let data: String = chars.collect();
 
         Ok(match first_char {
-            's' | 'd' => {
-                if data == "continue" {
-                    match first_char {
-                        's' => Self::SetSubjects(UpdateBitflags::Continue),
-                        'd' => {
-                            Self::SetSubjectsFilter(UpdateBitflags::Continue)
-                        }
-                        _ => bail!("this should never occur"), /* TODO: remove this */
-                    }
-                } else {
-                    let subjects_int = data.parse()?;
-                    let subjects = Subjects::from_bits(subjects_int)
-                        .context("can't create subjects")?;
-
-                    match first_char {
-                        's' => {
-                            Self::SetSubjects(UpdateBitflags::Update(subjects))
-                        }
-                        'd' => Self::SetSubjectsFilter(UpdateBitflags::Update(
-                            subjects,
-                        )),
-                        _ => bail!("this should never occur"), /* TODO: remove this */
-                    }
-                }
-            }
+            's' => Self::SetSubjects(data.parse()?),
+            'd' => Self::SetSubjectsFilter(data.parse()?),
             'p' => {
                 if data == "continue" {
                     Self::SetDatingPurpose(UpdateBitflags::Continue)
@@ -148,9 +380,54 @@ impl FromStr for Callback {
                     Self::SetDatingPurpose(UpdateBitflags::Update(purpose))
                 }
             }
-            'e' => Self::Edit,
+            'g' => {
+                let (up, down) =
+                    data.split_once(':').context("can't split grade filter")?;
+                Self::SetGradeFilter { up: up.parse()?, down: down.parse()? }
+            }
+            'v' => Self::SetUniversityFilter { show: data == "1" },
+            'e' => Self::Edit(
+                (!data.is_empty())
+                    .then(|| {
+                        EditField::iter()
+                            .find(|field| field.label() == data)
+                            .context("unknown edit field")
+                    })
+                    .transpose()?,
+            ),
+            'l' => Self::Likes { page: data.parse()? },
+            'm' => Self::Matches { page: data.parse()? },
+            'u' => Self::BroadcastOptOut,
+            'y' => Self::ConfirmGrade { confirmed: data == "1" },
+            '📍' => Self::SetEvent {
+                event_id: (!data.is_empty()).then(|| data.parse()).transpose()?,
+            },
+            '🖼' => Self::ManagePhotos(data.parse()?),
             '✍' => Self::CreateProfile,
             '🚀' => Self::FindPartner,
+            '🚫' => Self::Block { dating_id: data.parse()? },
+            '⚠' => match data.split_once(':') {
+                None => Self::Report {
+                    dating_id: data.parse()?,
+                    step: ReportStep::ChooseReason,
+                },
+                Some((dating_id, "c")) => Self::Report {
+                    dating_id: dating_id.parse()?,
+                    step: ReportStep::Cancel,
+                },
+                Some((dating_id, reason)) => Self::Report {
+                    dating_id: dating_id.parse()?,
+                    step: ReportStep::Reason(variant_from_index(reason)?),
+                },
+            },
+            '🛡' => {
+                let (report_id, resolution) =
+                    data.split_once(':').context("can't split moderation")?;
+                Self::Moderate {
+                    report_id: report_id.parse()?,
+                    resolution: variant_from_index(resolution)?,
+                }
+            }
             '👎' | '💌' | '👍' | '💔' | '❤' => {
                 let dating_id = data.parse()?;
                 let code = first_char.try_into()?;
@@ -2,11 +2,12 @@ use std::{fmt::Display, str::FromStr};
 
 use anyhow::Context;
 use itertools::Itertools;
+use serde::{Deserialize, Serialize};
 use strsim::jaro_winkler;
 
 include!(concat!(env!("OUT_DIR"), "/citiesmap.rs"));
 
-#[derive(Clone, Default, Debug, PartialEq, Eq)]
+#[derive(Clone, Default, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct UserCity(Option<City>);
 
 impl Display for UserCity {
@@ -28,7 +29,7 @@ impl UserCity {
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct City(i32);
 
 impl City {
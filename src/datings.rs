@@ -1,21 +1,22 @@
+use std::time::Instant;
+
 use anyhow::Context;
-use entities::{datings, sea_orm_active_enums::ImageKind};
+use entities::{datings, users};
 use teloxide::{
     prelude::*,
     types::{
-        Chat, InlineKeyboardButton, InlineKeyboardMarkup, InputFile,
-        InputMedia, InputMediaPhoto, InputMediaVideo, KeyboardButton,
-        KeyboardMarkup, KeyboardRemove, MessageId,
+        Chat, InlineKeyboardButton, InlineKeyboardMarkup, KeyboardButton,
+        KeyboardMarkup, MessageId,
     },
     ApiError, RequestError,
 };
 use tracing::*;
 
 use crate::{
-    callbacks::{Callback, RateCode},
+    callbacks::{Callback, RateCode, ReportStep},
     db::Database,
     text,
-    types::{PublicProfile, UserSettings},
+    types::{GradeFilter, PublicProfile, UserSettings},
     Bot,
 };
 
@@ -27,14 +28,19 @@ pub async fn send_profile(
     let user =
         db.get_user(id).await?.context("user to send profile not found")?;
 
-    let profile: PublicProfile = (&user).try_into()?;
+    let profile = public_profile(db, &user).await?;
 
     send_user_photos(bot, db, id, id).await?;
 
-    let msg = format!("Так выглядит ваша анкета:\n\n{profile}");
+    let grade_filter = GradeFilter::from(&user);
+
+    let msg = format!(
+        "Так выглядит ваша анкета:\n\n{profile}\n\n🎓 Вы ищете: \
+         {grade_filter}."
+    );
 
     bot.send_message(ChatId(id), msg)
-        .reply_markup(KeyboardRemove::new())
+        .reply_markup(crate::utils::make_edit_keyboard(false))
         .await?;
 
     send_ready_to_datings(bot, id).await?;
@@ -42,6 +48,23 @@ pub async fn send_profile(
     Ok(())
 }
 
+/// Profile of the user with olympiad results and the current event
+pub async fn public_profile(
+    db: &Database,
+    user: &users::Model,
+) -> anyhow::Result<PublicProfile> {
+    let achievements = db
+        .get_achievements(user.id)
+        .await?
+        .into_iter()
+        .map(TryInto::try_into)
+        .collect::<anyhow::Result<_>>()?;
+    let event = db.get_user_event(user).await?;
+    Ok(PublicProfile::try_from(user)?
+        .with_achievements(achievements)
+        .with_event(event.as_ref()))
+}
+
 async fn send_ready_to_datings(bot: &Bot, id: i64) -> anyhow::Result<()> {
     let keyboard = vec![vec![InlineKeyboardButton::callback(
         "Смотреть анкеты 🚀",
@@ -55,11 +78,33 @@ async fn send_ready_to_datings(bot: &Bot, id: i64) -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Tell banned and disabled users they can't date, `false` then
+pub async fn check_can_date(
+    bot: &Bot,
+    db: &Database,
+    chat: ChatId,
+) -> anyhow::Result<bool> {
+    let user = db.get_user(chat.0).await?.context("user not found")?;
+    let refusal = if user.banned {
+        text::PROFILE_BANNED
+    } else if !user.active {
+        text::ENABLE_TO_DATE
+    } else {
+        return Ok(true);
+    };
+    bot.send_message(chat, refusal).await?;
+    Ok(false)
+}
+
 pub async fn send_recommendation(
     bot: &Bot,
     db: &Database,
     chat: ChatId,
 ) -> anyhow::Result<()> {
+    if !check_can_date(bot, db, chat).await? {
+        return Ok(());
+    }
+
     if !crate::utils::check_user_subscribed_channel(bot, chat.0).await? {
         let keyboard = vec![vec![InlineKeyboardButton::callback(
             "Я подписался на канал",
@@ -87,7 +132,15 @@ pub async fn send_recommendation(
         return Ok(());
     }
 
-    if let Some((dating, partner)) = db.get_partner(chat.0).await? {
+    let search_start = Instant::now();
+    let partner = db.get_partner(chat.0).await?;
+    crate::metrics::PARTNER_SEARCH_SECONDS
+        .observe(search_start.elapsed().as_secs_f64());
+    crate::metrics::PARTNER_SEARCHES
+        .with_label_values(&[if partner.is_some() { "hit" } else { "miss" }])
+        .inc();
+
+    if let Some((dating, partner)) = partner {
         // Clean buttons of old message with this dating if it exist
         if let Some(msg) = dating.initiator_msg_id {
             if let Err(e) = bot
@@ -106,32 +159,38 @@ pub async fn send_recommendation(
 
         send_user_photos(bot, db, partner.id, chat.0).await?;
 
-        let keyboard = vec![vec![
-            InlineKeyboardButton::callback(
-                "👎",
-                Callback::Dating {
-                    dating_id: dating.id,
-                    code: RateCode::Dislike,
-                }
-                .to_string(),
-            ),
-            InlineKeyboardButton::callback(
-                "💌",
-                Callback::Dating {
-                    dating_id: dating.id,
-                    code: RateCode::LikeWithMsg,
-                }
-                .to_string(),
-            ),
-            InlineKeyboardButton::callback(
-                "👍",
-                Callback::Dating { dating_id: dating.id, code: RateCode::Like }
+        let keyboard = vec![
+            vec![
+                InlineKeyboardButton::callback(
+                    "👎",
+                    Callback::Dating {
+                        dating_id: dating.id,
+                        code: RateCode::Dislike,
+                    }
                     .to_string(),
-            ),
-        ]];
+                ),
+                InlineKeyboardButton::callback(
+                    "💌",
+                    Callback::Dating {
+                        dating_id: dating.id,
+                        code: RateCode::LikeWithMsg,
+                    }
+                    .to_string(),
+                ),
+                InlineKeyboardButton::callback(
+                    "👍",
+                    Callback::Dating {
+                        dating_id: dating.id,
+                        code: RateCode::Like,
+                    }
+                    .to_string(),
+                ),
+            ],
+            vec![report_button(dating.id), block_button(dating.id)],
+        ];
         let keyboard_markup = InlineKeyboardMarkup::new(keyboard);
 
-        let partner_profile: PublicProfile = (&partner).try_into()?;
+        let partner_profile = public_profile(db, &partner).await?;
 
         let sent_msg = bot
             .send_message(chat, partner_profile.to_string())
@@ -168,18 +227,43 @@ pub async fn send_recommendation(
     Ok(())
 }
 
+/// Refuse a like if the users blocked each other after the recommendation,
+/// returns `true` if the like was refused
+pub async fn refuse_blocked_like(
+    db: &Database,
+    dating: &entities::datings::Model,
+) -> anyhow::Result<bool> {
+    if !db.is_blocked(dating.initiator_id, dating.partner_id).await? {
+        return Ok(false);
+    }
+    db.set_dating_initiator_reaction(dating.id, false).await?;
+    Ok(true)
+}
+
 pub async fn send_like(
     db: &Database,
     bot: &Bot,
     dating: &entities::datings::Model,
     msg: Option<String>,
 ) -> anyhow::Result<()> {
+    // Blocked users can't send likes to each other
+    if db.is_blocked(dating.initiator_id, dating.partner_id).await? {
+        return Ok(());
+    }
+
+    if let Some(m) = &msg {
+        db.set_dating_like_msg(dating.id, m.clone()).await?;
+    }
+
     let user = db
         .get_user(dating.initiator_id)
         .await?
         .context("dating initiator not found")?;
+    if user.banned {
+        return Ok(());
+    }
 
-    let user_profile: PublicProfile = (&user).try_into()?;
+    let user_profile = public_profile(db, &user).await?;
 
     let like_msg = msg.map_or_else(
         || format!("Кому-то понравилась ваша анкета:\n\n{user_profile}"),
@@ -214,25 +298,10 @@ pub async fn send_like(
         Ok(_) => {}
     }
 
-    let keyboard = vec![vec![
-        InlineKeyboardButton::callback(
-            "💔",
-            Callback::Dating {
-                dating_id: dating.id,
-                code: RateCode::ResponseDislike,
-            }
-            .to_string(),
-        ),
-        InlineKeyboardButton::callback(
-            "❤",
-            Callback::Dating {
-                dating_id: dating.id,
-                code: RateCode::ResponseLike,
-            }
-            .to_string(),
-        ),
-    ]];
-    let keyboard_markup = InlineKeyboardMarkup::new(keyboard);
+    let keyboard_markup = InlineKeyboardMarkup::new([
+        response_buttons(dating.id),
+        vec![report_button(dating.id), block_button(dating.id)],
+    ]);
 
     match bot
         .send_message(ChatId(dating.partner_id), like_msg)
@@ -256,12 +325,186 @@ pub async fn send_like(
                     .context("error sending like while sending profile"),
             );
         }
-        Ok(_) => {}
+        Ok(_) => crate::metrics::LIKES.inc(),
     }
 
     Ok(())
 }
 
+fn report_button(dating_id: i32) -> InlineKeyboardButton {
+    InlineKeyboardButton::callback(
+        "⚠ Пожаловаться",
+        Callback::Report { dating_id, step: ReportStep::ChooseReason }
+            .to_string(),
+    )
+}
+
+fn block_button(dating_id: i32) -> InlineKeyboardButton {
+    InlineKeyboardButton::callback(
+        "🚫 Заблокировать",
+        Callback::Block { dating_id }.to_string(),
+    )
+}
+
+fn response_buttons(dating_id: i32) -> Vec<InlineKeyboardButton> {
+    vec![
+        InlineKeyboardButton::callback(
+            "💔",
+            Callback::Dating { dating_id, code: RateCode::ResponseDislike }
+                .to_string(),
+        ),
+        InlineKeyboardButton::callback(
+            "❤",
+            Callback::Dating { dating_id, code: RateCode::ResponseLike }
+                .to_string(),
+        ),
+    ]
+}
+
+/// Send the page of the `/likes` inbox: one unanswered like with buttons to
+/// respond and to go to the neighbouring likes
+pub async fn send_incoming_like(
+    bot: &Bot,
+    db: &Database,
+    chat: ChatId,
+    page: u64,
+) -> anyhow::Result<()> {
+    let count = db.count_incoming_likes(chat.0).await?;
+    if count == 0 {
+        bot.send_message(chat, text::NO_INCOMING_LIKES).await?;
+        return Ok(());
+    }
+
+    // Likes could be answered since the page button was sent
+    let page = page.min(count - 1);
+    let dating = db
+        .get_incoming_like(chat.0, page)
+        .await?
+        .context("incoming like not found")?;
+    let initiator = db
+        .get_user(dating.initiator_id)
+        .await?
+        .context("dating initiator not found")?;
+
+    let profile = public_profile(db, &initiator).await?;
+
+    send_user_photos(bot, db, initiator.id, chat.0).await?;
+
+    let navigation = crate::utils::make_pagination_buttons(page, count, |page| {
+        Callback::Likes { page }
+    });
+    let keyboard_markup = InlineKeyboardMarkup::new([
+        response_buttons(dating.id),
+        vec![report_button(dating.id), block_button(dating.id)],
+        navigation,
+    ]);
+
+    let like_msg = dating.initiator_msg.map_or_else(
+        || format!("Лайк {} из {count}:\n\n{profile}", page + 1),
+        |m| {
+            format!(
+                "Лайк {} из {count}, вам оставили \
+                 сообщение:\n{m}\n\n{profile}",
+                page + 1
+            )
+        },
+    );
+    bot.send_message(chat, like_msg)
+        .reply_markup(keyboard_markup)
+        .await?;
+
+    Ok(())
+}
+
+/// Text and buttons of the `/matches` page
+async fn make_match_page(
+    bot: &Bot,
+    db: &Database,
+    chat: ChatId,
+    page: u64,
+) -> anyhow::Result<(String, InlineKeyboardMarkup)> {
+    let count = db.count_matches(chat.0).await?;
+    if count == 0 {
+        return Ok((
+            text::NO_MATCHES.to_owned(),
+            InlineKeyboardMarkup::default(),
+        ));
+    }
+
+    // Old page buttons may point beyond the end
+    let page = page.min(count - 1);
+    let (dating, other) =
+        db.get_match(chat.0, page).await?.context("match not found")?;
+
+    let profile = public_profile(db, &other).await?;
+    let mut msg = format!("Пара {} из {count}:\n\n{profile}", page + 1);
+
+    let mut keyboard = Vec::new();
+    // Usernames change, so the link is made every time
+    match crate::utils::user_url(bot, other.id).await? {
+        Some(url) => {
+            keyboard.push(vec![InlineKeyboardButton::url("Открыть чат", url)]);
+        }
+        None => msg.push_str(text::MATCH_URL_UNAVAILABLE),
+    }
+    keyboard.push(vec![block_button(dating.id)]);
+    keyboard.push(crate::utils::make_pagination_buttons(
+        page,
+        count,
+        |page| Callback::Matches { page },
+    ));
+
+    Ok((msg, InlineKeyboardMarkup::new(keyboard)))
+}
+
+/// Send the page of the `/matches` history
+pub async fn send_match(
+    bot: &Bot,
+    db: &Database,
+    chat: ChatId,
+    page: u64,
+) -> anyhow::Result<()> {
+    let (msg, keyboard_markup) = make_match_page(bot, db, chat, page).await?;
+    bot.send_message(chat, msg).reply_markup(keyboard_markup).await?;
+    Ok(())
+}
+
+/// Turn the `/matches` message to another page
+pub async fn edit_match(
+    bot: &Bot,
+    db: &Database,
+    chat: ChatId,
+    msg_id: MessageId,
+    page: u64,
+) -> anyhow::Result<()> {
+    let (msg, keyboard_markup) = make_match_page(bot, db, chat, page).await?;
+    bot.edit_message_text(chat, msg_id, msg)
+        .reply_markup(keyboard_markup)
+        .await?;
+    Ok(())
+}
+
+/// Answer the dating negatively when the user has blocked or reported the
+/// other one
+pub async fn reject_dating(
+    bot: &Bot,
+    db: &Database,
+    dating: &datings::Model,
+    user: i64,
+) -> anyhow::Result<()> {
+    if dating.initiator_id == user {
+        // It was the recommendation, show the next one
+        if dating.initiator_reaction.is_none() {
+            db.set_dating_initiator_reaction(dating.id, false).await?;
+            send_recommendation(bot, db, ChatId(user)).await?;
+        }
+    } else if dating.partner_reaction.is_none() {
+        // It was the like, remove it from the inbox
+        db.set_dating_partner_reaction(dating.id, false).await?;
+    }
+    Ok(())
+}
+
 pub async fn mutual_like(
     bot: &Bot,
     db: &Database,
@@ -272,9 +515,10 @@ pub async fn mutual_like(
         .await?
         .context("dating partner not found")?;
 
-    let partner_profile: PublicProfile = (&partner).try_into()?;
+    let partner_profile = public_profile(db, &partner).await?;
 
     db.set_dating_partner_reaction(dating.id, true).await?;
+    crate::metrics::MATCHES.inc();
 
     if let Err(e) =
         send_user_photos(bot, db, dating.partner_id, dating.initiator_id).await
@@ -308,29 +552,17 @@ pub async fn mutual_like(
     Ok(())
 }
 
-async fn send_user_photos(
+pub async fn send_user_photos(
     bot: &Bot,
     db: &Database,
     user: i64,
     chat: i64,
 ) -> std::result::Result<(), crate::AppError> {
-    let user_images = db.get_images(user).await?;
-
-    if !user_images.is_empty() {
-        let medias = user_images.into_iter().map(|(id, kind)| {
-            let input_file = InputFile::file_id(id);
-            match kind {
-                ImageKind::Image => {
-                    let input_media_photo = InputMediaPhoto::new(input_file);
-                    InputMedia::Photo(input_media_photo)
-                }
-                ImageKind::Video => {
-                    let input_media_video = InputMediaVideo::new(input_file);
-                    InputMedia::Video(input_media_video)
-                }
-            }
-        });
-        bot.send_media_group(ChatId(chat), medias).await?;
+    let user_images = db.get_user_images(user).await?;
+    let refreshed =
+        crate::photos::send(bot, ChatId(chat), &user_images, false).await?;
+    for (id, telegram_id) in refreshed {
+        db.set_image_telegram_id(id, telegram_id).await?;
     }
     Ok(())
 }
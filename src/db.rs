@@ -1,29 +1,79 @@
+use std::sync::Arc;
+
 use anyhow::{Context, Result};
 use entities::{
     prelude::*,
-    sea_orm_active_enums::{ImageKind, LocationFilter},
+    sea_orm_active_enums::{
+        Campaign, ImageKind, LocationFilter, ReportReason, ReportResolution,
+    },
     *,
 };
+use futures_util::future::BoxFuture;
+use itertools::Itertools;
 use migration::{Migrator, MigratorTrait};
 use sea_orm::{Database as SeaDatabase, DatabaseConnection, *};
 use sea_query::*;
+use teloxide::{dispatching::dialogue::Storage, types::ChatId};
 use tracing::{instrument, log::LevelFilter};
 
+use crate::{
+    broadcasts::{Delivery, Segment},
+    events::NewEvent,
+    olympiads::Achievement,
+    ranking::RankingWeights,
+    types::{academic_year, Course, Stage},
+    State,
+};
+
+#[derive(Debug)]
 pub struct Database {
     conn: DatabaseConnection,
+    ranking: RankingWeights,
+}
+
+/// Numbers for the `/stats` admin command
+#[derive(Debug)]
+pub struct Stats {
+    pub users: u64,
+    pub active_users: u64,
+    /// Users seen in the last week
+    pub recent_users: u64,
+    pub banned_users: u64,
+    pub datings: u64,
+    pub likes: u64,
+    pub matches: u64,
+    pub open_reports: u64,
+}
+
+/// Datings of the user for the `/user` admin command
+#[derive(Debug)]
+pub struct UserStats {
+    /// Recommendations the user has seen
+    pub recommended: u64,
+    pub likes_sent: u64,
+    pub likes_received: u64,
+    pub matches: u64,
 }
 
 impl Database {
     pub async fn new() -> Result<Self> {
-        let db_url = std::env::var("DATABASE_URL")?;
+        Self::connect(std::env::var("DATABASE_URL")?).await
+    }
 
+    pub async fn connect(db_url: String) -> Result<Self> {
         let mut conn_options = ConnectOptions::new(db_url);
         conn_options.sqlx_logging_level(LevelFilter::Debug);
         conn_options.sqlx_logging(true);
 
         let conn = SeaDatabase::connect(conn_options).await?;
         Migrator::up(&conn, None).await?;
-        Ok(Self { conn })
+        Ok(Self { conn, ranking: RankingWeights::from_env()? })
+    }
+
+    #[cfg(test)]
+    #[must_use]
+    pub fn with_ranking(self, ranking: RankingWeights) -> Self {
+        Self { ranking, ..self }
     }
 
     #[instrument(level = "debug", skip(self))]
@@ -43,6 +93,97 @@ impl Database {
         Ok(())
     }
 
+    /// Store the edited profile at once. `images` replace the stored ones in
+    /// their order, new ones have negative ids. `achievements` replace the
+    /// stored ones too. Returns `file_unique_id`s of the deleted images that
+    /// no image has anymore.
+    #[instrument(level = "debug", skip(self))]
+    pub async fn save_profile(
+        &self,
+        settings: crate::UserSettings,
+        images: Option<&[images::Model]>,
+        achievements: Option<&[Achievement]>,
+    ) -> Result<Vec<String>> {
+        let txn = self.conn.begin().await?;
+        let id = settings.id;
+        let user = settings.into_active_model();
+        if Users::find_by_id(id).one(&txn).await?.is_some() {
+            Users::update(user).exec(&txn).await?;
+        } else {
+            Users::insert(user).exec(&txn).await?;
+        }
+
+        let mut unused = Vec::new();
+        if let Some(images) = images {
+            let kept: Vec<_> = images
+                .iter()
+                .map(|image| image.id)
+                .filter(|id| *id > 0)
+                .collect();
+            let deleted = Images::find()
+                .filter(images::Column::UserId.eq(id))
+                .filter(images::Column::Id.is_not_in(kept.clone()))
+                .all(&txn)
+                .await?;
+            Images::delete_many()
+                .filter(images::Column::UserId.eq(id))
+                .filter(images::Column::Id.is_not_in(kept))
+                .exec(&txn)
+                .await?;
+            unused = unused_files(&txn, deleted).await?;
+            for (position, image) in (0i16..).zip(images) {
+                if image.id > 0 {
+                    Images::update_many()
+                        .col_expr(
+                            images::Column::Position,
+                            Expr::value(position),
+                        )
+                        .filter(images::Column::Id.eq(image.id))
+                        .filter(images::Column::UserId.eq(id))
+                        .exec(&txn)
+                        .await?;
+                } else {
+                    images::ActiveModel {
+                        user_id: ActiveValue::Set(id),
+                        telegram_id: ActiveValue::Set(
+                            image.telegram_id.clone(),
+                        ),
+                        kind: ActiveValue::Set(image.kind.clone()),
+                        position: ActiveValue::Set(position),
+                        file_unique_id: ActiveValue::Set(
+                            image.file_unique_id.clone(),
+                        ),
+                        ..Default::default()
+                    }
+                    .insert(&txn)
+                    .await?;
+                }
+            }
+        }
+
+        if let Some(achievements) = achievements {
+            Achievements::delete_many()
+                .filter(achievements::Column::UserId.eq(id))
+                .exec(&txn)
+                .await?;
+            for achievement in achievements {
+                achievements::ActiveModel {
+                    user_id: ActiveValue::Set(id),
+                    olympiad: ActiveValue::Set(achievement.olympiad.into()),
+                    subject: ActiveValue::Set(achievement.subject.into()),
+                    level: ActiveValue::Set(achievement.level.clone()),
+                    year: ActiveValue::Set(achievement.year),
+                    diploma: ActiveValue::Set(achievement.diploma.clone()),
+                    ..Default::default()
+                }
+                .insert(&txn)
+                .await?;
+            }
+        }
+        txn.commit().await?;
+        Ok(unused)
+    }
+
     #[instrument(level = "debug", skip(self))]
     pub async fn get_images(
         &self,
@@ -55,6 +196,8 @@ impl Database {
         }
         Ok(images::Entity::find()
             .filter(images::Column::UserId.eq(user_id))
+            .order_by_asc(images::Column::Position)
+            .order_by_asc(images::Column::Id)
             .select_only()
             .columns([images::Column::TelegramId, images::Column::Kind])
             .into_model::<ImageTelegramId>()
@@ -65,6 +208,21 @@ impl Database {
             .collect())
     }
 
+    /// Images of the user in the order of the profile
+    #[instrument(level = "debug", skip(self))]
+    pub async fn get_user_images(
+        &self,
+        user_id: i64,
+    ) -> Result<Vec<images::Model>> {
+        Ok(Images::find()
+            .filter(images::Column::UserId.eq(user_id))
+            .order_by_asc(images::Column::Position)
+            .order_by_asc(images::Column::Id)
+            .all(&self.conn)
+            .await?)
+    }
+
+    /// Add the image after the existing ones
     #[instrument(level = "debug", skip(self))]
     pub async fn create_image(
         &self,
@@ -72,35 +230,111 @@ impl Database {
         tg_id: String,
         kind: ImageKind,
     ) -> Result<()> {
+        let count = Images::find()
+            .filter(images::Column::UserId.eq(user_id))
+            .count(&self.conn)
+            .await?;
         let image = entities::images::ActiveModel {
             user_id: ActiveValue::Set(user_id),
             telegram_id: ActiveValue::Set(tg_id),
             kind: ActiveValue::Set(kind),
+            position: ActiveValue::Set(count.try_into()?),
             ..Default::default()
         };
         Images::insert(image).exec(&self.conn).await?;
         Ok(())
     }
 
+    /// Replace the Telegram id of the image, e.g. after it was uploaded again
+    #[instrument(level = "debug", skip(self))]
+    pub async fn set_image_telegram_id(
+        &self,
+        id: i32,
+        telegram_id: String,
+    ) -> Result<()> {
+        Images::update_many()
+            .col_expr(images::Column::TelegramId, Expr::value(telegram_id))
+            .filter(images::Column::Id.eq(id))
+            .exec(&self.conn)
+            .await?;
+        Ok(())
+    }
+
     #[instrument(level = "debug", skip(self))]
     pub async fn create_state(&self, id: i64) -> Result<()> {
         if States::find_by_id(id).one(&self.conn).await?.is_none() {
-            let state =
-                entities::states::ActiveModel { id: ActiveValue::Set(id) };
+            let state = entities::states::ActiveModel {
+                id: ActiveValue::Set(id),
+                ..Default::default()
+            };
             States::insert(state).exec(&self.conn).await?;
         }
         Ok(())
     }
 
     #[instrument(level = "debug", skip(self))]
-    pub async fn clean_images(&self, user_id: i64) -> Result<()> {
-        Images::delete_many()
-            .filter(images::Column::UserId.eq(user_id))
+    pub async fn load_dialogue(
+        &self,
+        id: i64,
+    ) -> Result<Option<serde_json::Value>> {
+        Ok(States::find_by_id(id)
+            .one(&self.conn)
+            .await?
+            .and_then(|s| s.dialogue))
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    pub async fn save_dialogue(
+        &self,
+        id: i64,
+        dialogue: Option<serde_json::Value>,
+    ) -> Result<()> {
+        let state = entities::states::ActiveModel {
+            id: ActiveValue::Set(id),
+            dialogue: ActiveValue::Set(dialogue),
+            ..Default::default()
+        };
+        States::insert(state)
+            .on_conflict(
+                OnConflict::column(states::Column::Id)
+                    .update_column(states::Column::Dialogue)
+                    .to_owned(),
+            )
             .exec(&self.conn)
             .await?;
         Ok(())
     }
 
+    /// Delete all images of the user, returns `file_unique_id`s that no image
+    /// has anymore
+    #[instrument(level = "debug", skip(self))]
+    pub async fn clean_images(&self, user_id: i64) -> Result<Vec<String>> {
+        let txn = self.conn.begin().await?;
+        let deleted = Images::find()
+            .filter(images::Column::UserId.eq(user_id))
+            .all(&txn)
+            .await?;
+        Images::delete_many()
+            .filter(images::Column::UserId.eq(user_id))
+            .exec(&txn)
+            .await?;
+        let unused = unused_files(&txn, deleted).await?;
+        txn.commit().await?;
+        Ok(unused)
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    pub async fn get_achievements(
+        &self,
+        user_id: i64,
+    ) -> Result<Vec<achievements::Model>> {
+        Ok(Achievements::find()
+            .filter(achievements::Column::UserId.eq(user_id))
+            .order_by_asc(achievements::Column::Id)
+            .all(&self.conn)
+            .await?)
+    }
+
     #[instrument(level = "debug", skip(self))]
     pub async fn get_user(&self, id: i64) -> Result<Option<users::Model>> {
         Ok(Users::find_by_id(id).one(&self.conn).await?)
@@ -133,13 +367,21 @@ impl Database {
         user_id: i64,
     ) -> Result<Option<(datings::Model, users::Model)>> {
         // Load dating initiator
-        let user = Users::find_by_id(user_id)
+        let mut user = Users::find_by_id(user_id)
             .one(&self.conn)
             .await?
             .context("user not found")?;
 
         self.update_last_activity(user_id).await?;
 
+        // Users at an event are matched as if they were in its city, and
+        // those at the same event meet regardless of location filters
+        let event = self.get_user_event(&user).await?;
+        if let Some(city) = event.as_ref().and_then(|e| e.city) {
+            user.city = Some(city);
+        }
+        let same_event = event.map(|e| users::Column::EventId.eq(e.id));
+
         // TODO: fix this
         let user_id_clone = user.id;
 
@@ -150,22 +392,55 @@ impl Database {
             .await?;
 
         if let Some(dating) = last_unresponded_dating {
+            // The same conditions as for a new recommendation
             let partner = Users::find_by_id(dating.partner_id)
+                .filter(not_blocked(
+                    Expr::value(user_id),
+                    users::Column::Id.into_expr().into(),
+                ))
+                .filter(users::Column::Active.eq(true))
+                .filter(users::Column::Banned.eq(false))
                 .one(&self.conn)
-                .await?
-                .context("partner not found")?;
-            return Ok(Some((dating, partner)));
+                .await?;
+            if let Some(partner) = partner {
+                return Ok(Some((dating, partner)));
+            }
+            // The partner became unavailable after being recommended
+            self.set_dating_initiator_reaction(dating.id, false).await?;
         }
 
         let mut partner_query = Users::find()
             // Don't recommend user to himself
             .filter(users::Column::Id.ne(user_id))
+            // Don't recommend blocked users and users who blocked the user
+            .filter(not_blocked(
+                Expr::value(user_id),
+                users::Column::Id.into_expr().into(),
+            ))
             // Only recommend activated profiles
             .filter(users::Column::Active.eq(true))
+            .filter(users::Column::Banned.eq(false))
             // Only recommend active users
             .filter(users::Column::LastActivity.into_expr().gt(
                 Expr::current_timestamp().sub(Expr::cust("interval '14 days'")),
             ))
+            // School students only meet university students if they opted
+            // in, and never meet those who don't study anymore
+            .filter(match Stage::from(&user) {
+                Stage::School(_) if user.show_university => Condition::any()
+                    .add(is_school(academic_year()))
+                    .add(is_university(academic_year())),
+                Stage::School(_) => is_school(academic_year()).into_condition(),
+                Stage::University(_) => Condition::any()
+                    .add(is_school(academic_year()).not())
+                    .add(
+                        is_school(academic_year())
+                            .and(users::Column::ShowUniversity.eq(true)),
+                    ),
+                Stage::Graduate(_) => {
+                    is_school(academic_year()).not().into_condition()
+                }
+            })
             // Respect users's graduation delta preference
             .filter(users::Column::GraduationYear.between(
                 user.graduation_year - user.grade_up_filter,
@@ -208,17 +483,11 @@ impl Database {
             // Respect partner's subject preference
             .filter(
                 Condition::any()
-                    .add(
-                        Expr::cust_with_exprs("$1 & $2", [
-                            users::Column::SubjectsFilter
-                                .into_expr()
-                                .cast_as(Alias::new("bit(32)")),
-                            Expr::value(user.subjects)
-                                .cast_as(Alias::new("bit(32)")),
-                        ])
-                        .ne(Expr::value(0i32).cast_as(Alias::new("bit(32)"))),
-                    )
-                    .add(users::Column::SubjectsFilter.eq(0i32)),
+                    .add(overlap(
+                        users::Column::SubjectsFilter.into_expr(),
+                        Expr::value(user.subjects.clone()),
+                    ))
+                    .add(users::Column::SubjectsFilter.eq(Vec::<i32>::new())),
             )
             // Respect partner's gender preference
             .filter(
@@ -229,6 +498,15 @@ impl Database {
                             .eq(Some(user.gender.clone())),
                     ),
             )
+            // Respect partner's achievement filter
+            .filter(
+                Condition::any()
+                    .add(users::Column::AchievementFilter.is_null())
+                    .add(has_achievement(
+                        Expr::value(user_id),
+                        users::Column::AchievementFilter.into_expr().into(),
+                    )),
+            )
             // Respect partner's location filter
             .filter(
                 Condition::any()
@@ -245,8 +523,7 @@ impl Database {
                                     .eq(LocationFilter::SameCounty),
                             )
                             .add(
-                                users::Column::City
-                                    .into_expr()
+                                user_city()
                                     .binary(BinOper::RShift, 16)
                                     .eq(user.city.unwrap_or(0) >> 16),
                             ),
@@ -259,8 +536,7 @@ impl Database {
                                     .eq(LocationFilter::SameSubject),
                             )
                             .add(
-                                users::Column::City
-                                    .into_expr()
+                                user_city()
                                     .binary(BinOper::RShift, 8)
                                     .binary(BinOper::Mod, 2i32.pow(8))
                                     .eq((user.city.unwrap_or(0) >> 8)
@@ -268,7 +544,8 @@ impl Database {
                             ),
                     )
                     // SameCity
-                    .add(users::Column::City.eq(user.city)),
+                    .add(user_city().eq(user.city))
+                    .add_option(same_event.clone()),
             )
             // Don't recommend the same partner more than once a week
             .join_rev(
@@ -300,21 +577,15 @@ impl Database {
             )
             .group_by(users::Column::Id)
             .having(datings::Column::Id.count().eq(0))
-            // Get random partner
-            .order_by_desc(SimpleExpr::from(Func::random()));
+            // Get the most relevant partner
+            .order_by_desc(self.ranking.score(&user));
 
         // Respect user's subject preference
-        if user.subjects_filter != 0 {
-            partner_query = partner_query.filter(
-                Expr::cust_with_exprs("$1 & $2", [
-                    users::Column::Subjects
-                        .into_expr()
-                        .cast_as(Alias::new("bit(32)")),
-                    Expr::value(user.subjects_filter)
-                        .cast_as(Alias::new("bit(32)")),
-                ])
-                .ne(Expr::value(0i32).cast_as(Alias::new("bit(32)"))),
-            );
+        if !user.subjects_filter.is_empty() {
+            partner_query = partner_query.filter(overlap(
+                users::Column::Subjects.into_expr(),
+                Expr::value(user.subjects_filter.clone()),
+            ));
         }
 
         // Respect user's gender preference
@@ -323,28 +594,36 @@ impl Database {
                 partner_query.filter(users::Column::Gender.eq(Some(g.clone())));
         }
 
+        // Respect user's achievement filter
+        if let Some(level) = &user.achievement_filter {
+            partner_query = partner_query.filter(has_achievement(
+                users::Column::Id.into_expr().into(),
+                Expr::value(level.clone())
+                    .cast_as(Alias::new("olympiad_level")),
+            ));
+        }
+
         // Respect user's location filter
-        partner_query = match user.location_filter {
-            LocationFilter::SameCountry => partner_query, // Just match
-            // everything
-            LocationFilter::SameCounty => partner_query.filter(
-                users::Column::City
-                    .into_expr()
+        let location = match user.location_filter {
+            LocationFilter::SameCountry => None, // Just match everything
+            LocationFilter::SameCounty => Some(
+                user_city()
                     .binary(BinOper::RShift, 16)
                     .eq(user.city.context("user city must be set")? >> 16),
             ),
-            LocationFilter::SameSubject => partner_query.filter(
-                users::Column::City
-                    .into_expr()
+            LocationFilter::SameSubject => Some(
+                user_city()
                     .binary(BinOper::RShift, 8)
                     .binary(BinOper::Mod, 2i32.pow(8))
                     .eq((user.city.context("user city must be set")? >> 8)
                         % 2i32.pow(8)),
             ),
-            LocationFilter::SameCity => {
-                partner_query.filter(users::Column::City.eq(user.city))
-            }
+            LocationFilter::SameCity => Some(user_city().eq(user.city)),
         };
+        if let Some(location) = location {
+            partner_query = partner_query
+                .filter(Condition::any().add(location).add_option(same_event));
+        }
 
         let txn = self.conn.begin().await?;
 
@@ -391,6 +670,21 @@ impl Database {
         Ok(())
     }
 
+    /// Keep the message left with the like to show it in the `/likes` inbox
+    #[instrument(level = "debug", skip(self))]
+    pub async fn set_dating_like_msg(
+        &self,
+        dating: i32,
+        msg: String,
+    ) -> Result<()> {
+        Datings::update_many()
+            .filter(datings::Column::Id.eq(dating))
+            .col_expr(datings::Column::InitiatorMsg, Expr::value(msg))
+            .exec(&self.conn)
+            .await?;
+        Ok(())
+    }
+
     #[instrument(level = "debug", skip(self))]
     pub async fn set_dating_partner_reaction(
         &self,
@@ -405,6 +699,753 @@ impl Database {
         Ok(())
     }
 
+    /// Likes the user hasn't responded to yet, oldest first
+    fn incoming_likes(user_id: i64) -> Select<Datings> {
+        Datings::find()
+            .filter(datings::Column::PartnerId.eq(user_id))
+            .filter(datings::Column::InitiatorReaction.eq(true))
+            .filter(datings::Column::PartnerReaction.is_null())
+            .filter(not_blocked(
+                datings::Column::InitiatorId.into_expr().into(),
+                datings::Column::PartnerId.into_expr().into(),
+            ))
+            // Likes of banned users are hidden
+            .filter(not_banned(
+                datings::Column::InitiatorId.into_expr().into(),
+            ))
+            .order_by_asc(datings::Column::Id)
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    pub async fn count_incoming_likes(&self, user_id: i64) -> Result<u64> {
+        Ok(Self::incoming_likes(user_id).count(&self.conn).await?)
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    pub async fn get_incoming_like(
+        &self,
+        user_id: i64,
+        page: u64,
+    ) -> Result<Option<datings::Model>> {
+        Ok(Self::incoming_likes(user_id)
+            .offset(page)
+            .one(&self.conn)
+            .await?)
+    }
+
+    /// Datings of the user with mutual likes, newest first
+    fn matches(user_id: i64) -> Select<Datings> {
+        Datings::find()
+            // Matches with banned users are hidden
+            .filter(
+                Condition::any()
+                    .add(datings::Column::InitiatorId.eq(user_id).and(
+                        not_banned(
+                            datings::Column::PartnerId.into_expr().into(),
+                        ),
+                    ))
+                    .add(datings::Column::PartnerId.eq(user_id).and(
+                        not_banned(
+                            datings::Column::InitiatorId.into_expr().into(),
+                        ),
+                    )),
+            )
+            .filter(datings::Column::InitiatorReaction.eq(true))
+            .filter(datings::Column::PartnerReaction.eq(true))
+            // Blocking someone unmatches them
+            .filter(not_blocked(
+                datings::Column::InitiatorId.into_expr().into(),
+                datings::Column::PartnerId.into_expr().into(),
+            ))
+            .order_by_desc(datings::Column::Id)
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    pub async fn count_matches(&self, user_id: i64) -> Result<u64> {
+        Ok(Self::matches(user_id).count(&self.conn).await?)
+    }
+
+    /// Match on the page and the other user of it
+    #[instrument(level = "debug", skip(self))]
+    pub async fn get_match(
+        &self,
+        user_id: i64,
+        page: u64,
+    ) -> Result<Option<(datings::Model, users::Model)>> {
+        let Some(dating) =
+            Self::matches(user_id).offset(page).one(&self.conn).await?
+        else {
+            return Ok(None);
+        };
+
+        let other_id = if dating.initiator_id == user_id {
+            dating.partner_id
+        } else {
+            dating.initiator_id
+        };
+        let other = Users::find_by_id(other_id)
+            .one(&self.conn)
+            .await?
+            .context("matched user not found")?;
+        Ok(Some((dating, other)))
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    pub async fn block_user(&self, blocker: i64, blocked: i64) -> Result<()> {
+        let block = blocks::ActiveModel {
+            blocker_id: ActiveValue::Set(blocker),
+            blocked_id: ActiveValue::Set(blocked),
+            ..Default::default()
+        };
+        Blocks::insert(block)
+            .on_conflict(
+                OnConflict::columns([
+                    blocks::Column::BlockerId,
+                    blocks::Column::BlockedId,
+                ])
+                .do_nothing()
+                .to_owned(),
+            )
+            .exec_without_returning(&self.conn)
+            .await?;
+        Ok(())
+    }
+
+    /// One of the users has blocked the other
+    #[instrument(level = "debug", skip(self))]
+    pub async fn is_blocked(&self, first: i64, second: i64) -> Result<bool> {
+        let blocks = Blocks::find()
+            .filter(
+                Condition::any()
+                    .add(
+                        blocks::Column::BlockerId
+                            .eq(first)
+                            .and(blocks::Column::BlockedId.eq(second)),
+                    )
+                    .add(
+                        blocks::Column::BlockerId
+                            .eq(second)
+                            .and(blocks::Column::BlockedId.eq(first)),
+                    ),
+            )
+            .count(&self.conn)
+            .await?;
+        Ok(blocks > 0)
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    pub async fn count_active_users(&self) -> Result<u64> {
+        Ok(Users::find()
+            .filter(users::Column::Active.eq(true))
+            .count(&self.conn)
+            .await?)
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    pub async fn get_stats(&self) -> Result<Stats> {
+        let likes =
+            Datings::find().filter(datings::Column::InitiatorReaction.eq(true));
+        Ok(Stats {
+            users: Users::find().count(&self.conn).await?,
+            active_users: self.count_active_users().await?,
+            recent_users: Users::find()
+                .filter(users::Column::LastActivity.into_expr().gt(
+                    Expr::current_timestamp()
+                        .sub(Expr::cust("interval '7 days'")),
+                ))
+                .count(&self.conn)
+                .await?,
+            banned_users: Users::find()
+                .filter(users::Column::Banned.eq(true))
+                .count(&self.conn)
+                .await?,
+            datings: Datings::find().count(&self.conn).await?,
+            likes: likes.clone().count(&self.conn).await?,
+            matches: likes
+                .filter(datings::Column::PartnerReaction.eq(true))
+                .count(&self.conn)
+                .await?,
+            open_reports: Reports::find()
+                .filter(reports::Column::Resolution.is_null())
+                .count(&self.conn)
+                .await?,
+        })
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    pub async fn get_user_stats(&self, id: i64) -> Result<UserStats> {
+        let sent = Datings::find().filter(datings::Column::InitiatorId.eq(id));
+        Ok(UserStats {
+            recommended: sent.clone().count(&self.conn).await?,
+            likes_sent: sent
+                .filter(datings::Column::InitiatorReaction.eq(true))
+                .count(&self.conn)
+                .await?,
+            likes_received: Datings::find()
+                .filter(datings::Column::PartnerId.eq(id))
+                .filter(datings::Column::InitiatorReaction.eq(true))
+                .count(&self.conn)
+                .await?,
+            matches: self.count_matches(id).await?,
+        })
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    pub async fn create_report(
+        &self,
+        reporter: i64,
+        reported: i64,
+        reason: ReportReason,
+        text: String,
+    ) -> Result<reports::Model> {
+        let report = reports::ActiveModel {
+            reporter_id: ActiveValue::Set(reporter),
+            reported_id: ActiveValue::Set(reported),
+            reason: ActiveValue::Set(reason),
+            text: ActiveValue::Set(text),
+            ..Default::default()
+        };
+        Ok(report.insert(&self.conn).await?)
+    }
+
+    /// Resolve the report unless it's already resolved, `None` then
+    #[instrument(level = "debug", skip(self))]
+    pub async fn resolve_report(
+        &self,
+        id: i32,
+        resolution: ReportResolution,
+    ) -> Result<Option<reports::Model>> {
+        let updated = Reports::update_many()
+            .col_expr(reports::Column::Resolution, resolution.as_enum())
+            .filter(reports::Column::Id.eq(id))
+            .filter(reports::Column::Resolution.is_null())
+            .exec(&self.conn)
+            .await?;
+        if updated.rows_affected == 0 {
+            return Ok(None);
+        }
+        Ok(Reports::find_by_id(id).one(&self.conn).await?)
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    pub async fn is_banned(&self, id: i64) -> Result<bool> {
+        Ok(self.get_user(id).await?.is_some_and(|u| u.banned))
+    }
+
+    /// Banned users are deactivated and can't activate their profile
+    #[instrument(level = "debug", skip(self))]
+    pub async fn set_banned(&self, id: i64, banned: bool) -> Result<()> {
+        let mut update = Users::update_many()
+            .col_expr(users::Column::Banned, Expr::value(banned))
+            .filter(users::Column::Id.eq(id));
+        if banned {
+            update = update.col_expr(users::Column::Active, Expr::value(false));
+        }
+        update.exec(&self.conn).await?;
+        Ok(())
+    }
+
+    /// Active users with at least `threshold` unanswered likes, some of them
+    /// newer than the last reminder, who weren't reminded in the last
+    /// `interval_hours`, with the number of likes and the newest like
+    #[instrument(level = "debug", skip(self))]
+    pub async fn get_users_to_remind_likes(
+        &self,
+        threshold: i64,
+        interval_hours: i32,
+    ) -> Result<Vec<(i64, i64, i32)>> {
+        Ok(Users::find()
+            .select_only()
+            .column(users::Column::Id)
+            .column_as(datings::Column::Id.count(), "likes")
+            .column_as(datings::Column::Id.max(), "last_like")
+            .join_rev(
+                JoinType::InnerJoin,
+                datings::Entity::belongs_to(users::Entity)
+                    .from(datings::Column::PartnerId)
+                    .to(users::Column::Id)
+                    .into(),
+            )
+            .filter(datings::Column::InitiatorReaction.eq(true))
+            .filter(datings::Column::PartnerReaction.is_null())
+            // The same likes as in the `/likes` inbox
+            .filter(not_blocked(
+                datings::Column::InitiatorId.into_expr().into(),
+                datings::Column::PartnerId.into_expr().into(),
+            ))
+            .filter(not_banned(
+                datings::Column::InitiatorId.into_expr().into(),
+            ))
+            .filter(users::Column::Active.eq(true))
+            .filter(
+                Condition::any()
+                    .add(users::Column::LastLikesReminder.is_null())
+                    .add(users::Column::LastLikesReminder.into_expr().lt(
+                        Expr::current_timestamp().sub(Expr::cust_with_values(
+                            "$1 * interval '1 hour'",
+                            [interval_hours],
+                        )),
+                    )),
+            )
+            .group_by(users::Column::Id)
+            .having(
+                datings::Column::Id
+                    .count()
+                    .binary(BinOper::GreaterThanOrEqual, threshold),
+            )
+            .having(datings::Column::Id.max().binary(
+                BinOper::GreaterThan,
+                Func::coalesce([
+                    users::Column::LastRemindedLike.into_expr().into(),
+                    Expr::val(0).into(),
+                ]),
+            ))
+            .into_tuple()
+            .all(&self.conn)
+            .await?)
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    pub async fn set_likes_reminded(
+        &self,
+        user_id: i64,
+        last_like: i32,
+    ) -> Result<()> {
+        Users::update_many()
+            .col_expr(
+                users::Column::LastLikesReminder,
+                Expr::current_timestamp().into(),
+            )
+            .col_expr(users::Column::LastRemindedLike, Expr::value(last_like))
+            .filter(users::Column::Id.eq(user_id))
+            .exec(&self.conn)
+            .await?;
+        Ok(())
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    pub async fn create_broadcast(
+        &self,
+        author_id: i64,
+        text: String,
+        segment: Segment,
+    ) -> Result<broadcasts::Model> {
+        let broadcast = broadcasts::ActiveModel {
+            author_id: ActiveValue::Set(author_id),
+            text: ActiveValue::Set(text),
+            only_active: ActiveValue::Set(segment.only_active),
+            county: ActiveValue::Set(segment.county),
+            region: ActiveValue::Set(segment.region),
+            graduation_year: ActiveValue::Set(segment.graduation_year),
+            subjects: ActiveValue::Set(segment.subjects.map(Into::into)),
+            ..Default::default()
+        };
+        Ok(broadcast.insert(&self.conn).await?)
+    }
+
+    /// Users in the broadcast's segment who haven't received it yet, in
+    /// the order of sending
+    fn broadcast_recipients(broadcast: &broadcasts::Model) -> Select<Users> {
+        let mut query = Users::find()
+            .filter(users::Column::Id.gt(broadcast.last_user_id))
+            .filter(users::Column::Banned.eq(false))
+            .filter(users::Column::BroadcastOptOut.eq(false))
+            .order_by_asc(users::Column::Id);
+        if broadcast.only_active {
+            query = query.filter(users::Column::Active.eq(true));
+        }
+        if let Some(county) = broadcast.county {
+            query = query.filter(
+                users::Column::City
+                    .into_expr()
+                    .binary(BinOper::RShift, 16)
+                    .eq(county),
+            );
+        }
+        if let Some(region) = broadcast.region {
+            query = query.filter(
+                users::Column::City
+                    .into_expr()
+                    .binary(BinOper::RShift, 8)
+                    .binary(BinOper::Mod, 2i32.pow(8))
+                    .eq(region),
+            );
+        }
+        if let Some(year) = broadcast.graduation_year {
+            query = query.filter(users::Column::GraduationYear.eq(year));
+        }
+        if let Some(subjects) = &broadcast.subjects {
+            query = query.filter(overlap(
+                users::Column::Subjects.into_expr(),
+                Expr::value(subjects.clone()),
+            ));
+        }
+        query
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    pub async fn count_broadcast_recipients(
+        &self,
+        broadcast: &broadcasts::Model,
+    ) -> Result<u64> {
+        Ok(Self::broadcast_recipients(broadcast).count(&self.conn).await?)
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    pub async fn get_broadcast_recipients(
+        &self,
+        broadcast: &broadcasts::Model,
+        limit: u64,
+    ) -> Result<Vec<i64>> {
+        Ok(Self::broadcast_recipients(broadcast)
+            .select_only()
+            .column(users::Column::Id)
+            .limit(limit)
+            .into_tuple()
+            .all(&self.conn)
+            .await?)
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    pub async fn get_unfinished_broadcasts(
+        &self,
+    ) -> Result<Vec<broadcasts::Model>> {
+        Ok(Broadcasts::find()
+            .filter(broadcasts::Column::Finished.is_null())
+            .order_by_asc(broadcasts::Column::Id)
+            .all(&self.conn)
+            .await?)
+    }
+
+    /// Remember the last user the broadcast was sent to and count the
+    /// delivery
+    #[instrument(level = "debug", skip(self))]
+    pub async fn update_broadcast_progress(
+        &self,
+        id: i32,
+        last_user_id: i64,
+        delivery: Delivery,
+    ) -> Result<()> {
+        let counter = delivery.column();
+        Broadcasts::update_many()
+            .col_expr(broadcasts::Column::LastUserId, Expr::value(last_user_id))
+            .col_expr(counter, counter.into_expr().add(1))
+            .filter(broadcasts::Column::Id.eq(id))
+            .exec(&self.conn)
+            .await?;
+        Ok(())
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    pub async fn finish_broadcast(&self, id: i32) -> Result<broadcasts::Model> {
+        Broadcasts::update_many()
+            .col_expr(
+                broadcasts::Column::Finished,
+                Expr::current_timestamp().into(),
+            )
+            .filter(broadcasts::Column::Id.eq(id))
+            .exec(&self.conn)
+            .await?;
+        Broadcasts::find_by_id(id)
+            .one(&self.conn)
+            .await?
+            .context("broadcast not found")
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    pub async fn set_broadcast_opt_out(
+        &self,
+        user_id: i64,
+        opt_out: bool,
+    ) -> Result<()> {
+        Users::update_many()
+            .col_expr(users::Column::BroadcastOptOut, Expr::value(opt_out))
+            .filter(users::Column::Id.eq(user_id))
+            .exec(&self.conn)
+            .await?;
+        Ok(())
+    }
+
+    /// Active users who were last seen between `inactive_days` and
+    /// `deactivate_days` ago
+    #[instrument(level = "debug", skip(self))]
+    pub async fn get_inactive_users(
+        &self,
+        inactive_days: i32,
+        deactivate_days: i32,
+        cooldown_days: i32,
+    ) -> Result<Vec<users::Model>> {
+        Ok(Users::find()
+            .filter(users::Column::Active.eq(true))
+            .filter(
+                users::Column::LastActivity
+                    .into_expr()
+                    .lt(days_ago(inactive_days)),
+            )
+            .filter(
+                users::Column::LastActivity
+                    .into_expr()
+                    .gte(days_ago(deactivate_days)),
+            )
+            .filter(user_cooldown_passed(Campaign::Inactive, cooldown_days))
+            .all(&self.conn)
+            .await?)
+    }
+
+    /// Active profiles created in the user's county since the user was
+    /// last seen
+    #[instrument(level = "debug", skip(self))]
+    pub async fn count_new_users_near(
+        &self,
+        user: &users::Model,
+    ) -> Result<u64> {
+        let mut query = Users::find()
+            .filter(users::Column::Id.ne(user.id))
+            .filter(users::Column::Active.eq(true))
+            .filter(users::Column::Created.gt(user.last_activity));
+        if let Some(city) = user.city {
+            query = query.filter(
+                users::Column::City
+                    .into_expr()
+                    .binary(BinOper::RShift, 16)
+                    .eq(city >> 16),
+            );
+        }
+        Ok(query.count(&self.conn).await?)
+    }
+
+    /// Private chats that started the bot `unfinished_days` ago, but have no
+    /// profile and weren't reminded to create it yet
+    #[instrument(level = "debug", skip(self))]
+    pub async fn get_unfinished_users(
+        &self,
+        unfinished_days: i32,
+    ) -> Result<Vec<i64>> {
+        Ok(States::find()
+            .select_only()
+            .column(states::Column::Id)
+            // Group chats, like the admin one, have negative ids
+            .filter(states::Column::Id.gt(0))
+            .filter(
+                states::Column::Created
+                    .into_expr()
+                    .lt(days_ago(unfinished_days)),
+            )
+            .filter(
+                Expr::exists(
+                    Query::select()
+                        .expr(Expr::value(1))
+                        .from(Users)
+                        .and_where(
+                            Expr::col((Users, users::Column::Id))
+                                .equals((States, states::Column::Id)),
+                        )
+                        .to_owned(),
+                )
+                .not(),
+            )
+            .filter(
+                delivered(
+                    Expr::col((States, states::Column::Id)).into(),
+                    Campaign::Unfinished,
+                )
+                .not(),
+            )
+            .into_tuple()
+            .all(&self.conn)
+            .await?)
+    }
+
+    /// Active users who haven't been seen for `deactivate_days`
+    #[instrument(level = "debug", skip(self))]
+    pub async fn get_silent_users(
+        &self,
+        deactivate_days: i32,
+        cooldown_days: i32,
+    ) -> Result<Vec<i64>> {
+        Ok(Users::find()
+            .select_only()
+            .column(users::Column::Id)
+            .filter(users::Column::Active.eq(true))
+            .filter(
+                users::Column::LastActivity
+                    .into_expr()
+                    .lt(days_ago(deactivate_days)),
+            )
+            .filter(user_cooldown_passed(Campaign::Deactivated, cooldown_days))
+            .into_tuple()
+            .all(&self.conn)
+            .await?)
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    pub async fn set_campaign_delivered(
+        &self,
+        user_id: i64,
+        campaign: Campaign,
+    ) -> Result<()> {
+        let delivery = campaign_deliveries::ActiveModel {
+            user_id: ActiveValue::Set(user_id),
+            campaign: ActiveValue::Set(campaign),
+            ..Default::default()
+        };
+        CampaignDeliveries::insert(delivery)
+            .on_conflict(
+                OnConflict::columns([
+                    campaign_deliveries::Column::UserId,
+                    campaign_deliveries::Column::Campaign,
+                ])
+                .value(
+                    campaign_deliveries::Column::Time,
+                    Expr::current_timestamp(),
+                )
+                .to_owned(),
+            )
+            .exec(&self.conn)
+            .await?;
+        Ok(())
+    }
+
+    /// Active students who haven't confirmed their grade or course in this
+    /// academic year and weren't asked yet. Graduates of the previous years
+    /// have nothing to confirm.
+    #[instrument(level = "debug", skip(self))]
+    pub async fn get_users_to_check_grade(
+        &self,
+        academic_year: i16,
+    ) -> Result<Vec<users::Model>> {
+        Ok(Users::find()
+            .filter(users::Column::Active.eq(true))
+            .filter(users::Column::GradeConfirmed.lt(academic_year))
+            // Those who have just finished school are asked too
+            .filter(
+                Condition::any()
+                    .add(users::Column::GraduationYear.gte(academic_year))
+                    .add(is_university(academic_year)),
+            )
+            .filter(
+                delivered_since(
+                    Expr::col((Users, users::Column::Id)).into(),
+                    Campaign::GradeCheck,
+                    academic_year_start(academic_year),
+                )
+                .not(),
+            )
+            .all(&self.conn)
+            .await?)
+    }
+
+    /// Active users who were asked to confirm their grade more than
+    /// `deadline_days` ago and didn't answer
+    #[instrument(level = "debug", skip(self))]
+    pub async fn get_users_missed_grade_check(
+        &self,
+        academic_year: i16,
+        deadline_days: i32,
+    ) -> Result<Vec<i64>> {
+        let id: SimpleExpr = Expr::col((Users, users::Column::Id)).into();
+        Ok(Users::find()
+            .select_only()
+            .column(users::Column::Id)
+            .filter(users::Column::Active.eq(true))
+            .filter(users::Column::GradeConfirmed.lt(academic_year))
+            .filter(delivered_since(
+                id.clone(),
+                Campaign::GradeCheck,
+                academic_year_start(academic_year),
+            ))
+            .filter(cooldown_passed(id, Campaign::GradeCheck, deadline_days))
+            .into_tuple()
+            .all(&self.conn)
+            .await?)
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    pub async fn set_grade_confirmed(
+        &self,
+        user_id: i64,
+        academic_year: i16,
+    ) -> Result<()> {
+        Users::update_many()
+            .col_expr(users::Column::GradeConfirmed, Expr::value(academic_year))
+            .filter(users::Column::Id.eq(user_id))
+            .exec(&self.conn)
+            .await?;
+        Ok(())
+    }
+
+    /// Events going on today
+    #[instrument(level = "debug", skip(self))]
+    pub async fn get_current_events(&self) -> Result<Vec<events::Model>> {
+        Ok(Events::find()
+            .filter(is_going_on())
+            .order_by_asc(events::Column::Ends)
+            .all(&self.conn)
+            .await?)
+    }
+
+    /// Events which haven't ended yet
+    #[instrument(level = "debug", skip(self))]
+    pub async fn get_upcoming_events(&self) -> Result<Vec<events::Model>> {
+        Ok(Events::find()
+            .filter(events::Column::Ends.into_expr().gte(Expr::current_date()))
+            .order_by_asc(events::Column::Starts)
+            .all(&self.conn)
+            .await?)
+    }
+
+    /// Event the user is at, if it's going on
+    #[instrument(level = "debug", skip(self))]
+    pub async fn get_user_event(
+        &self,
+        user: &users::Model,
+    ) -> Result<Option<events::Model>> {
+        let Some(id) = user.event_id else {
+            return Ok(None);
+        };
+        Ok(Events::find_by_id(id)
+            .filter(is_going_on())
+            .one(&self.conn)
+            .await?)
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    pub async fn create_event(
+        &self,
+        event: NewEvent,
+    ) -> Result<events::Model> {
+        let event = events::ActiveModel {
+            name: ActiveValue::Set(event.name),
+            city: ActiveValue::Set(event.city),
+            starts: ActiveValue::Set(event.starts),
+            ends: ActiveValue::Set(event.ends),
+            ..Default::default()
+        };
+        Ok(event.insert(&self.conn).await?)
+    }
+
+    /// Delete the event, returns whether it existed. Users at the event
+    /// lose the status.
+    #[instrument(level = "debug", skip(self))]
+    pub async fn delete_event(&self, id: i32) -> Result<bool> {
+        let result = Events::delete_by_id(id).exec(&self.conn).await?;
+        Ok(result.rows_affected > 0)
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    pub async fn set_user_event(
+        &self,
+        user_id: i64,
+        event_id: Option<i32>,
+    ) -> Result<()> {
+        Users::update_many()
+            .col_expr(users::Column::EventId, Expr::value(event_id))
+            .filter(users::Column::Id.eq(user_id))
+            .exec(&self.conn)
+            .await?;
+        Ok(())
+    }
+
     #[instrument(level = "debug", skip(self))]
     pub async fn set_dating_initiator_msg(
         &self,
@@ -419,3 +1460,235 @@ impl Database {
         Ok(())
     }
 }
+
+/// Time `days` ago
+fn days_ago(days: i32) -> SimpleExpr {
+    Expr::current_timestamp()
+        .sub(Expr::cust_with_values("$1 * interval '1 day'", [days]))
+}
+
+/// Deliveries of the campaign to the user
+fn deliveries(user_id: SimpleExpr, campaign: Campaign) -> SelectStatement {
+    Query::select()
+        .expr(Expr::value(1))
+        .from(CampaignDeliveries)
+        .and_where(
+            Expr::col((CampaignDeliveries, campaign_deliveries::Column::UserId))
+                .eq(user_id),
+        )
+        .and_where(campaign_deliveries::Column::Campaign.eq(campaign))
+        .to_owned()
+}
+
+/// Condition that the campaign was ever sent to the user
+fn delivered(user_id: SimpleExpr, campaign: Campaign) -> SimpleExpr {
+    Expr::exists(deliveries(user_id, campaign))
+}
+
+/// Condition that the campaign was sent to the user after `since`
+fn delivered_since(
+    user_id: SimpleExpr,
+    campaign: Campaign,
+    since: SimpleExpr,
+) -> SimpleExpr {
+    Expr::exists(
+        deliveries(user_id, campaign)
+            .and_where(
+                Expr::col((
+                    CampaignDeliveries,
+                    campaign_deliveries::Column::Time,
+                ))
+                .gt(since),
+            )
+            .to_owned(),
+    )
+}
+
+/// Condition that the campaign wasn't sent to the user in the last
+/// `cooldown_days`
+fn cooldown_passed(
+    user_id: SimpleExpr,
+    campaign: Campaign,
+    cooldown_days: i32,
+) -> SimpleExpr {
+    delivered_since(user_id, campaign, days_ago(cooldown_days)).not()
+}
+
+/// [`cooldown_passed`] for queries on the `users` table
+fn user_cooldown_passed(campaign: Campaign, cooldown_days: i32) -> SimpleExpr {
+    cooldown_passed(
+        Expr::col((Users, users::Column::Id)).into(),
+        campaign,
+        cooldown_days,
+    )
+}
+
+/// Condition that the user is a school student, see [`Stage::new`]
+fn is_school(academic_year: i16) -> SimpleExpr {
+    users::Column::GraduationYear.gt(academic_year)
+}
+
+/// Condition that the user is a university student, see [`Stage::new`]
+fn is_university(academic_year: i16) -> SimpleExpr {
+    users::Column::University.eq(true).and(
+        users::Column::GraduationYear.between(
+            academic_year + 1 - i16::from(Course::MAX),
+            academic_year,
+        ),
+    )
+}
+
+/// September 1 of the academic year
+fn academic_year_start(academic_year: i16) -> SimpleExpr {
+    Expr::cust_with_values("make_date($1, 9, 1)", [i32::from(academic_year)])
+}
+
+/// Condition that the arrays have common elements
+fn overlap(
+    left: impl Into<SimpleExpr>,
+    right: impl Into<SimpleExpr>,
+) -> SimpleExpr {
+    Expr::cust_with_exprs("$1 && $2", [left.into(), right.into()])
+}
+
+/// Condition that the event from `events` is going on today
+fn is_going_on() -> SimpleExpr {
+    let ends = Expr::col((Events, events::Column::Ends));
+    Expr::col((Events, events::Column::Starts))
+        .lte(Expr::current_date())
+        .and(ends.gte(Expr::current_date()))
+}
+
+/// City of the user from `users` for matching, the city of the event the
+/// user is at overrides it
+pub fn user_city() -> SimpleExpr {
+    Expr::cust(
+        r#"coalesce((SELECT "events"."city" FROM "events"
+            WHERE "events"."id" = "users"."event_id"
+            AND current_date BETWEEN "events"."starts" AND "events"."ends"),
+            "users"."city")"#,
+    )
+}
+
+/// Condition that the user has an achievement of the level or higher
+fn has_achievement(user_id: SimpleExpr, level: SimpleExpr) -> SimpleExpr {
+    Expr::exists(
+        Query::select()
+            .expr(Expr::value(1))
+            .from(Achievements)
+            .and_where(
+                Expr::col((Achievements, achievements::Column::UserId))
+                    .eq(user_id),
+            )
+            .and_where(
+                Expr::col((Achievements, achievements::Column::Level))
+                    .binary(BinOper::GreaterThanOrEqual, level),
+            )
+            .to_owned(),
+    )
+}
+
+/// Condition that neither of the users has blocked the other
+fn not_blocked(first: SimpleExpr, second: SimpleExpr) -> SimpleExpr {
+    let blocker = Expr::col((Blocks, blocks::Column::BlockerId));
+    let target = Expr::col((Blocks, blocks::Column::BlockedId));
+    Expr::exists(
+        Query::select()
+            .expr(Expr::value(1))
+            .from(Blocks)
+            .cond_where(
+                Condition::any()
+                    .add(
+                        blocker
+                            .clone()
+                            .eq(first.clone())
+                            .and(target.clone().eq(second.clone())),
+                    )
+                    .add(blocker.eq(second).and(target.eq(first))),
+            )
+            .to_owned(),
+    )
+    .not()
+}
+
+/// `file_unique_id`s of the deleted images that no image has anymore
+async fn unused_files(
+    conn: &impl ConnectionTrait,
+    deleted: Vec<images::Model>,
+) -> Result<Vec<String>> {
+    let files: Vec<_> = deleted
+        .into_iter()
+        .filter_map(|image| image.file_unique_id)
+        .unique()
+        .collect();
+    let used: Vec<Option<String>> = Images::find()
+        .select_only()
+        .column(images::Column::FileUniqueId)
+        .filter(images::Column::FileUniqueId.is_in(files.clone()))
+        .into_tuple()
+        .all(conn)
+        .await?;
+    Ok(files
+        .into_iter()
+        .filter(|file| !used.contains(&Some(file.clone())))
+        .collect())
+}
+
+/// Condition that the user isn't banned
+fn not_banned(user_id: SimpleExpr) -> SimpleExpr {
+    // Aliased, so the condition works in queries of `users` too
+    let banned = Alias::new("banned_users");
+    Expr::exists(
+        Query::select()
+            .expr(Expr::value(1))
+            .from_as(Users, banned.clone())
+            .and_where(
+                Expr::col((banned.clone(), users::Column::Id)).eq(user_id),
+            )
+            .and_where(Expr::col((banned, users::Column::Banned)).eq(true))
+            .to_owned(),
+    )
+    .not()
+}
+
+/// Dialogues are stored in the `states` table as JSON, so users don't lose
+/// their progress after the bot restarts.
+impl Storage<State> for Database {
+    type Error = anyhow::Error;
+
+    fn remove_dialogue(
+        self: Arc<Self>,
+        chat_id: ChatId,
+    ) -> BoxFuture<'static, Result<(), Self::Error>> {
+        Box::pin(async move { self.save_dialogue(chat_id.0, None).await })
+    }
+
+    fn update_dialogue(
+        self: Arc<Self>,
+        chat_id: ChatId,
+        dialogue: State,
+    ) -> BoxFuture<'static, Result<(), Self::Error>> {
+        Box::pin(async move {
+            let dialogue = serde_json::to_value(dialogue)?;
+            self.save_dialogue(chat_id.0, Some(dialogue)).await
+        })
+    }
+
+    fn get_dialogue(
+        self: Arc<Self>,
+        chat_id: ChatId,
+    ) -> BoxFuture<'static, Result<Option<State>, Self::Error>> {
+        Box::pin(async move {
+            let Some(d) = self.load_dialogue(chat_id.0).await? else {
+                return Ok(None);
+            };
+            // A state of an older version mustn't lock the user out, they
+            // start over instead
+            Ok(serde_json::from_value(d)
+                .map_err(|e| {
+                    tracing::warn!(chat_id = chat_id.0, "bad dialogue: {e}");
+                })
+                .ok())
+        })
+    }
+}
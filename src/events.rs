@@ -0,0 +1,132 @@
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, Context};
+use chrono::NaiveDate;
+use entities::events;
+use teloxide::{
+    prelude::*,
+    types::{InlineKeyboardButton, InlineKeyboardMarkup},
+};
+
+use crate::{callbacks::Callback, cities::UserCity, db::Database, text, Bot};
+
+/// Event from `/addevent <начало> <конец> <название>` with dates as
+/// `ДД.ММ.ГГГГ`. The city of the event goes on the next line, without it
+/// users at the event keep their cities.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NewEvent {
+    pub name: String,
+    pub city: Option<i32>,
+    pub starts: NaiveDate,
+    pub ends: NaiveDate,
+}
+
+impl FromStr for NewEvent {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (line, city) = s.split_once('\n').unwrap_or((s, ""));
+        let mut parts = line.trim().splitn(3, char::is_whitespace);
+        let mut date = || -> anyhow::Result<NaiveDate> {
+            let date = parts.next().context("dates are missing")?;
+            Ok(NaiveDate::parse_from_str(date, "%d.%m.%Y")?)
+        };
+        let starts = date()?;
+        let ends = date()?;
+        if ends < starts {
+            bail!("the event ends before it starts");
+        }
+        let name = parts.next().unwrap_or_default().trim().to_owned();
+        if name.is_empty() {
+            bail!("name is missing");
+        }
+
+        let city = city.trim();
+        let city = if city.is_empty() {
+            None
+        } else {
+            let city: UserCity =
+                city.parse().map_err(|()| anyhow!("city not found"))?;
+            city.into()
+        };
+
+        Ok(Self { name, city, starts, ends })
+    }
+}
+
+/// Line of the profile for the user at the event
+pub fn badge(event: &events::Model) -> String {
+    format!("📍 Сейчас на «{}» до {}.", event.name, event.ends.format("%d.%m"))
+}
+
+/// Event for admins' `/events`
+pub fn describe(event: &events::Model) -> String {
+    let city = UserCity::try_from(event.city)
+        .map_or_else(|_| "город не найден".to_owned(), |c| c.to_string());
+    format!(
+        "#{} {}, {} — {}, {city}",
+        event.id,
+        event.name,
+        event.starts.format("%d.%m.%Y"),
+        event.ends.format("%d.%m.%Y")
+    )
+}
+
+/// Let the user choose the event they are at
+pub async fn request_event(
+    bot: &Bot,
+    db: &Database,
+    chat: ChatId,
+) -> anyhow::Result<()> {
+    let events = db.get_current_events().await?;
+    if events.is_empty() {
+        bot.send_message(chat, text::NO_EVENTS).await?;
+        return Ok(());
+    }
+
+    let mut keyboard: Vec<Vec<_>> = events
+        .into_iter()
+        .map(|event| {
+            vec![InlineKeyboardButton::callback(
+                event.name,
+                Callback::SetEvent { event_id: Some(event.id) }.to_string(),
+            )]
+        })
+        .collect();
+    keyboard.push(vec![InlineKeyboardButton::callback(
+        text::NOT_AT_EVENT,
+        Callback::SetEvent { event_id: None }.to_string(),
+    )]);
+
+    bot.send_message(chat, text::REQUEST_EVENT)
+        .reply_markup(InlineKeyboardMarkup::new(keyboard))
+        .await?;
+    Ok(())
+}
+
+/// Save the event the user has chosen, `None` if the user has left it
+pub async fn set_event(
+    bot: &Bot,
+    db: &Database,
+    msg: &Message,
+    event_id: Option<i32>,
+) -> anyhow::Result<()> {
+    let chat = msg.chat.id;
+    bot.edit_message_reply_markup(chat, msg.id).await?;
+
+    let Some(event_id) = event_id else {
+        db.set_user_event(chat.0, None).await?;
+        bot.send_message(chat, text::EVENT_LEFT).await?;
+        return Ok(());
+    };
+
+    let events = db.get_current_events().await?;
+    let Some(event) = events.iter().find(|e| e.id == event_id) else {
+        bot.send_message(chat, text::EVENT_ENDED).await?;
+        return Ok(());
+    };
+    db.set_user_event(chat.0, Some(event.id)).await?;
+    bot.send_message(chat, format!("Готово! В анкете будет:\n{}", badge(event)))
+        .await?;
+    Ok(())
+}
@@ -2,7 +2,6 @@ use std::{mem, sync::Arc};
 
 use anyhow::{bail, ensure, Context};
 use db::Database;
-use entities::sea_orm_active_enums::ImageKind;
 use teloxide::{
     // net::Download,
     prelude::*,
@@ -15,10 +14,15 @@ use teloxide::{
 use tracing::instrument;
 
 use crate::{
-    callbacks::{Callback, RateCode, UpdateBitflags},
+    callbacks::{
+        Callback, EditField, PhotosAction, RateCode, ReportStep,
+        SubjectsAction, UpdateBitflags,
+    },
     cities::{UserCity},
     db, text,
-    types::{DatingPurpose, Grade, Subjects, LocationFilter},
+    types::{
+        DatingPurpose, GradeFilter, LocationFilter, Stage, Subjects,
+    },
     utils, Bot, MyDialogue, State, StateData,
 };
 
@@ -39,7 +43,7 @@ enum HandleError {
 }
 
 macro_rules! make_macros {
-    ($bot:ident, $msg:ident, $state:ident, $chat:ident) => {
+    ($bot:ident, $state:ident, $chat:ident) => {
         // Why macros? Because async closures are unstable,
         // the only difference is "!"
         macro_rules! upd_print {
@@ -49,12 +53,6 @@ macro_rules! make_macros {
                 *$state = e;
             };
         }
-        // NOTE: not removing buttons is considered a bug!
-        macro_rules! remove_buttons {
-            () => {
-                $bot.edit_message_reply_markup($chat.id, $msg.id).await?;
-            };
-        }
         macro_rules! send {
             ($e:expr) => {
                 $bot.send_message($chat.id, $e).await?;
@@ -107,9 +105,14 @@ pub async fn handle_message(
     mut state: State,
     msg: Message,
 ) -> anyhow::Result<()> {
+    let old_state = state.clone();
+    crate::metrics::UPDATES
+        .with_label_values(&["message", (&state).into()])
+        .inc();
     if let Err(e) = try_handle_message(&db, &bot, &mut state, &msg).await {
         handle_error(e, &bot, &state, &msg.chat).await?;
     }
+    crate::metrics::transition(&old_state, &state);
     dialogue.update(state).await?;
     Ok(())
 }
@@ -124,11 +127,13 @@ pub async fn handle_callback(
 ) -> anyhow::Result<()> {
     let msg = q.message.as_ref().context("callback message is None")?;
     let data = q.data.as_deref().context("callback data is None")?;
+    let old_state = state.clone();
     if let Err(e) =
         try_handle_callback(&db, &bot, &mut state, msg, data, &q).await
     {
         handle_error(e, &bot, &state, &msg.chat).await?;
     }
+    crate::metrics::transition(&old_state, &state);
     dialogue.update(state).await?;
     Ok(())
 }
@@ -149,6 +154,7 @@ pub async fn print_state(
         SetGender(_) => set_gender(bot, chat).await?,
         SetGenderFilter(_) => set_gender_filter(bot, chat).await?,
         SetGraduationYear(_) => set_grade(bot, chat).await?,
+        SetGradeFilter(p) => set_grade_filter(bot, chat, p).await?,
         SetSubjects(p) => set_subjects(bot, chat, p).await?,
         SetSubjectsFilter(p) => {
             set_subjects_filter(bot, chat, p).await?;
@@ -159,7 +165,13 @@ pub async fn print_state(
             set_location_filter(bot, chat, p).await?;
         }
         SetAbout(_) => set_about(bot, chat).await?,
-        SetPhotos(_) => set_photos(bot, chat).await?,
+        SetAchievements(p) => {
+            set_achievements(bot, chat, &p.achievement).await?;
+        }
+        SetAchievementsFilter(_) => set_achievements_filter(bot, chat).await?,
+        SetPhotos(p) => set_photos(bot, chat, p).await?,
+        ManagePhotos(p) => manage_photos(bot, chat, &p.images).await?,
+        ConfirmEdit(p) => confirm_edit(bot, chat, p).await?,
         // others
         LikeWithMessage { .. } => {
             crate::datings::request_like_msg(bot, chat).await?;
@@ -171,16 +183,79 @@ pub async fn print_state(
     Ok(())
 }
 
-async fn try_handle_message(
+/// Show the staged edits to confirm them. Edits without a stored profile to
+/// compare with, e.g. of the grade after the rollover, are saved right away.
+async fn review_edit(
     db: &Database,
     bot: &Bot,
+    chat: &Chat,
+    data: &mut StateData,
+) -> anyhow::Result<State> {
+    if data.saved.is_none() {
+        db.create_or_update_user(data.s.clone()).await?;
+        crate::datings::send_profile(bot, db, data.s.id).await?;
+        return Ok(State::Start);
+    }
+    if data.changes().is_empty() {
+        bot.send_message(chat.id, text::EDIT_UNCHANGED)
+            .reply_markup(KeyboardRemove::new())
+            .await?;
+        return Ok(State::Edit);
+    }
+    Ok(State::ConfirmEdit(mem::take(data)))
+}
+
+/// State that changes only the field of the saved profile
+async fn edit_field(
+    db: &Database,
+    user_id: i64,
+    field: EditField,
+) -> anyhow::Result<State> {
+    use State::*;
+
+    let user = db.get_user(user_id).await?.context("user not found")?;
+    let images = db.get_user_images(user_id).await?;
+    let achievements = db
+        .get_achievements(user_id)
+        .await?
+        .into_iter()
+        .map(TryInto::try_into)
+        .collect::<anyhow::Result<_>>()?;
+    let data = StateData::edit(user.try_into()?, images, achievements);
+    Ok(match field {
+        EditField::Name => SetName(data),
+        EditField::Gender => SetGender(data),
+        EditField::GenderFilter => SetGenderFilter(data),
+        EditField::Grade => SetGraduationYear(data),
+        EditField::GradeFilter => SetGradeFilter(data),
+        EditField::Subjects => SetSubjects(data),
+        EditField::SubjectsFilter => SetSubjectsFilter(data),
+        EditField::DatingPurpose => SetDatingPurpose(data),
+        EditField::City => SetCity(data),
+        // The filter is relative to the city, so it's chosen there
+        EditField::LocationFilter
+            if data.s.city.clone().and_then(UserCity::get_city).is_none() =>
+        {
+            SetCity(data)
+        }
+        EditField::LocationFilter => SetLocationFilter(data),
+        EditField::About => SetAbout(data),
+        EditField::Achievements => SetAchievements(data),
+        EditField::AchievementsFilter => SetAchievementsFilter(data),
+        EditField::Photos => ManagePhotos(data),
+    })
+}
+
+async fn try_handle_message(
+    db: &Arc<Database>,
+    bot: &Bot,
     state: &mut State,
     msg: &Message,
 ) -> anyhow::Result<()> {
     let chat = &msg.chat;
     let t = msg.text();
 
-    make_macros!(bot, msg, state, chat);
+    make_macros!(bot, state, chat);
 
     use State::*;
     match state {
@@ -191,7 +266,7 @@ async fn try_handle_message(
             upd_print!(if data.create_new {
                 SetGender(mem::take(data))
             } else {
-                Start
+                review_edit(db, bot, chat, data).await?
             });
         }
         SetGender(data) => {
@@ -201,7 +276,7 @@ async fn try_handle_message(
             upd_print!(if data.create_new {
                 SetGenderFilter(mem::take(data))
             } else {
-                Start
+                review_edit(db, bot, chat, data).await?
             });
         }
         SetGenderFilter(data) => {
@@ -211,19 +286,30 @@ async fn try_handle_message(
             upd_print!(if data.create_new {
                 SetGraduationYear(mem::take(data))
             } else {
-                Start
+                review_edit(db, bot, chat, data).await?
             });
         }
         SetGraduationYear(data) => {
             let t = t.ok_or(HandleError::NeedText)?;
-            let grade = t.parse::<i8>().map_err(|_| HandleError::WrongText)?;
-            let grade =
-                Grade::try_from(grade).map_err(|_| HandleError::WrongText)?;
-            data.s.grade = Some(grade);
-            upd_print!(if data.create_new {
-                SetSubjects(mem::take(data))
+            let user_stage =
+                t.parse::<Stage>().map_err(|_| HandleError::WrongText)?;
+            // Only school students choose whether to see university students
+            if !user_stage.is_school() {
+                data.s.show_university = Some(false);
+            }
+            // The grade filter of school students counts grades and offers
+            // university students, so it's chosen again when that changes
+            let filter_valid = data
+                .s
+                .stage
+                .as_ref()
+                .is_some_and(|old| old.is_school() == user_stage.is_school());
+            data.s.stage = Some(user_stage);
+            data.s.grade_confirmed = Some(crate::types::academic_year());
+            upd_print!(if data.create_new || !filter_valid {
+                SetGradeFilter(mem::take(data))
             } else {
-                Start
+                review_edit(db, bot, chat, data).await?
             });
         }
         SetCity(data) => {
@@ -241,7 +327,7 @@ async fn try_handle_message(
                     upd_print!(if data.create_new {
                         SetAbout(mem::take(data))
                     } else {
-                        Start
+                        review_edit(db, bot, chat, data).await?
                     });
                 }
                 city => {
@@ -272,7 +358,7 @@ async fn try_handle_message(
             upd_print!(if data.create_new {
                 SetAbout(mem::take(data))
             } else {
-                Start
+                review_edit(db, bot, chat, data).await?
             });
         }
         SetAbout(data) => {
@@ -282,61 +368,110 @@ async fn try_handle_message(
                 HandleError::Length
             );
             data.s.about = Some(t.to_owned());
-            // FIXME: HACK: create user before SetPhotos
-            db.create_or_update_user(data.s.clone()).await?;
+            upd_print!(if data.create_new {
+                SetAchievements(mem::take(data))
+            } else {
+                review_edit(db, bot, chat, data).await?
+            });
+        }
+        SetAchievements(data) => {
+            let t = t.ok_or(HandleError::NeedText)?;
+            let draft = &mut data.achievement;
+            if draft.olympiad.is_none() {
+                match t {
+                    text::ACHIEVEMENTS_DONE => {
+                        upd_print!(if data.create_new {
+                            SetAchievementsFilter(mem::take(data))
+                        } else {
+                            review_edit(db, bot, chat, data).await?
+                        });
+                        return Ok(());
+                    }
+                    text::ACHIEVEMENTS_CLEAR => {
+                        data.achievements.clear();
+                        send!(text::ACHIEVEMENTS_CLEARED);
+                    }
+                    _ => {
+                        draft.answer(t).map_err(|_| HandleError::WrongText)?;
+                    }
+                }
+            } else if let Some(achievement) =
+                draft.answer(t).map_err(|_| HandleError::WrongText)?
+            {
+                send!(format!("Добавлено: {achievement}"));
+                data.achievements.push(achievement);
+            }
+            print_state(state, bot, chat).await?;
+        }
+        SetAchievementsFilter(data) => {
+            let t = t.ok_or(HandleError::NeedText)?;
+            let filter = t.parse().map_err(|_| HandleError::WrongText)?;
+            data.s.achievement_filter = Some(filter);
             upd_print!(if data.create_new {
                 SetPhotos(mem::take(data))
             } else {
-                Start
+                review_edit(db, bot, chat, data).await?
             });
         }
         SetPhotos(data) => match t {
-            Some("Без фото") => {
-                db.clean_images(chat.id.0).await?;
+            Some(answer @ ("Без фото" | "Сохранить")) if data.create_new => {
+                if answer == "Без фото" {
+                    data.images.clear();
+                }
+                let unused = db
+                    .save_profile(
+                        data.s.clone(),
+                        Some(&data.images),
+                        Some(&data.achievements),
+                    )
+                    .await?;
+                crate::archive::update(bot, &data.images, &unused).await;
+                send!(text::PROFILE_CREATED, remove);
                 crate::datings::send_profile(bot, db, data.s.id).await?;
                 upd_print!(Start);
             }
             Some("Сохранить") => {
-                crate::datings::send_profile(bot, db, data.s.id).await?;
-                upd_print!(Start);
+                send!(text::PHOTOS_UPLOADED, remove);
+                upd_print!(ManagePhotos(mem::take(data)));
             }
             _ => {
-                // TODO: change type of photos_count to Option<u8>
-                // TODO: reset photos button
-                if data.photos_count == 0 {
-                    db.clean_images(msg.chat.id.0).await?;
-                } else if data.photos_count >= 10 {
-                    send!(
-                        "Невозможно добавить более 10 фото/видео",
-                        markup[[KeyboardButton::new("Сохранить")]]
-                    );
-                    return Ok(());
-                };
-
-                if let Some([.., photo]) = msg.photo() {
-                    let file = bot.get_file(&photo.file.id).await?;
-                    db.create_image(chat.id.0, file.meta.id, ImageKind::Image)
-                        .await?;
-                } else if let Some(video) = msg.video() {
-                    let file = bot.get_file(&video.file.id).await?;
-                    db.create_image(chat.id.0, file.meta.id, ImageKind::Video)
-                        .await?;
-                } else {
-                    bail!(HandleError::WrongText);
+                let (file, kind) = match crate::photos::media(msg) {
+                    Some(Ok(media)) => media,
+                    Some(Err(text)) => {
+                        send!(text);
+                        return Ok(());
+                    }
+                    None => bail!(HandleError::WrongText),
                 };
-
-                data.photos_count += 1;
-
-                send!(
-                    format!(
-                        "Добавлено {}/10 фото/видео. Добавить ещё?",
-                        data.photos_count
-                    ),
-                    markup[[KeyboardButton::new("Сохранить")]]
-                );
+                crate::photos::upload(bot, data, msg, file, kind).await?;
             }
         },
-        // TODO: confirm profile change State
+        ConfirmEdit(data) => {
+            match t {
+                Some("Сохранить") => {
+                    let images = data.images_changed().then_some(&data.images);
+                    let achievements = data
+                        .achievements_changed()
+                        .then_some(&data.achievements);
+                    let images = images.map(Vec::as_slice);
+                    let unused = db
+                        .save_profile(
+                            data.s.clone(),
+                            images,
+                            achievements.map(Vec::as_slice),
+                        )
+                        .await?;
+                    let saved = images.unwrap_or_default();
+                    crate::archive::update(bot, saved, &unused).await;
+                    send!(text::EDIT_SAVED, remove);
+                }
+                Some("Отменить") => {
+                    send!(text::EDIT_CANCELLED, remove);
+                }
+                _ => bail!(HandleError::WrongText),
+            }
+            upd_print!(Edit);
+        }
         Start => {
             bot.send_message(
                 chat.id,
@@ -352,10 +487,18 @@ async fn try_handle_message(
             {
                 db.set_dating_initiator_reaction(dating.id, false).await?;
                 "Отправка лайка отменена"
+            } else if crate::datings::refuse_blocked_like(db, dating).await? {
+                text::USER_UNAVAILABLE
             } else {
                 db.set_dating_initiator_reaction(dating.id, true).await?;
-                crate::datings::send_like(db, bot, dating, Some(t.to_owned()))
-                    .await?;
+                // Boxed to keep the handler's future small
+                Box::pin(crate::datings::send_like(
+                    db,
+                    bot,
+                    dating,
+                    Some(t.to_owned()),
+                ))
+                .await?;
                 "Лайк отправлен!"
             };
 
@@ -370,11 +513,18 @@ async fn try_handle_message(
         }
 
         // explicit ignore (for now)
-        SetSubjects(_) | SetSubjectsFilter(_) | SetDatingPurpose(_) | Edit => {}
+        SetGradeFilter(_)
+        | SetSubjects(_)
+        | SetSubjectsFilter(_)
+        | SetDatingPurpose(_)
+        | ManagePhotos(_)
+        | Edit => {}
     }
     Ok(())
 }
 
+// The lint sums the futures of every arm, but only one of them is alive
+#[allow(clippy::large_stack_frames)]
 async fn try_handle_callback(
     db: &Database,
     bot: &Bot,
@@ -384,13 +534,40 @@ async fn try_handle_callback(
     q: &CallbackQuery,
 ) -> anyhow::Result<()> {
     let chat = &msg.chat;
-    make_macros!(bot, msg, state, chat);
+    make_macros!(bot, state, chat);
+    // NOTE: not removing buttons is considered a bug!
+    macro_rules! remove_buttons {
+        () => {
+            bot.edit_message_reply_markup(chat.id, msg.id).await?;
+        };
+    }
 
     let callback: Callback = data.parse()?;
+    crate::metrics::UPDATES
+        .with_label_values(&[
+            "callback",
+            callback.char_id().encode_utf8(&mut [0; 4]),
+        ])
+        .inc();
+
+    // Broadcasts can arrive in any state
+    if callback == Callback::BroadcastOptOut {
+        crate::broadcasts::opt_out(bot, db, msg).await?;
+        return Ok(());
+    }
 
     use State::*;
 
-    if matches!(callback, Callback::Dating { .. }) && *state != Start {
+    if matches!(
+        callback,
+        Callback::Dating { .. }
+            | Callback::Block { .. }
+            | Callback::Report { .. }
+            | Callback::Likes { .. }
+            | Callback::Matches { .. }
+            | Callback::SetEvent { .. }
+    ) && *state != Start
+    {
         bot.answer_callback_query(&q.id)
             .text("Сначала выйдите из режима редактирования!")
             .show_alert(true)
@@ -399,16 +576,56 @@ async fn try_handle_callback(
     }
 
     match state {
+        SetGradeFilter(data) => {
+            let user_stage = data
+                .s
+                .stage
+                .clone()
+                .context("stage must be set at this moment")?;
+            let (up, down) = match callback {
+                Callback::SetGradeFilter { up, down } => (up, down),
+                Callback::SetUniversityFilter { show } => {
+                    data.s.show_university = Some(show);
+                    bot.edit_message_reply_markup(msg.chat.id, msg.id)
+                        .reply_markup(utils::make_grade_filter_keyboard(
+                            data.s.grade_up_filter.unwrap_or(1),
+                            data.s.grade_down_filter.unwrap_or(1),
+                            Some(show),
+                        ))
+                        .await?;
+                    return Ok(());
+                }
+                _ => bail!("wrong callback type"),
+            };
+
+            let mut filter = format!(
+                "Вы ищете: {}.",
+                GradeFilter::new(user_stage, up, down)
+            );
+            if data.s.show_university == Some(true) {
+                filter.push_str(text::UNIVERSITY_SHOWN);
+            }
+            remove_buttons!();
+            bot.edit_message_text(msg.chat.id, msg.id, filter).await?;
+
+            data.s.grade_up_filter = Some(up);
+            data.s.grade_down_filter = Some(down);
+            upd_print!(if data.create_new {
+                SetSubjects(mem::take(data))
+            } else {
+                review_edit(db, bot, chat, data).await?
+            });
+        }
         SetSubjects(data) => {
             let Callback::SetSubjects(changed_subjects) = callback else {
                 bail!("wrong callback type")
             };
 
             // FIXME: store Subjects in EditProfile
-            let current_subjects = data.s.subjects.clone().map_or_else(Subjects::empty, |s| s.into());
+            let current_subjects = data.s.subjects.clone().map_or_else(Subjects::default, |s| s.into());
 
             match changed_subjects {
-                UpdateBitflags::Continue => {
+                SubjectsAction::Continue => {
                     remove_buttons!();
 
                     let subjects_str = if current_subjects.is_empty() {
@@ -422,14 +639,20 @@ async fn try_handle_callback(
                         .await?;
 
                     data.s.subjects = Some(current_subjects.try_into()?);
-                    upd_print!(SetSubjectsFilter(mem::take(data)));
+                    upd_print!(if data.create_new {
+                        SetSubjectsFilter(mem::take(data))
+                    } else {
+                        review_edit(db, bot, chat, data).await?
+                    });
                 }
-                UpdateBitflags::Update(changed_subjects) => {
-                    let new_subjects = current_subjects ^ changed_subjects;
+                action => {
+                    let mut new_subjects = current_subjects;
+                    let page = action.apply(&mut new_subjects);
 
                     bot.edit_message_reply_markup(msg.chat.id, msg.id)
                         .reply_markup(utils::make_subjects_keyboard(
-                            new_subjects,
+                            &new_subjects,
+                            page,
                             &utils::SubjectsKeyboardType::User,
                         ))
                         .await?;
@@ -444,10 +667,10 @@ async fn try_handle_callback(
             };
 
             // FIXME: store Subjects in EditProfile
-            let current_filter = data.s.subjects_filter.clone().map_or_else(Subjects::empty, |s| s.into());
+            let current_filter = data.s.subjects_filter.clone().map_or_else(Subjects::default, |s| s.into());
 
             match changed_subjects_filter {
-                UpdateBitflags::Continue => {
+                SubjectsAction::Continue => {
                     remove_buttons!();
 
                     let subjects_filter_str = if current_filter.is_empty() {
@@ -466,16 +689,21 @@ async fn try_handle_callback(
                     .await?;
 
                     data.s.subjects_filter = Some(current_filter.into());
-                    upd_print!(SetSubjectsFilter(mem::take(data)));
+                    upd_print!(if data.create_new {
+                        SetDatingPurpose(mem::take(data))
+                    } else {
+                        review_edit(db, bot, chat, data).await?
+                    });
                 }
-                UpdateBitflags::Update(changed_subjects_filter) => {
-                    let new_subjects_filter =
-                        current_filter ^ changed_subjects_filter;
+                action => {
+                    let mut new_subjects_filter = current_filter;
+                    let page = action.apply(&mut new_subjects_filter);
 
                     bot.edit_message_reply_markup(msg.chat.id, msg.id)
                         .reply_markup(utils::make_subjects_keyboard(
-                            changed_subjects_filter,
-                            &utils::SubjectsKeyboardType::User,
+                            &new_subjects_filter,
+                            page,
+                            &utils::SubjectsKeyboardType::Partner,
                         ))
                         .await?;
 
@@ -510,7 +738,7 @@ async fn try_handle_callback(
                     upd_print!(if data.create_new {
                         SetCity(mem::take(data))
                     } else {
-                        Start
+                        review_edit(db, bot, chat, data).await?
                     });
                 }
                 UpdateBitflags::Update(changed_purpose) => {
@@ -526,31 +754,51 @@ async fn try_handle_callback(
                 }
             }
         }
-        Edit => {
-            // TODO: edit should work in Start state
-            // ensure!(code == Callback::Edit, HandleError::WrongCode);
-            // TODO: strum on State?
-            // FIXME: check if user exists
-            let user =
-                db.get_user(msg.chat.id.0).await?.context("user not found")?;
-            let p = StateData::with_settings(user.try_into()?);
-
+        ManagePhotos(data) => {
+            let Callback::ManagePhotos(action) = callback else {
+                bail!("wrong callback type")
+            };
             remove_buttons!();
-            let state = match data {
-                "Имя" => SetName(p),
-                "Предметы" => SetSubjects(p),
-                "О себе" => SetAbout(p),
-                "Город" => SetCity(p),
-                "Фото" => SetPhotos(p),
-                "Отмена" => Start,
-                _ => bail!("unknown edit data"),
+            match action {
+                PhotosAction::Add => {
+                    upd_print!(SetPhotos(mem::take(data)));
+                }
+                PhotosAction::Done => {
+                    upd_print!(review_edit(db, bot, chat, data).await?);
+                }
+                action => {
+                    crate::photos::apply(&mut data.images, &action)?;
+                    upd_print!(ManagePhotos(mem::take(data)));
+                }
+            }
+        }
+        Edit => {
+            let Callback::Edit(field) = callback else {
+                bail!("wrong callback type")
             };
-            upd_print!(state);
+            remove_buttons!();
+            if let Some(field) = field {
+                upd_print!(edit_field(db, chat.id.0, field).await?);
+            } else {
+                crate::datings::send_profile(bot, db, chat.id.0).await?;
+                upd_print!(Start);
+            }
         }
         Start => {
             match callback {
-                Callback::Edit => todo!(),
+                Callback::Edit(Some(field)) => {
+                    remove_buttons!();
+                    upd_print!(edit_field(db, chat.id.0, field).await?);
+                }
                 Callback::Dating { dating_id, code } => {
+                    // Declining a like is allowed to anyone
+                    if code != RateCode::ResponseDislike
+                        && !crate::datings::check_can_date(bot, db, chat.id)
+                            .await?
+                    {
+                        remove_buttons!();
+                        return Ok(());
+                    }
                     let dating = db.get_dating(dating_id).await?;
                     match code {
                         RateCode::Dislike => {
@@ -582,6 +830,20 @@ async fn try_handle_callback(
                                 dating.initiator_reaction.is_none(),
                                 "user abuses likes"
                             );
+                            if crate::datings::refuse_blocked_like(
+                                db, &dating,
+                            )
+                            .await?
+                            {
+                                send!(text::USER_UNAVAILABLE);
+                                crate::datings::send_recommendation(
+                                    bot,
+                                    db,
+                                    ChatId(dating.initiator_id),
+                                )
+                                .await?;
+                                return Ok(());
+                            }
 
                             db.set_dating_initiator_reaction(dating_id, true)
                                 .await?;
@@ -608,11 +870,22 @@ async fn try_handle_callback(
                                 dating.partner_reaction.is_none(),
                                 "partner abuses likes"
                             );
-
                             let initiator = db
                                 .get_user(dating.initiator_id)
                                 .await?
                                 .context("dating initiator not found")?;
+                            if initiator.banned
+                                || db
+                                    .is_blocked(
+                                        dating.initiator_id,
+                                        dating.partner_id,
+                                    )
+                                    .await?
+                            {
+                                remove_buttons!();
+                                send!(text::USER_UNAVAILABLE);
+                                return Ok(());
+                            }
 
                             let markup = InlineKeyboardMarkup::new([[
                                 InlineKeyboardButton::url(
@@ -636,9 +909,123 @@ async fn try_handle_callback(
                         }
                     }
                 }
+                Callback::Block { dating_id } => {
+                    let dating = db.get_dating(dating_id).await?;
+                    let is_initiator = dating.initiator_id == chat.id.0;
+                    ensure!(
+                        is_initiator || dating.partner_id == chat.id.0,
+                        "user isn't in the dating"
+                    );
+
+                    remove_buttons!();
+                    if is_initiator {
+                        db.block_user(dating.initiator_id, dating.partner_id)
+                            .await?;
+                    } else {
+                        db.block_user(dating.partner_id, dating.initiator_id)
+                            .await?;
+                    }
+                    send!(text::USER_BLOCKED);
+                    crate::datings::reject_dating(bot, db, &dating, chat.id.0)
+                        .await?;
+                }
+                Callback::Report { dating_id, step } => {
+                    let dating = db.get_dating(dating_id).await?;
+                    ensure!(
+                        dating.initiator_id == chat.id.0
+                            || dating.partner_id == chat.id.0,
+                        "user isn't in the dating"
+                    );
+
+                    match step {
+                        ReportStep::ChooseReason => {
+                            crate::reports::request_reason(bot, msg, dating_id)
+                                .await?;
+                        }
+                        ReportStep::Cancel => {
+                            bot.delete_message(chat.id, msg.id).await?;
+                        }
+                        ReportStep::Reason(reason) => {
+                            // Reasons are asked in reply to the reported
+                            // message
+                            let reported = msg
+                                .reply_to_message()
+                                .context("reported message not found")?;
+
+                            bot.edit_message_text(
+                                chat.id,
+                                msg.id,
+                                text::REPORT_SENT,
+                            )
+                            .await?;
+                            bot.edit_message_reply_markup(chat.id, reported.id)
+                                .await?;
+                            crate::reports::report(
+                                bot,
+                                db,
+                                &dating,
+                                chat.id.0,
+                                reason,
+                                reported.text().unwrap_or_default().to_owned(),
+                            )
+                            .await?;
+                            crate::datings::reject_dating(
+                                bot,
+                                db,
+                                &dating,
+                                chat.id.0,
+                            )
+                            .await?;
+                        }
+                    }
+                }
+                Callback::Moderate { report_id, resolution } => {
+                    crate::reports::moderate(
+                        bot,
+                        db,
+                        q,
+                        msg,
+                        report_id,
+                        resolution,
+                    )
+                    .await?;
+                }
+                Callback::Likes { page } => {
+                    remove_buttons!();
+                    crate::datings::send_incoming_like(
+                        bot,
+                        db,
+                        msg.chat.id,
+                        page,
+                    )
+                    .await?;
+                }
+                Callback::Matches { page } => {
+                    crate::datings::edit_match(
+                        bot,
+                        db,
+                        msg.chat.id,
+                        msg.id,
+                        page,
+                    )
+                    .await?;
+                }
                 Callback::CreateProfile => {
+                    remove_buttons!();
                     crate::start_profile_creation(state, msg, bot).await?;
                 }
+                Callback::ConfirmGrade { confirmed } => {
+                    remove_buttons!();
+                    if let Some(new_state) =
+                        crate::rollover::answer(bot, db, msg.chat.id, confirmed)
+                            .await?
+                    {
+                        upd_print!(new_state);
+                    }
+                }
+                Callback::SetEvent { event_id } => {
+                    crate::events::set_event(bot, db, msg, event_id).await?;
+                }
                 Callback::FindPartner => {
                     remove_buttons!();
                     // TODO: refactor this
@@ -656,7 +1043,10 @@ async fn try_handle_callback(
         | SetCity(_)
         | SetLocationFilter(_)
         | SetAbout(_)
+        | SetAchievements(_)
+        | SetAchievementsFilter(_)
         | SetPhotos(_)
+        | ConfirmEdit(_)
         | LikeWithMessage { .. } => {}
     }
 
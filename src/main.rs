@@ -13,33 +13,48 @@
 
 use std::{str::FromStr, sync::Arc};
 
+use admin::AdminCommand;
 use db::Database;
 use sentry_tracing::EventFilter;
 use teloxide::{
     adaptors::{throttle::Limits, Throttle},
-    dispatching::dialogue::InMemStorage,
+    dispatching::UpdateHandler,
     error_handlers::ErrorHandler,
     prelude::*,
     types::{InlineKeyboardButton, InlineKeyboardMarkup},
     utils::command::BotCommands,
     RequestError,
 };
+use serde::{Deserialize, Serialize};
 use tracing::*;
 use tracing_subscriber::prelude::*;
 use types::UserSettings;
 
+mod admin;
+mod archive;
+mod broadcasts;
 mod callbacks;
 mod cities;
 mod datings;
 mod db;
+mod events;
 mod handle;
+mod metrics;
+mod olympiads;
+mod photos;
+mod ranking;
+mod reminders;
+mod reports;
+mod rollover;
 mod request;
+#[cfg(test)]
+mod tests;
 mod text;
 mod types;
 mod utils;
 
 type Bot = Throttle<teloxide::Bot>;
-type MyDialogue = Dialogue<State, InMemStorage<State>>;
+type MyDialogue = Dialogue<State, Database>;
 
 #[derive(thiserror::Error, Debug)]
 pub enum AppError {
@@ -63,6 +78,9 @@ impl ErrorHandler<anyhow::Error> for AppErrorHandler {
         error: anyhow::Error,
     ) -> futures_util::future::BoxFuture<'static, ()> {
         warn!("{}", error.to_string());
+        if let Some(e) = error.downcast_ref::<RequestError>() {
+            metrics::api_error(e);
+        }
         sentry_anyhow::capture_anyhow(&error);
 
         Box::pin(async {})
@@ -117,8 +135,59 @@ async fn main() -> anyhow::Result<()> {
         ..Default::default()
     });
 
-    let handler = dptree::entry()
-        .enter_dialogue::<Update, InMemStorage<State>, State>()
+    let database = Arc::new(db::Database::new().await?);
+
+    if let Err(e) = admin::set_commands(&bot).await {
+        sentry_anyhow::capture_anyhow(&e.context("can't set bot commands"));
+    }
+
+    let likes_reminder = reminders::LikesReminder::from_env()?;
+    tokio::spawn(likes_reminder.run(bot.clone(), database.clone()));
+
+    let reengagement = reminders::Reengagement::from_env()?;
+    tokio::spawn(reengagement.run(bot.clone(), database.clone()));
+
+    let grade_check = rollover::GradeCheck::from_env()?;
+    tokio::spawn(grade_check.run(bot.clone(), database.clone()));
+
+    let broadcaster = broadcasts::Broadcaster::from_env()?;
+    tokio::spawn(broadcaster.run(bot.clone(), database.clone()));
+
+    if let Some(addr) = metrics::addr_from_env()? {
+        let database = database.clone();
+        tokio::spawn(async move {
+            if let Err(e) = metrics::serve(addr, database).await {
+                sentry_anyhow::capture_anyhow(
+                    &e.context("metrics server failed"),
+                );
+            }
+        });
+    }
+
+    Dispatcher::builder(bot, schema())
+        .dependencies(dptree::deps![database])
+        .error_handler(AppErrorHandler::new())
+        .enable_ctrlc_handler()
+        .build()
+        .dispatch()
+        .await;
+    Ok(())
+}
+
+fn schema() -> UpdateHandler<anyhow::Error> {
+    dptree::entry()
+        // Any update from a user keeps the profile from being deactivated
+        .inspect_async(|db: Arc<Database>, update: Update| async move {
+            let Some(chat) = update.chat().filter(|c| c.is_private()) else {
+                return;
+            };
+            if let Err(e) = db.update_last_activity(chat.id.0).await {
+                sentry_anyhow::capture_anyhow(
+                    &e.context("can't update last activity"),
+                );
+            }
+        })
+        .enter_dialogue::<Update, Database, State>()
         // .branch(
         //     dptree::filter_map(|update: Update| {
         //         Some(!update.chat()?.is_private())
@@ -127,6 +196,13 @@ async fn main() -> anyhow::Result<()> {
         // )
         .branch(
             Update::filter_message()
+                .branch(
+                    dptree::filter(|msg: Message| {
+                        admin::is_admin(msg.chat.id.0)
+                    })
+                    .filter_command::<AdminCommand>()
+                    .endpoint(admin::answer),
+                )
                 .branch(
                     dptree::entry()
                         .filter_command::<Command>()
@@ -137,37 +213,113 @@ async fn main() -> anyhow::Result<()> {
         .branch(
             Update::filter_callback_query()
                 .branch(dptree::endpoint(handle::handle_callback)),
-        );
-
-    let database = db::Database::new().await?;
-
-    Dispatcher::builder(bot, handler)
-        .dependencies(dptree::deps![
-            InMemStorage::<State>::new(),
-            Arc::new(database)
-        ])
-        .error_handler(AppErrorHandler::new())
-        .enable_ctrlc_handler()
-        .build()
-        .dispatch()
-        .await;
-    Ok(())
+        )
 }
 
-#[derive(Clone, Default, Debug, PartialEq, Eq)]
+#[derive(Clone, Default, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct StateData {
     s: UserSettings,
     create_new: bool,
-    photos_count: u8,
+    #[serde(default)]
+    achievement: olympiads::AchievementDraft,
+    /// Photos and videos of the profile, new ones have negative ids until
+    /// they are stored
+    #[serde(default)]
+    images: Vec<entities::images::Model>,
+    /// Olympiad results of the profile
+    #[serde(default)]
+    achievements: Vec<olympiads::Achievement>,
+    /// The stored profile the edits are compared with, changes are saved
+    /// without a review when there is none
+    #[serde(default)]
+    saved: Option<SavedProfile>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SavedProfile {
+    s: UserSettings,
+    images: Vec<i32>,
+    #[serde(default)]
+    achievements: Vec<olympiads::Achievement>,
 }
 
 impl StateData {
     pub fn with_settings(s: UserSettings) -> Self {
         Self { s, ..Default::default() }
     }
+
+    /// Staged edits of the stored profile
+    pub fn edit(
+        s: UserSettings,
+        images: Vec<entities::images::Model>,
+        achievements: Vec<olympiads::Achievement>,
+    ) -> Self {
+        let saved = SavedProfile {
+            s: s.clone(),
+            images: images.iter().map(|image| image.id).collect(),
+            achievements: achievements.clone(),
+        };
+        Self {
+            s,
+            images,
+            achievements,
+            saved: Some(saved),
+            ..Default::default()
+        }
+    }
+
+    /// Whether the images differ from the stored ones
+    pub fn images_changed(&self) -> bool {
+        self.saved.as_ref().is_none_or(|saved| {
+            !self.images.iter().map(|image| image.id).eq(saved.images.clone())
+        })
+    }
+
+    /// Whether the achievements differ from the stored ones
+    pub fn achievements_changed(&self) -> bool {
+        self.saved
+            .as_ref()
+            .is_none_or(|saved| self.achievements != saved.achievements)
+    }
+
+    /// Lines describing the staged edits
+    pub fn changes(&self) -> Vec<String> {
+        let Some(saved) = &self.saved else {
+            return Vec::new();
+        };
+        let mut changes = saved.s.changes(&self.s);
+        if self.images_changed() {
+            let (before, after) = (saved.images.len(), self.images.len());
+            changes.push(if before == after {
+                "Фото и видео: изменены".to_owned()
+            } else {
+                format!("Фото и видео: {before} → {after}")
+            });
+        }
+        if self.achievements_changed() {
+            let label = callbacks::EditField::Achievements.label();
+            let before = saved.achievements.len();
+            let after = self.achievements.len();
+            changes.push(if before == after {
+                format!("{label}: изменены")
+            } else {
+                format!("{label}: {before} → {after}")
+            });
+        }
+        changes
+    }
 }
 
-#[derive(Clone, Default, Debug, PartialEq, Eq)]
+#[derive(
+    Clone,
+    Default,
+    Debug,
+    PartialEq,
+    Eq,
+    Serialize,
+    Deserialize,
+    strum::IntoStaticStr,
+)]
 pub enum State {
     #[default]
     Start,
@@ -176,13 +328,20 @@ pub enum State {
     SetGender(StateData),
     SetGenderFilter(StateData),
     SetGraduationYear(StateData),
+    SetGradeFilter(StateData),
     SetSubjects(StateData),
     SetSubjectsFilter(StateData),
     SetDatingPurpose(StateData),
     SetCity(StateData),
     SetLocationFilter(StateData),
     SetAbout(StateData),
+    SetAchievements(StateData),
+    SetAchievementsFilter(StateData),
     SetPhotos(StateData),
+    /// Reordering and deleting uploaded photos
+    ManagePhotos(StateData),
+    /// Showing the staged edits until they are saved or discarded
+    ConfirmEdit(StateData),
     /// Waiting for the message for the like
     LikeWithMessage {
         dating: entities::datings::Model,
@@ -190,7 +349,7 @@ pub enum State {
     Edit,
 }
 
-#[derive(Debug, BotCommands, Clone)]
+#[derive(Debug, BotCommands, Clone, strum::IntoStaticStr)]
 #[command(rename_rule = "lowercase", description = "Доступные команды:")]
 enum Command {
     #[command(description = "заполнить анкету")]
@@ -201,10 +360,20 @@ enum Command {
     Edit,
     #[command(description = "найти партнёра")]
     Date,
+    #[command(description = "кому понравилась моя анкета")]
+    Likes,
+    #[command(description = "взаимные лайки")]
+    Matches,
     #[command(description = "включить анкету")]
     Enable,
     #[command(description = "выключить анкету")]
     Disable,
+    #[command(description = "отметиться на сборах или олимпиаде")]
+    Event,
+    #[command(description = "получать рассылку")]
+    Subscribe,
+    #[command(description = "отписаться от рассылки")]
+    Unsubscribe,
     #[command(description = "приветственное сообщение")]
     Start,
     #[command(description = "помощь по командам")]
@@ -217,9 +386,8 @@ pub async fn start_profile_creation(
     bot: &Bot,
 ) -> anyhow::Result<()> {
     let chat = &msg.chat;
-    handle::make_macros!(bot, msg, state, chat);
+    handle::make_macros!(bot, state, chat);
 
-    remove_buttons!();
     if !utils::check_user_subscribed_channel(bot, msg.chat.id.0).await? {
         send!(
             text::SUBSCRIBE_TEXT,
@@ -239,7 +407,10 @@ pub async fn start_profile_creation(
     } else {
         send!(text::PROFILE_CREATION_STARTED);
         let settings = UserSettings::with_id(msg.chat.id.0);
-        upd_print!(State::SetName(StateData::with_settings(settings)));
+        upd_print!(State::SetName(StateData {
+            create_new: true,
+            ..StateData::with_settings(settings)
+        }));
     }
 
     Ok(())
@@ -262,9 +433,13 @@ async fn answer(
         msg: Message,
         cmd: Command,
     ) -> anyhow::Result<()> {
+        metrics::UPDATES.with_label_values(&["command", (&cmd).into()]).inc();
+
         match cmd {
             Command::Create => {
+                let old_state = state.clone();
                 start_profile_creation(&mut state, &msg, &bot).await?;
+                metrics::transition(&old_state, &state);
                 dialogue.update(state).await?;
             }
             Command::Edit => {
@@ -275,24 +450,48 @@ async fn answer(
                 }
 
                 request::edit_profile(&bot, &msg.chat).await?;
+                metrics::transition(&state, &State::Edit);
                 dialogue.update(State::Edit).await?;
             }
             Command::Help => {
-                bot.send_message(
-                    msg.chat.id,
-                    Command::descriptions().to_string(),
-                )
-                .await?;
+                let mut help = Command::descriptions().to_string();
+                if admin::is_admin(msg.chat.id.0) {
+                    let admin_help = AdminCommand::descriptions();
+                    help = format!("{help}\n\n{admin_help}");
+                }
+                bot.send_message(msg.chat.id, help).await?;
             }
             Command::Date => {
-                if db.get_user(msg.chat.id.0).await?.is_none() {
+                let Some(user) = db.get_user(msg.chat.id.0).await? else {
                     bot.send_message(msg.chat.id, text::PLEASE_CREATE_PROFILE)
                         .await?;
                     return Ok(());
+                };
+                // Don't match with a grade from the last academic year
+                if rollover::ask_if_stale(&bot, &db, &user).await? {
+                    return Ok(());
                 }
 
                 datings::send_recommendation(&bot, &db, msg.chat.id).await?;
             }
+            Command::Likes => {
+                if db.get_user(msg.chat.id.0).await?.is_none() {
+                    bot.send_message(msg.chat.id, text::PLEASE_CREATE_PROFILE)
+                        .await?;
+                    return Ok(());
+                }
+
+                datings::send_incoming_like(&bot, &db, msg.chat.id, 0).await?;
+            }
+            Command::Matches => {
+                if db.get_user(msg.chat.id.0).await?.is_none() {
+                    bot.send_message(msg.chat.id, text::PLEASE_CREATE_PROFILE)
+                        .await?;
+                    return Ok(());
+                }
+
+                datings::send_match(&bot, &db, msg.chat.id, 0).await?;
+            }
             Command::Profile => {
                 if db.get_user(msg.chat.id.0).await?.is_none() {
                     bot.send_message(msg.chat.id, text::PLEASE_CREATE_PROFILE)
@@ -303,10 +502,15 @@ async fn answer(
                 datings::send_profile(&bot, &db, msg.chat.id.0).await?;
             }
             Command::Enable => {
-                if db.get_user(msg.chat.id.0).await?.is_none() {
+                let Some(user) = db.get_user(msg.chat.id.0).await? else {
                     bot.send_message(msg.chat.id, text::PLEASE_CREATE_PROFILE)
                         .await?;
                     return Ok(());
+                };
+
+                if db.is_banned(msg.chat.id.0).await? {
+                    bot.send_message(msg.chat.id, text::PROFILE_BANNED).await?;
+                    return Ok(());
                 }
 
                 db.create_or_update_user(UserSettings {
@@ -314,7 +518,10 @@ async fn answer(
                     ..UserSettings::with_id(msg.chat.id.0)
                 })
                 .await?;
+                // The user is back, don't hide or disable the profile again
+                db.update_last_activity(msg.chat.id.0).await?;
                 bot.send_message(msg.chat.id, text::PROFILE_ENABLED).await?;
+                rollover::ask_if_stale(&bot, &db, &user).await?;
             }
             Command::Disable => {
                 if db.get_user(msg.chat.id.0).await?.is_none() {
@@ -330,6 +537,31 @@ async fn answer(
                 .await?;
                 bot.send_message(msg.chat.id, text::PROFILE_DISABLED).await?;
             }
+            Command::Event => {
+                if db.get_user(msg.chat.id.0).await?.is_none() {
+                    bot.send_message(msg.chat.id, text::PLEASE_CREATE_PROFILE)
+                        .await?;
+                    return Ok(());
+                }
+
+                events::request_event(&bot, &db, msg.chat.id).await?;
+            }
+            Command::Subscribe | Command::Unsubscribe => {
+                if db.get_user(msg.chat.id.0).await?.is_none() {
+                    bot.send_message(msg.chat.id, text::PLEASE_CREATE_PROFILE)
+                        .await?;
+                    return Ok(());
+                }
+
+                let opt_out = matches!(cmd, Command::Unsubscribe);
+                db.set_broadcast_opt_out(msg.chat.id.0, opt_out).await?;
+                let reply = if opt_out {
+                    text::BROADCAST_OPTED_OUT
+                } else {
+                    text::BROADCAST_OPTED_IN
+                };
+                bot.send_message(msg.chat.id, reply).await?;
+            }
             Command::Start => {
                 db.create_state(msg.chat.id.0).await?;
 
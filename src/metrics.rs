@@ -0,0 +1,145 @@
+use std::{
+    convert::Infallible,
+    net::SocketAddr,
+    sync::{Arc, LazyLock},
+};
+
+use anyhow::Context;
+use hyper::{
+    header::CONTENT_TYPE,
+    service::{make_service_fn, service_fn},
+    Body, Response, Server, StatusCode,
+};
+use prometheus::{
+    register_histogram, register_int_counter, register_int_counter_vec,
+    register_int_gauge, Encoder, Histogram, IntCounter, IntCounterVec,
+    IntGauge, TextEncoder,
+};
+use teloxide::RequestError;
+
+use crate::{db::Database, State};
+
+pub static UPDATES: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "bvilovebot_updates_total",
+        "Handled updates by type and command, callback code or dialogue state",
+        &["type", "kind"],
+    )
+    .expect("can't register metric")
+});
+pub static TRANSITIONS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "bvilovebot_state_transitions_total",
+        "Dialogue state changes",
+        &["from", "to"],
+    )
+    .expect("can't register metric")
+});
+pub static PARTNER_SEARCHES: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "bvilovebot_partner_searches_total",
+        "Partner searches by whether a partner was found",
+        &["result"],
+    )
+    .expect("can't register metric")
+});
+pub static PARTNER_SEARCH_SECONDS: LazyLock<Histogram> = LazyLock::new(|| {
+    register_histogram!(
+        "bvilovebot_partner_search_seconds",
+        "Latency of the partner search",
+        vec![0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0],
+    )
+    .expect("can't register metric")
+});
+pub static LIKES: LazyLock<IntCounter> = LazyLock::new(|| {
+    register_int_counter!("bvilovebot_likes_total", "Sent likes")
+        .expect("can't register metric")
+});
+pub static MATCHES: LazyLock<IntCounter> = LazyLock::new(|| {
+    register_int_counter!("bvilovebot_matches_total", "Mutual likes")
+        .expect("can't register metric")
+});
+pub static API_ERRORS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "bvilovebot_api_errors_total",
+        "Telegram API errors by kind",
+        &["error"],
+    )
+    .expect("can't register metric")
+});
+static ACTIVE_USERS: LazyLock<IntGauge> = LazyLock::new(|| {
+    register_int_gauge!("bvilovebot_active_users", "Enabled profiles")
+        .expect("can't register metric")
+});
+
+/// Count the dialogue state change, if there was one
+pub fn transition(from: &State, to: &State) {
+    let from: &'static str = from.into();
+    let to: &'static str = to.into();
+    if from != to {
+        TRANSITIONS.with_label_values(&[from, to]).inc();
+    }
+}
+
+/// Count the error if it came from Telegram
+pub fn api_error(error: &RequestError) {
+    if let RequestError::Api(e) = error {
+        // Drop the description of unknown errors, it has unbounded values
+        let kind = format!("{e:?}");
+        let kind = kind.split('(').next().unwrap_or_default();
+        API_ERRORS.with_label_values(&[kind]).inc();
+    }
+}
+
+/// All metrics in the Prometheus text format
+pub async fn render(db: &Database) -> anyhow::Result<String> {
+    let active_users = db.count_active_users().await?;
+    ACTIVE_USERS.set(active_users.try_into()?);
+    // Metrics are registered on the first use, export unused ones as zeros
+    LazyLock::force(&PARTNER_SEARCH_SECONDS);
+    LazyLock::force(&LIKES);
+    LazyLock::force(&MATCHES);
+
+    let mut out = Vec::new();
+    TextEncoder::new().encode(&prometheus::gather(), &mut out)?;
+    Ok(String::from_utf8(out)?)
+}
+
+/// Address of the metrics server from `METRICS_ADDR` env var, the server
+/// is disabled if it isn't set
+pub fn addr_from_env() -> anyhow::Result<Option<SocketAddr>> {
+    std::env::var("METRICS_ADDR")
+        .ok()
+        .map(|addr| addr.parse().context("can't parse METRICS_ADDR"))
+        .transpose()
+}
+
+/// Serve metrics on every path of the address
+pub async fn serve(addr: SocketAddr, db: Arc<Database>) -> anyhow::Result<()> {
+    let make_service = make_service_fn(move |_| {
+        let db = db.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |_| {
+                let db = db.clone();
+                async move { Ok::<_, Infallible>(respond(&db).await) }
+            }))
+        }
+    });
+    Server::try_bind(&addr)?.serve(make_service).await?;
+    Ok(())
+}
+
+async fn respond(db: &Database) -> Response<Body> {
+    match render(db).await {
+        Ok(metrics) => Response::builder()
+            .header(CONTENT_TYPE, TextEncoder::new().format_type())
+            .body(Body::from(metrics)),
+        Err(e) => {
+            sentry_anyhow::capture_anyhow(&e.context("can't render metrics"));
+            Response::builder()
+                .status(StatusCode::INTERNAL_SERVER_ERROR)
+                .body(Body::empty())
+        }
+    }
+    .expect("metrics response must be valid")
+}
@@ -0,0 +1,323 @@
+use std::{fmt::Display, str::FromStr};
+
+use anyhow::{bail, Context};
+use chrono::Datelike;
+use entities::{
+    achievements,
+    sea_orm_active_enums::{Diploma, OlympiadLevel},
+};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use strsim::jaro_winkler;
+
+use crate::types::Subject;
+
+include!(concat!(env!("OUT_DIR"), "/olympiadsmap.rs"));
+
+/// Olympiad from `olympiads.csv`
+#[derive(Debug)]
+pub struct OlympiadInfo {
+    pub name: &'static str,
+    pub organizer: &'static str,
+    /// Subjects the olympiad is held in
+    pub subjects: &'static [Subject],
+    /// Level in the list of olympiads, `Final` for olympiads with stages
+    /// like the all-russian one
+    pub level: OlympiadLevel,
+    /// Months when the olympiad usually takes place
+    pub months: &'static [u32],
+    /// Stages a diploma can be of, empty for listed olympiads
+    pub stages: &'static [OlympiadLevel],
+}
+
+/// Levels from the lowest, the same order as in the database
+pub const LEVELS: [OlympiadLevel; 7] = [
+    OlympiadLevel::School,
+    OlympiadLevel::Municipal,
+    OlympiadLevel::Regional,
+    OlympiadLevel::Level3,
+    OlympiadLevel::Level2,
+    OlympiadLevel::Level1,
+    OlympiadLevel::Final,
+];
+
+/// Diplomas from the lowest
+pub const DIPLOMAS: [Diploma; 3] =
+    [Diploma::Participant, Diploma::PrizeWinner, Diploma::Winner];
+
+/// Stages of the all-russian olympiad and levels of the listed ones
+pub const fn level_name(level: &OlympiadLevel) -> &'static str {
+    match level {
+        OlympiadLevel::School => "школьный этап",
+        OlympiadLevel::Municipal => "муниципальный этап",
+        OlympiadLevel::Regional => "региональный этап",
+        OlympiadLevel::Final => "заключительный этап",
+        OlympiadLevel::Level1 => "I уровень",
+        OlympiadLevel::Level2 => "II уровень",
+        OlympiadLevel::Level3 => "III уровень",
+    }
+}
+
+pub const fn diploma_name(diploma: &Diploma) -> &'static str {
+    match diploma {
+        Diploma::Participant => "участник",
+        Diploma::PrizeWinner => "призёр",
+        Diploma::Winner => "победитель",
+    }
+}
+
+/// Filter of partner's achievements, the partner must have an achievement
+/// of the level or higher
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AchievementFilter(pub Option<OlympiadLevel>);
+
+impl AchievementFilter {
+    /// Options of the filter, from the softest
+    pub fn all() -> impl Iterator<Item = Self> {
+        std::iter::once(None).chain(LEVELS.into_iter().map(Some)).map(Self)
+    }
+}
+
+impl From<Option<OlympiadLevel>> for AchievementFilter {
+    fn from(value: Option<OlympiadLevel>) -> Self {
+        Self(value)
+    }
+}
+
+impl From<AchievementFilter> for Option<OlympiadLevel> {
+    fn from(value: AchievementFilter) -> Self {
+        value.0
+    }
+}
+
+impl FromStr for AchievementFilter {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::all()
+            .find(|filter| filter.to_string() == s)
+            .context("unknown achievement filter")
+    }
+}
+
+impl Display for AchievementFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self.0 {
+            None => "Не важно",
+            Some(OlympiadLevel::School) => "Любые олимпиады",
+            Some(OlympiadLevel::Municipal) => "Муниципальный этап и выше",
+            Some(OlympiadLevel::Regional) => "Региональный этап и выше",
+            Some(OlympiadLevel::Level3) => "Перечневые олимпиады и выше",
+            Some(OlympiadLevel::Level2) => "II уровень и выше",
+            Some(OlympiadLevel::Level1) => "I уровень и выше",
+            Some(OlympiadLevel::Final) => "Только заключительный этап",
+        })
+    }
+}
+
+/// Capitalized name for buttons
+pub fn capitalize(name: &str) -> String {
+    let mut chars = name.chars();
+    chars.next().map_or_else(String::new, |first| {
+        first.to_uppercase().chain(chars).collect()
+    })
+}
+
+fn parse_level(s: &str) -> anyhow::Result<OlympiadLevel> {
+    LEVELS
+        .into_iter()
+        .find(|level| {
+            level_name(level) == s || capitalize(level_name(level)) == s
+        })
+        .context("unknown olympiad level")
+}
+
+fn parse_diploma(s: &str) -> anyhow::Result<Diploma> {
+    DIPLOMAS
+        .into_iter()
+        .find(|diploma| {
+            diploma_name(diploma) == s || capitalize(diploma_name(diploma)) == s
+        })
+        .context("unknown diploma")
+}
+
+fn parse_subject(s: &str) -> anyhow::Result<Subject> {
+    Subject::all()
+        .find(|subject| subject.name() == s)
+        .context("unknown subject")
+}
+
+/// Olympiad from the catalog, the id is stored in the database
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "i32", into = "i32")]
+pub struct Olympiad(i32);
+
+impl Olympiad {
+    /// All olympiads in the order of the catalog
+    pub fn all() -> impl Iterator<Item = Self> {
+        OLYMPIADS.keys().sorted_unstable().map(|id| Self(*id))
+    }
+
+    /// `None` if the olympiad was removed from the catalog
+    pub fn info(self) -> Option<&'static OlympiadInfo> {
+        OLYMPIADS.get(&self.0)
+    }
+
+    pub fn name(self) -> &'static str {
+        self.info().map_or("Олимпиада", |info| info.name)
+    }
+
+    pub fn subjects(self) -> &'static [Subject] {
+        self.info().map_or(&[], |info| info.subjects)
+    }
+
+    /// Levels a diploma of the olympiad can have
+    pub fn levels(self) -> &'static [OlympiadLevel] {
+        self.info().map_or(&[], |info| {
+            if info.stages.is_empty() {
+                std::slice::from_ref(&info.level)
+            } else {
+                info.stages
+            }
+        })
+    }
+}
+
+impl TryFrom<i32> for Olympiad {
+    type Error = anyhow::Error;
+
+    fn try_from(id: i32) -> Result<Self, Self::Error> {
+        if !OLYMPIADS.contains_key(&id) {
+            bail!("olympiad not found");
+        }
+        Ok(Self(id))
+    }
+}
+
+impl From<Olympiad> for i32 {
+    fn from(olympiad: Olympiad) -> Self {
+        olympiad.0
+    }
+}
+
+impl FromStr for Olympiad {
+    type Err = anyhow::Error;
+
+    fn from_str(query: &str) -> Result<Self, Self::Err> {
+        if let Some(id) = OLYMPIADS_REV.get(query) {
+            return Ok(Self(*id));
+        }
+        let query = query.to_lowercase();
+        let (id, best) = OLYMPIADS
+            .entries()
+            .map(|(id, olympiad)| {
+                (id, jaro_winkler(&query, &olympiad.name.to_lowercase()))
+            })
+            .max_by(|(_, left), (_, right)| left.total_cmp(right))
+            .expect("there must be at least 1 olympiad");
+        if best > 0.8 {
+            Ok(Self(*id))
+        } else {
+            bail!("olympiad not found")
+        }
+    }
+}
+
+/// Olympiad result shown in the profile
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Achievement {
+    pub olympiad: Olympiad,
+    pub subject: Subject,
+    pub level: OlympiadLevel,
+    pub year: i16,
+    pub diploma: Diploma,
+}
+
+impl TryFrom<achievements::Model> for Achievement {
+    type Error = anyhow::Error;
+
+    fn try_from(value: achievements::Model) -> Result<Self, Self::Error> {
+        Ok(Self {
+            // Results of removed olympiads are still shown, without a name
+            olympiad: Olympiad(value.olympiad),
+            subject: value.subject.try_into()?,
+            level: value.level,
+            year: value.year,
+            diploma: value.diploma,
+        })
+    }
+}
+
+impl Display for Achievement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!(
+            "{}, {} ({}, {}, {})",
+            capitalize(diploma_name(&self.diploma)),
+            self.olympiad.name(),
+            self.subject.name(),
+            level_name(&self.level),
+            self.year
+        ))
+    }
+}
+
+/// Achievement being entered in the profile editor, fields are asked in
+/// the order of declaration
+#[derive(Clone, Default, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AchievementDraft {
+    pub olympiad: Option<Olympiad>,
+    pub subject: Option<Subject>,
+    pub level: Option<OlympiadLevel>,
+    pub year: Option<i16>,
+}
+
+impl AchievementDraft {
+    /// Fill the next field from the answer, returns the achievement once
+    /// it's complete and resets the draft
+    pub fn answer(&mut self, s: &str) -> anyhow::Result<Option<Achievement>> {
+        match self {
+            Self { olympiad: None, .. } => {
+                let olympiad: Olympiad = s.parse()?;
+                // Listed olympiads have the only level
+                if let [level] = olympiad.levels() {
+                    self.level = Some(level.clone());
+                }
+                self.olympiad = Some(olympiad);
+            }
+            Self { olympiad: Some(olympiad), subject: None, .. } => {
+                let subject = parse_subject(s)?;
+                if !olympiad.subjects().contains(&subject) {
+                    bail!("the olympiad isn't held in the subject");
+                }
+                self.subject = Some(subject);
+            }
+            Self { olympiad: Some(olympiad), level: None, .. } => {
+                let level = parse_level(s)?;
+                if !olympiad.levels().contains(&level) {
+                    bail!("the olympiad doesn't have the level");
+                }
+                self.level = Some(level);
+            }
+            Self { year: None, .. } => {
+                let year = s.parse()?;
+                let now = i16::try_from(chrono::Local::now().year())?;
+                if !(1990..=now).contains(&year) {
+                    bail!("wrong year");
+                }
+                self.year = Some(year);
+            }
+            Self { olympiad, subject, level, year } => {
+                let achievement = Achievement {
+                    olympiad: olympiad.context("olympiad must be set")?,
+                    subject: subject.context("subject must be set")?,
+                    level: level.clone().context("level must be set")?,
+                    year: year.context("year must be set")?,
+                    diploma: parse_diploma(s)?,
+                };
+                *self = Self::default();
+                return Ok(Some(achievement));
+            }
+        }
+        Ok(None)
+    }
+}
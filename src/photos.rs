@@ -0,0 +1,343 @@
+use std::{
+    collections::BTreeMap,
+    sync::Mutex,
+    time::Duration,
+};
+
+use anyhow::{bail, Context};
+use entities::{images, sea_orm_active_enums::ImageKind};
+use teloxide::{
+    prelude::*,
+    types::{
+        FileMeta, InputFile, InputMedia, InputMediaPhoto, InputMediaVideo,
+        KeyboardButton, KeyboardMarkup,
+    },
+    ApiError, RequestError,
+};
+
+use crate::{callbacks::PhotosAction, text, Bot, StateData};
+
+/// Most photos and videos a profile can have, Telegram doesn't send bigger
+/// albums
+pub const MAX_PHOTOS: usize = 10;
+
+/// Longest voice intro or video note in seconds
+const MAX_DURATION: u32 = 60;
+
+/// Bots can't download bigger files
+const MAX_SIZE: u32 = 20 * 1024 * 1024;
+
+/// Telegram sends every item of an album as a separate message, the album is
+/// complete when no items arrived for this time
+const ALBUM_WINDOW: Duration = Duration::from_secs(1);
+
+/// Finished albums are remembered this long, so items handled late still
+/// belong to their album
+const ALBUM_TTL: Duration = Duration::from_mins(1);
+
+/// Recent albums, by `media_group_id`
+static ALBUMS: Mutex<BTreeMap<String, Album>> = Mutex::new(BTreeMap::new());
+
+#[derive(Default)]
+struct Album {
+    /// Staged items of the album
+    ids: Vec<i32>,
+    /// Number of images of the profile after the latest item
+    count: usize,
+    /// The album doesn't fit into the profile
+    rejected: bool,
+    /// Number of items handled so far, the reply waits until it stops growing
+    received: usize,
+    /// The reply was sent, later items join the album without a new one
+    done: bool,
+}
+
+/// File, kind and duration of the media in the message. Photos, videos and
+/// GIFs can be of any duration, it's 0 for them.
+fn file(msg: &Message) -> Option<(&FileMeta, ImageKind, u32)> {
+    Some(if let Some([.., photo]) = msg.photo() {
+        (&photo.file, ImageKind::Image, 0)
+    } else if let Some(video) = msg.video() {
+        (&video.file, ImageKind::Video, 0)
+    } else if let Some(voice) = msg.voice() {
+        (&voice.file, ImageKind::Voice, voice.duration)
+    } else if let Some(note) = msg.video_note() {
+        (&note.file, ImageKind::VideoNote, note.duration)
+    } else {
+        (&msg.animation()?.file, ImageKind::Animation, 0)
+    })
+}
+
+/// File of the profile media in the message, `None` if there is none. `Err`
+/// has the text for the user if the media is too long or too big.
+pub fn media(
+    msg: &Message,
+) -> Option<Result<(&FileMeta, ImageKind), &'static str>> {
+    let (file, kind, duration) = file(msg)?;
+    Some(if file.size > MAX_SIZE {
+        Err(text::MEDIA_TOO_BIG)
+    } else if duration > MAX_DURATION {
+        Err(text::MEDIA_TOO_LONG)
+    } else {
+        Ok((file, kind))
+    })
+}
+
+/// Stage the media from the message. Items of an album are staged as they
+/// arrive with one reply for the whole album once no items arrived for
+/// [`ALBUM_WINDOW`], albums that don't fit are dropped as a whole.
+pub async fn upload(
+    bot: &Bot,
+    data: &mut StateData,
+    msg: &Message,
+    file: &FileMeta,
+    kind: ImageKind,
+) -> anyhow::Result<()> {
+    let first_id = data.images.iter().map(|image| image.id).min();
+    let image = images::Model {
+        id: first_id.unwrap_or_default().min(0) - 1,
+        user_id: data.s.id,
+        telegram_id: file.id.clone(),
+        kind,
+        position: 0,
+        file_unique_id: Some(file.unique_id.clone()),
+    };
+    let id = image.id;
+    let added = stage(&mut data.images, image);
+    let Some(group) = msg.media_group_id() else {
+        return reply(bot, msg.chat.id, added).await;
+    };
+
+    let (first, late_rejection) = {
+        let mut albums = ALBUMS.lock().unwrap();
+        let first = !albums.contains_key(group);
+        let album = albums.entry(group.to_owned()).or_default();
+        album.received += 1;
+        let was_rejected = album.rejected;
+        if album.rejected {
+            data.images.retain(|image| image.id != id);
+        } else if let Some(count) = added {
+            album.ids.push(id);
+            album.count = count;
+        } else {
+            data.images.retain(|image| !album.ids.contains(&image.id));
+            album.rejected = true;
+        }
+        // A late item can still make the whole album not fit
+        let late_rejection = album.done && album.rejected && !was_rejected;
+        drop(albums);
+        (first, late_rejection)
+    };
+    if late_rejection {
+        return reply(bot, msg.chat.id, None).await;
+    }
+    if first {
+        let bot = bot.clone();
+        let (chat, group) = (msg.chat.id, group.to_owned());
+        tokio::spawn(async move {
+            let count = loop {
+                let received = received(&group);
+                tokio::time::sleep(ALBUM_WINDOW).await;
+                let mut albums = ALBUMS.lock().unwrap();
+                let Some(album) = albums.get_mut(&group) else {
+                    return;
+                };
+                let finished = album.received == received;
+                album.done = finished;
+                let count = (!album.rejected).then_some(album.count);
+                drop(albums);
+                if finished {
+                    break count;
+                }
+            };
+            if let Err(e) = reply(&bot, chat, count).await {
+                sentry_anyhow::capture_anyhow(&e.context("can't reply album"));
+            }
+            tokio::time::sleep(ALBUM_TTL).await;
+            ALBUMS.lock().unwrap().remove(&group);
+        });
+    }
+    Ok(())
+}
+
+/// Number of handled items of the album
+fn received(group: &str) -> usize {
+    ALBUMS.lock().unwrap().get(group).map_or(0, |album| album.received)
+}
+
+/// Add the image if there is room for it, returns the new number of images
+fn stage(
+    images: &mut Vec<images::Model>,
+    image: images::Model,
+) -> Option<usize> {
+    (images.len() < MAX_PHOTOS).then(|| {
+        images.push(image);
+        images.len()
+    })
+}
+
+/// Tell the number of images, `None` if the upload didn't fit
+async fn reply(
+    bot: &Bot,
+    chat: ChatId,
+    count: Option<usize>,
+) -> anyhow::Result<()> {
+    let text = count.map_or_else(
+        || format!("Невозможно добавить более {MAX_PHOTOS} фото/видео"),
+        |count| {
+            format!("Добавлено {count}/{MAX_PHOTOS} фото/видео. Добавить ещё?")
+        },
+    );
+    let keyboard = vec![vec![KeyboardButton::new("Сохранить")]];
+    bot.send_message(chat, text)
+        .reply_markup(KeyboardMarkup::new(keyboard).resize_keyboard(true))
+        .await?;
+    Ok(())
+}
+
+/// Send the media of a profile, photos and videos in one album and the rest
+/// one by one. `numbered` puts the positions in the captions. Files with dead
+/// ids are uploaded from the archive, returns their new ids to store.
+pub async fn send(
+    bot: &Bot,
+    chat: ChatId,
+    images: &[images::Model],
+    numbered: bool,
+) -> Result<Vec<(i32, String)>, RequestError> {
+    let (album, rest): (Vec<_>, Vec<_>) =
+        images.iter().zip(1..).partition(|(image, _)| {
+            matches!(image.kind, ImageKind::Image | ImageKind::Video)
+        });
+    let new_id = |(&(image, _), message): (&(&images::Model, _), Message)| {
+        Some((image.id, file(&message)?.0.id.clone()))
+    };
+
+    let mut refreshed = Vec::new();
+    if !album.is_empty() {
+        if let Err(e) = send_album(bot, chat, &album, numbered, false).await {
+            if !is_dead_file(&e) {
+                return Err(e);
+            }
+            let messages = send_album(bot, chat, &album, numbered, true).await?;
+            refreshed.extend(album.iter().zip(messages).filter_map(new_id));
+        }
+    }
+    for item in &rest {
+        if let Err(e) = send_one(bot, chat, *item, numbered, false).await {
+            if !is_dead_file(&e) {
+                return Err(e);
+            }
+            let message = send_one(bot, chat, *item, numbered, true).await?;
+            refreshed.extend(new_id((item, message)));
+        }
+    }
+    Ok(refreshed)
+}
+
+fn is_dead_file(e: &RequestError) -> bool {
+    match e {
+        RequestError::Api(
+            ApiError::WrongFileId
+            | ApiError::WrongFileIdOrUrl
+            | ApiError::FileIdInvalid,
+        ) => true,
+        RequestError::Api(ApiError::Unknown(description)) => {
+            description.contains("file identifier")
+        }
+        _ => false,
+    }
+}
+
+/// The local copy if `from_archive` and there is one, the file id otherwise
+async fn input(image: &images::Model, from_archive: bool) -> InputFile {
+    if from_archive {
+        let unique_id = image.file_unique_id.as_deref();
+        if let Some(path) = crate::archive::find(unique_id).await {
+            return InputFile::file(path);
+        }
+    }
+    InputFile::file_id(&image.telegram_id)
+}
+
+async fn send_album(
+    bot: &Bot,
+    chat: ChatId,
+    album: &[(&images::Model, usize)],
+    numbered: bool,
+    from_archive: bool,
+) -> Result<Vec<Message>, RequestError> {
+    let mut media = Vec::new();
+    for &(image, n) in album {
+        let file = input(image, from_archive).await;
+        let caption = numbered.then(|| n.to_string());
+        media.push(if image.kind == ImageKind::Video {
+            InputMedia::Video(InputMediaVideo {
+                caption,
+                ..InputMediaVideo::new(file)
+            })
+        } else {
+            InputMedia::Photo(InputMediaPhoto {
+                caption,
+                ..InputMediaPhoto::new(file)
+            })
+        });
+    }
+    bot.send_media_group(chat, media).await
+}
+
+async fn send_one(
+    bot: &Bot,
+    chat: ChatId,
+    (image, n): (&images::Model, usize),
+    numbered: bool,
+    from_archive: bool,
+) -> Result<Message, RequestError> {
+    let file = input(image, from_archive).await;
+    let caption = if numbered { n.to_string() } else { String::new() };
+    match image.kind {
+        ImageKind::Voice => bot.send_voice(chat, file).caption(caption).await,
+        // Video notes can't have captions
+        ImageKind::VideoNote => {
+            if numbered {
+                bot.send_message(chat, caption).await?;
+            }
+            bot.send_video_note(chat, file).await
+        }
+        _ => bot.send_animation(chat, file).caption(caption).await,
+    }
+}
+
+/// Delete or move the staged image. The ids are the ones shown to the user,
+/// so buttons of an outdated manager can't touch other images.
+pub fn apply(
+    images: &mut Vec<images::Model>,
+    action: &PhotosAction,
+) -> anyhow::Result<()> {
+    let (PhotosAction::Up(id)
+    | PhotosAction::Down(id)
+    | PhotosAction::First(id)
+    | PhotosAction::Delete(id)) = action
+    else {
+        bail!("the action doesn't change images")
+    };
+    let index = images
+        .iter()
+        .position(|image| image.id == *id)
+        .context("image not found")?;
+
+    match action {
+        PhotosAction::Up(_) if index > 0 => images.swap(index - 1, index),
+        PhotosAction::Down(_) if index + 1 < images.len() => {
+            images.swap(index, index + 1);
+        }
+        PhotosAction::First(_) => {
+            let image = images.remove(index);
+            images.insert(0, image);
+        }
+        PhotosAction::Delete(_) => {
+            images.remove(index);
+        }
+        _ => bail!("the image can't be moved further"),
+    }
+    Ok(())
+}
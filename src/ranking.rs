@@ -0,0 +1,114 @@
+use anyhow::Context;
+use entities::users;
+use sea_orm::sea_query::{Expr, SimpleExpr};
+
+use crate::db::user_city;
+
+/// Weights of the partner ranking, the candidate with the highest score is
+/// recommended. Every weight can be overridden with the `RANKING_*` env var,
+/// e.g. `RANKING_RANDOM=0.5`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RankingWeights {
+    /// Per subject both users study
+    pub subjects: f64,
+    /// Penalty per year of graduation year difference
+    pub grade: f64,
+    pub same_city: f64,
+    pub same_subject: f64,
+    pub same_county: f64,
+    /// Penalty per day since candidate's last activity
+    pub inactivity: f64,
+    /// Candidate has already liked the user
+    pub liked: f64,
+    /// Multiplier of a random number from 0 to 1, so the feed isn't the same
+    /// every time
+    pub random: f64,
+}
+
+impl Default for RankingWeights {
+    fn default() -> Self {
+        Self {
+            subjects: 1.0,
+            grade: 1.0,
+            same_city: 2.0,
+            same_subject: 1.0,
+            same_county: 0.5,
+            inactivity: 0.2,
+            liked: 3.0,
+            random: 2.0,
+        }
+    }
+}
+
+impl RankingWeights {
+    pub fn from_env() -> anyhow::Result<Self> {
+        let default = Self::default();
+
+        let weight = |name: &str, default: f64| -> anyhow::Result<f64> {
+            let var = format!("RANKING_{name}");
+            std::env::var(&var).map_or(Ok(default), |w| {
+                w.parse().with_context(|| format!("can't parse {var}"))
+            })
+        };
+
+        Ok(Self {
+            subjects: weight("SUBJECTS", default.subjects)?,
+            grade: weight("GRADE", default.grade)?,
+            same_city: weight("SAME_CITY", default.same_city)?,
+            same_subject: weight("SAME_SUBJECT", default.same_subject)?,
+            same_county: weight("SAME_COUNTY", default.same_county)?,
+            inactivity: weight("INACTIVITY", default.inactivity)?,
+            liked: weight("LIKED", default.liked)?,
+            random: weight("RANDOM", default.random)?,
+        })
+    }
+
+    /// Score of the candidate (row of `users`) as a partner for the user
+    pub fn score(&self, user: &users::Model) -> SimpleExpr {
+        let city = user.city;
+        let subject = city.map(|c| (c >> 8) % 2i32.pow(8));
+        let county = city.map(|c| c >> 16);
+
+        Expr::cust_with_exprs(
+            r#"
+            $1 * cardinality(ARRAY(
+                SELECT unnest("users"."subjects") INTERSECT SELECT unnest($2)
+            ))
+            - $3 * abs("users"."graduation_year" - $4)
+            + $5 * coalesce(CAST($15 = $6 AS integer), 0)
+            + $7 * coalesce(CAST(($16 >> 8) % 256 = $8 AS integer), 0)
+            + $9 * coalesce(CAST($17 >> 16 = $10 AS integer), 0)
+            - $11 * extract(
+                epoch FROM current_timestamp - "users"."last_activity"
+            ) / 86400
+            + $12 * CAST(EXISTS (
+                SELECT 1 FROM "datings" AS "liked"
+                WHERE "liked"."initiator_id" = "users"."id"
+                    AND "liked"."partner_id" = $13
+                    AND "liked"."initiator_reaction"
+            ) AS integer)
+            + $14 * random()
+            "#,
+            [
+                Expr::value(self.subjects),
+                Expr::value(user.subjects.clone()),
+                Expr::value(self.grade),
+                Expr::value(user.graduation_year),
+                Expr::value(self.same_city),
+                Expr::value(city),
+                Expr::value(self.same_subject),
+                Expr::value(subject),
+                Expr::value(self.same_county),
+                Expr::value(county),
+                Expr::value(self.inactivity),
+                Expr::value(self.liked),
+                Expr::value(user.id),
+                Expr::value(self.random),
+                // A candidate at an event is ranked by the event city
+                user_city(),
+                user_city(),
+                user_city(),
+            ],
+        )
+    }
+}
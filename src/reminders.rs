@@ -0,0 +1,284 @@
+use std::{sync::Arc, time::Duration};
+
+use anyhow::Context;
+use entities::sea_orm_active_enums::Campaign;
+use teloxide::{
+    prelude::*,
+    types::{InlineKeyboardButton, InlineKeyboardMarkup},
+    ApiError, RequestError,
+};
+use tracing::*;
+
+use crate::{
+    callbacks::Callback, db::Database, text, types::UserSettings, Bot,
+};
+
+/// How often to look for users to remind
+const CHECK_PERIOD: Duration = Duration::from_hours(1);
+
+/// Reminders about unanswered likes. Can be configured with
+/// `LIKES_REMINDER_THRESHOLD` and `LIKES_REMINDER_INTERVAL` (in hours) env
+/// vars.
+#[derive(Clone, Debug)]
+pub struct LikesReminder {
+    /// Minimal number of unanswered likes to send a reminder
+    pub threshold: i64,
+    /// Minimal number of hours between reminders to the same user
+    pub interval_hours: i32,
+}
+
+impl Default for LikesReminder {
+    fn default() -> Self {
+        Self { threshold: 3, interval_hours: 24 }
+    }
+}
+
+impl LikesReminder {
+    pub fn from_env() -> anyhow::Result<Self> {
+        let default = Self::default();
+        Ok(Self {
+            threshold: std::env::var("LIKES_REMINDER_THRESHOLD").map_or(
+                Ok(default.threshold),
+                |v| v.parse().context("can't parse LIKES_REMINDER_THRESHOLD"),
+            )?,
+            interval_hours: std::env::var("LIKES_REMINDER_INTERVAL").map_or(
+                Ok(default.interval_hours),
+                |v| v.parse().context("can't parse LIKES_REMINDER_INTERVAL"),
+            )?,
+        })
+    }
+
+    /// Remind users about likes forever
+    pub async fn run(self, bot: Bot, db: Arc<Database>) {
+        let mut interval = tokio::time::interval(CHECK_PERIOD);
+        loop {
+            interval.tick().await;
+            if let Err(e) = self.remind(&bot, &db).await {
+                sentry_anyhow::capture_anyhow(
+                    &e.context("error while sending likes reminders"),
+                );
+            }
+        }
+    }
+
+    /// Send reminders to everyone who has enough unanswered likes and got
+    /// new ones since the last reminder
+    #[instrument(skip(self, bot, db))]
+    pub async fn remind(&self, bot: &Bot, db: &Database) -> anyhow::Result<()> {
+        let users = db
+            .get_users_to_remind_likes(self.threshold, self.interval_hours)
+            .await?;
+
+        for (id, likes, last_like) in users {
+            let keyboard = vec![vec![InlineKeyboardButton::callback(
+                "Посмотреть ❤",
+                Callback::Likes { page: 0 }.to_string(),
+            )]];
+            let keyboard_markup = InlineKeyboardMarkup::new(keyboard);
+
+            let msg = format!(
+                "Лайков без ответа: {likes}. Посмотреть их можно командой \
+                 /likes"
+            );
+
+            match bot
+                .send_message(ChatId(id), msg)
+                .reply_markup(keyboard_markup)
+                .await
+            {
+                Err(
+                    e @ RequestError::Api(
+                        ApiError::BotBlocked | ApiError::UserDeactivated,
+                    ),
+                ) => {
+                    warn!("bot was blocked");
+                    crate::metrics::api_error(&e);
+                    db.create_or_update_user(UserSettings {
+                        active: Some(false),
+                        ..UserSettings::with_id(id)
+                    })
+                    .await?;
+                }
+                Err(e) => {
+                    crate::metrics::api_error(&e);
+                    sentry_anyhow::capture_anyhow(
+                        &anyhow::Error::from(e)
+                            .context("error sending likes reminder"),
+                    );
+                }
+                Ok(_) => db.set_likes_reminded(id, last_like).await?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Campaigns that bring users back: news about new profiles for inactive
+/// users, a nudge to finish the profile for those who only pressed
+/// `/start`, and deactivation after a long silence. The nudge is sent only
+/// once, other campaigns at most once per cooldown. Configured with
+/// `REENGAGE_INACTIVE_DAYS`, `REENGAGE_UNFINISHED_DAYS`,
+/// `REENGAGE_DEACTIVATE_DAYS` and `REENGAGE_COOLDOWN_DAYS` env vars.
+// The postfix is the unit of the fields
+#[allow(clippy::struct_field_names)]
+#[derive(Clone, Debug)]
+pub struct Reengagement {
+    /// Days without activity before the news about new profiles
+    pub inactive_days: i32,
+    /// Days after `/start` before the reminder to create a profile
+    pub unfinished_days: i32,
+    /// Days without activity before the profile is disabled
+    pub deactivate_days: i32,
+    /// Minimal number of days between messages of the same campaign
+    pub cooldown_days: i32,
+}
+
+impl Default for Reengagement {
+    fn default() -> Self {
+        Self {
+            inactive_days: 7,
+            unfinished_days: 1,
+            deactivate_days: 30,
+            cooldown_days: 7,
+        }
+    }
+}
+
+impl Reengagement {
+    pub fn from_env() -> anyhow::Result<Self> {
+        let default = Self::default();
+        let days = |var: &str, default: i32| {
+            std::env::var(var).map_or(Ok(default), |v| {
+                v.parse().with_context(|| format!("can't parse {var}"))
+            })
+        };
+        Ok(Self {
+            inactive_days: days(
+                "REENGAGE_INACTIVE_DAYS",
+                default.inactive_days,
+            )?,
+            unfinished_days: days(
+                "REENGAGE_UNFINISHED_DAYS",
+                default.unfinished_days,
+            )?,
+            deactivate_days: days(
+                "REENGAGE_DEACTIVATE_DAYS",
+                default.deactivate_days,
+            )?,
+            cooldown_days: days(
+                "REENGAGE_COOLDOWN_DAYS",
+                default.cooldown_days,
+            )?,
+        })
+    }
+
+    /// Run the campaigns forever
+    pub async fn run(self, bot: Bot, db: Arc<Database>) {
+        let mut interval = tokio::time::interval(CHECK_PERIOD);
+        loop {
+            interval.tick().await;
+            if let Err(e) = self.remind(&bot, &db).await {
+                sentry_anyhow::capture_anyhow(
+                    &e.context("error while sending re-engagement campaigns"),
+                );
+            }
+        }
+    }
+
+    /// Send every campaign to the users it's due to
+    #[instrument(skip(self, bot, db))]
+    pub async fn remind(&self, bot: &Bot, db: &Database) -> anyhow::Result<()> {
+        // Deactivate first, so silent users don't get the news too
+        for id in db
+            .get_silent_users(self.deactivate_days, self.cooldown_days)
+            .await?
+        {
+            db.create_or_update_user(UserSettings {
+                active: Some(false),
+                ..UserSettings::with_id(id)
+            })
+            .await?;
+            let msg = bot.send_message(ChatId(id), text::PROFILE_DEACTIVATED);
+            deliver(db, id, Campaign::Deactivated, msg).await?;
+        }
+
+        for user in db
+            .get_inactive_users(
+                self.inactive_days,
+                self.deactivate_days,
+                self.cooldown_days,
+            )
+            .await?
+        {
+            let new_users = db.count_new_users_near(&user).await?;
+            if new_users == 0 {
+                continue;
+            }
+            let keyboard = vec![vec![InlineKeyboardButton::callback(
+                "Смотреть анкеты 🚀",
+                Callback::FindPartner.to_string(),
+            )]];
+            let msg = bot
+                .send_message(
+                    ChatId(user.id),
+                    format!(
+                        "Пока вас не было, рядом появились новые анкеты: \
+                         {new_users}. Посмотреть их можно командой /date"
+                    ),
+                )
+                .reply_markup(InlineKeyboardMarkup::new(keyboard));
+            deliver(db, user.id, Campaign::Inactive, msg).await?;
+        }
+
+        for id in db.get_unfinished_users(self.unfinished_days).await? {
+            let keyboard = vec![vec![InlineKeyboardButton::callback(
+                "Заполнить анкету ✍",
+                Callback::CreateProfile.to_string(),
+            )]];
+            let msg = bot
+                .send_message(ChatId(id), text::FINISH_PROFILE)
+                .reply_markup(InlineKeyboardMarkup::new(keyboard));
+            deliver(db, id, Campaign::Unfinished, msg).await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Send the campaign message and start the cooldown. Users who blocked the
+/// bot also get the cooldown, so they aren't retried every check.
+async fn deliver(
+    db: &Database,
+    id: i64,
+    campaign: Campaign,
+    msg: impl std::future::IntoFuture<Output = Result<Message, RequestError>>,
+) -> anyhow::Result<()> {
+    match msg.await {
+        Err(
+            e @ RequestError::Api(
+                ApiError::BotBlocked | ApiError::UserDeactivated,
+            ),
+        ) => {
+            warn!("bot was blocked");
+            crate::metrics::api_error(&e);
+            // Users without a profile have nothing to deactivate
+            if campaign != Campaign::Unfinished {
+                db.create_or_update_user(UserSettings {
+                    active: Some(false),
+                    ..UserSettings::with_id(id)
+                })
+                .await?;
+            }
+        }
+        Err(e) => {
+            crate::metrics::api_error(&e);
+            sentry_anyhow::capture_anyhow(
+                &anyhow::Error::from(e).context("error sending campaign"),
+            );
+            return Ok(());
+        }
+        Ok(_) => {}
+    }
+    db.set_campaign_delivered(id, campaign).await
+}
@@ -0,0 +1,193 @@
+use anyhow::{ensure, Context};
+use entities::{
+    datings, reports,
+    sea_orm_active_enums::{ReportReason, ReportResolution},
+};
+use sea_orm::Iterable;
+use teloxide::{
+    prelude::*,
+    types::{InlineKeyboardButton, InlineKeyboardMarkup},
+};
+use tracing::*;
+
+use crate::{
+    callbacks::{Callback, ReportStep},
+    db::Database,
+    text, Bot,
+};
+
+const fn reason_name(reason: &ReportReason) -> &'static str {
+    match reason {
+        ReportReason::Name => "Имя",
+        ReportReason::About => "Текст анкеты",
+        ReportReason::Photos => "Фото",
+        ReportReason::Message => "Сообщение",
+        ReportReason::Other => "Другое",
+    }
+}
+
+const fn resolution_name(resolution: &ReportResolution) -> &'static str {
+    match resolution {
+        ReportResolution::Ban => "🔨 Забанить",
+        ReportResolution::HidePhotos => "🙈 Скрыть фото",
+        ReportResolution::Dismiss => "✅ Отклонить",
+    }
+}
+
+/// Chat where reports are sent to, `ADMIN_CHAT_ID` env var
+pub fn admin_chat() -> Option<ChatId> {
+    std::env::var("ADMIN_CHAT_ID").ok()?.parse().ok().map(ChatId)
+}
+
+/// Ask the reason of the report in reply to the reported message
+pub async fn request_reason(
+    bot: &Bot,
+    msg: &Message,
+    dating_id: i32,
+) -> anyhow::Result<()> {
+    let mut keyboard: Vec<Vec<_>> = ReportReason::iter()
+        .map(|reason| {
+            vec![InlineKeyboardButton::callback(
+                reason_name(&reason),
+                Callback::Report { dating_id, step: ReportStep::Reason(reason) }
+                    .to_string(),
+            )]
+        })
+        .collect();
+    keyboard.push(vec![InlineKeyboardButton::callback(
+        "Отмена",
+        Callback::Report { dating_id, step: ReportStep::Cancel }.to_string(),
+    )]);
+
+    bot.send_message(msg.chat.id, text::REQUEST_REPORT_REASON)
+        .reply_to_message_id(msg.id)
+        .reply_markup(InlineKeyboardMarkup::new(keyboard))
+        .await?;
+    Ok(())
+}
+
+/// Save the report on the other user of the dating and send it to
+/// moderators
+pub async fn report(
+    bot: &Bot,
+    db: &Database,
+    dating: &datings::Model,
+    reporter: i64,
+    reason: ReportReason,
+    text: String,
+) -> anyhow::Result<()> {
+    let offender = if dating.initiator_id == reporter {
+        dating.partner_id
+    } else {
+        dating.initiator_id
+    };
+    let report = db.create_report(reporter, offender, reason, text).await?;
+
+    if let Err(e) = send_to_moderators(bot, db, &report).await {
+        sentry_anyhow::capture_anyhow(
+            &e.context("error sending report to moderators"),
+        );
+    }
+    Ok(())
+}
+
+async fn send_to_moderators(
+    bot: &Bot,
+    db: &Database,
+    report: &reports::Model,
+) -> anyhow::Result<()> {
+    let Some(chat) = admin_chat() else {
+        warn!("ADMIN_CHAT_ID isn't set, report {} isn't sent", report.id);
+        return Ok(());
+    };
+
+    crate::datings::send_user_photos(bot, db, report.reported_id, chat.0)
+        .await?;
+
+    let keyboard = vec![ReportResolution::iter()
+        .map(|resolution| {
+            InlineKeyboardButton::callback(
+                resolution_name(&resolution),
+                Callback::Moderate { report_id: report.id, resolution }
+                    .to_string(),
+            )
+        })
+        .collect::<Vec<_>>()];
+
+    let msg = format!(
+        "Жалоба #{}\nПричина: {}\nОтправитель: {}\nНарушитель: {}\n\n{}",
+        report.id,
+        reason_name(&report.reason),
+        report.reporter_id,
+        report.reported_id,
+        report.text
+    );
+    bot.send_message(chat, msg)
+        .reply_markup(InlineKeyboardMarkup::new(keyboard))
+        .await?;
+    Ok(())
+}
+
+/// Apply moderator's decision on the report
+pub async fn moderate(
+    bot: &Bot,
+    db: &Database,
+    q: &CallbackQuery,
+    msg: &Message,
+    report_id: i32,
+    resolution: ReportResolution,
+) -> anyhow::Result<()> {
+    ensure!(
+        Some(msg.chat.id) == admin_chat(),
+        "moderation outside of the admin chat"
+    );
+    // Not every member of the admin chat is a moderator
+    if !i64::try_from(q.from.id.0).is_ok_and(crate::admin::is_admin) {
+        bot.answer_callback_query(&q.id)
+            .text(text::NOT_MODERATOR)
+            .show_alert(true)
+            .await?;
+        return Ok(());
+    }
+
+    // Another moderator could press a button of the same report first
+    let Some(report) = db.resolve_report(report_id, resolution.clone()).await?
+    else {
+        bot.answer_callback_query(&q.id)
+            .text(text::REPORT_ALREADY_RESOLVED)
+            .show_alert(true)
+            .await?;
+        return Ok(());
+    };
+    let notification = match resolution {
+        ReportResolution::Ban => {
+            db.set_banned(report.reported_id, true).await?;
+            Some(text::PROFILE_BANNED)
+        }
+        ReportResolution::HidePhotos => {
+            let unused = db.clean_images(report.reported_id).await?;
+            crate::archive::update(bot, &[], &unused).await;
+            Some(text::PHOTOS_HIDDEN)
+        }
+        ReportResolution::Dismiss => None,
+    };
+    if let Some(notification) = notification {
+        if let Err(e) =
+            bot.send_message(ChatId(report.reported_id), notification).await
+        {
+            sentry_anyhow::capture_anyhow(
+                &anyhow::Error::from(e)
+                    .context("error notifying reported user"),
+            );
+        }
+    }
+
+    let report_text = msg.text().context("report message has no text")?;
+    bot.edit_message_text(
+        msg.chat.id,
+        msg.id,
+        format!("{report_text}\n\nРешение: {}", resolution_name(&resolution)),
+    )
+    .await?;
+    Ok(())
+}
@@ -1,14 +1,16 @@
 use anyhow::Context;
+use chrono::Datelike;
+use entities::images;
 use itertools::Itertools;
 use teloxide::{
     prelude::*,
     types::{
-        Chat, ChatKind, InlineKeyboardButton, InlineKeyboardMarkup,
-        KeyboardButton, KeyboardMarkup, KeyboardRemove,
+        Chat, ChatKind, KeyboardButton, KeyboardMarkup, KeyboardRemove,
     },
 };
 
 use crate::{
+    olympiads::{self, AchievementDraft, AchievementFilter, Olympiad},
     text,
     types::{DatingPurpose, Subjects},
     utils, Bot, StateData,
@@ -123,6 +125,25 @@ pub async fn set_grade(bot: &Bot, chat: &Chat) -> anyhow::Result<()> {
     Ok(())
 }
 
+pub async fn set_grade_filter(
+    bot: &Bot,
+    chat: &Chat,
+    data: &StateData,
+) -> anyhow::Result<()> {
+    bot.send_message(chat.id, text::REQUEST_GRADE_FILTER)
+        .reply_markup(utils::make_grade_filter_keyboard(
+            data.s.grade_up_filter.unwrap_or(1),
+            data.s.grade_down_filter.unwrap_or(1),
+            data.s
+                .stage
+                .as_ref()
+                .filter(|s| s.is_school())
+                .map(|_| data.s.show_university.unwrap_or_default()),
+        ))
+        .await?;
+    Ok(())
+}
+
 pub async fn set_subjects(
     bot: &Bot,
     chat: &Chat,
@@ -130,10 +151,12 @@ pub async fn set_subjects(
 ) -> anyhow::Result<()> {
     bot.send_message(chat.id, text::EDIT_SUBJECTS)
         .reply_markup(utils::make_subjects_keyboard(
-            data.s
+            &data
+                .s
                 .subjects
                 .clone()
                 .map_or_else(Subjects::default, |s| s.into()),
+            0,
             &utils::SubjectsKeyboardType::User,
         ))
         .await?;
@@ -160,10 +183,12 @@ pub async fn set_subjects_filter(
 ) -> anyhow::Result<()> {
     bot.send_message(chat.id, text::EDIT_PARTNER_SUBJECTS)
         .reply_markup(utils::make_subjects_keyboard(
-            data.s
+            &data
+                .s
                 .subjects_filter
                 .clone()
                 .map_or_else(Subjects::default, |s| s.into()),
+            0,
             &utils::SubjectsKeyboardType::Partner,
         ))
         .await?;
@@ -177,8 +202,97 @@ pub async fn set_about(bot: &Bot, chat: &Chat) -> anyhow::Result<()> {
     Ok(())
 }
 
-pub async fn set_photos(bot: &Bot, chat: &Chat) -> anyhow::Result<()> {
-    let keyboard = vec![vec![KeyboardButton::new("Без фото")]];
+/// Ask the next field of the achievement
+pub async fn set_achievements(
+    bot: &Bot,
+    chat: &Chat,
+    draft: &AchievementDraft,
+) -> anyhow::Result<()> {
+    let (msg, keyboard): (_, Vec<Vec<_>>) = match draft {
+        AchievementDraft { olympiad: None, .. } => {
+            let mut keyboard: Vec<Vec<_>> = Olympiad::all()
+                .map(|olympiad| vec![KeyboardButton::new(olympiad.name())])
+                .collect();
+            keyboard.push(vec![
+                KeyboardButton::new(text::ACHIEVEMENTS_DONE),
+                KeyboardButton::new(text::ACHIEVEMENTS_CLEAR),
+            ]);
+            (text::REQUEST_OLYMPIAD, keyboard)
+        }
+        AchievementDraft { olympiad: Some(olympiad), subject: None, .. } => {
+            let keyboard = olympiad
+                .subjects()
+                .iter()
+                .map(|s| s.name())
+                .sorted_unstable_by_key(|name| name.to_lowercase())
+                .map(KeyboardButton::new)
+                .chunks(3)
+                .into_iter()
+                .map(Iterator::collect)
+                .collect();
+            (text::REQUEST_OLYMPIAD_SUBJECT, keyboard)
+        }
+        AchievementDraft { olympiad: Some(olympiad), level: None, .. } => {
+            let keyboard = olympiad
+                .levels()
+                .iter()
+                .map(|level| {
+                    KeyboardButton::new(olympiads::capitalize(
+                        olympiads::level_name(level),
+                    ))
+                })
+                .chunks(2)
+                .into_iter()
+                .map(Iterator::collect)
+                .collect();
+            (text::REQUEST_OLYMPIAD_LEVEL, keyboard)
+        }
+        AchievementDraft { year: None, .. } => {
+            let year = chrono::Local::now().year();
+            let keyboard = vec![(year - 2..=year)
+                .map(|year| KeyboardButton::new(year.to_string()))
+                .collect()];
+            (text::REQUEST_OLYMPIAD_YEAR, keyboard)
+        }
+        _ => {
+            let keyboard = vec![olympiads::DIPLOMAS
+                .iter()
+                .map(|diploma| {
+                    KeyboardButton::new(olympiads::capitalize(
+                        olympiads::diploma_name(diploma),
+                    ))
+                })
+                .collect()];
+            (text::REQUEST_DIPLOMA, keyboard)
+        }
+    };
+    bot.send_message(chat.id, msg)
+        .reply_markup(KeyboardMarkup::new(keyboard).resize_keyboard(true))
+        .await?;
+    Ok(())
+}
+
+pub async fn set_achievements_filter(
+    bot: &Bot,
+    chat: &Chat,
+) -> anyhow::Result<()> {
+    let keyboard: Vec<Vec<_>> = AchievementFilter::all()
+        .map(|filter| vec![KeyboardButton::new(filter.to_string())])
+        .collect();
+    bot.send_message(chat.id, text::REQUEST_ACHIEVEMENTS_FILTER)
+        .reply_markup(KeyboardMarkup::new(keyboard).resize_keyboard(true))
+        .await?;
+    Ok(())
+}
+
+pub async fn set_photos(
+    bot: &Bot,
+    chat: &Chat,
+    data: &StateData,
+) -> anyhow::Result<()> {
+    // Photos are removed in the photo manager while editing
+    let button = if data.create_new { "Без фото" } else { "Сохранить" };
+    let keyboard = vec![vec![KeyboardButton::new(button)]];
     let keyboard_markup = KeyboardMarkup::new(keyboard).resize_keyboard(true);
     bot.send_message(chat.id, text::REQUEST_SET_PHOTOS)
         .reply_markup(keyboard_markup)
@@ -186,18 +300,49 @@ pub async fn set_photos(bot: &Bot, chat: &Chat) -> anyhow::Result<()> {
     Ok(())
 }
 
-pub async fn edit_profile(bot: &Bot, chat: &Chat) -> anyhow::Result<()> {
-    let keyboard: Vec<Vec<_>> =
-        ["Имя", "Предметы", "О себе", "Город", "Фото", "Отмена"]
-            .into_iter()
-            .map(|i| InlineKeyboardButton::callback(i, format!("e{i}")))
-            .chunks(3)
-            .into_iter()
-            .map(|row| row.collect())
-            .collect();
+/// Album with numbered images and buttons to change it
+pub async fn manage_photos(
+    bot: &Bot,
+    chat: &Chat,
+    images: &[images::Model],
+) -> anyhow::Result<()> {
+    let ids: Vec<_> = images.iter().map(|image| image.id).collect();
+    let keyboard = utils::make_photos_keyboard(&ids);
+    if images.is_empty() {
+        bot.send_message(chat.id, text::PHOTOS_EMPTY)
+            .reply_markup(keyboard)
+            .await?;
+        return Ok(());
+    }
 
+    // New ids of dead files are stored when the profile is sent
+    crate::photos::send(bot, chat.id, images, true).await?;
+    bot.send_message(chat.id, text::MANAGE_PHOTOS)
+        .reply_markup(keyboard)
+        .await?;
+    Ok(())
+}
+
+/// Staged edits of the profile with buttons to save or discard them
+pub async fn confirm_edit(
+    bot: &Bot,
+    chat: &Chat,
+    data: &StateData,
+) -> anyhow::Result<()> {
+    let changes = data.changes().join("\n");
+    let keyboard = vec![vec![
+        KeyboardButton::new("Сохранить"),
+        KeyboardButton::new("Отменить"),
+    ]];
+    bot.send_message(chat.id, format!("{}\n\n{changes}", text::CONFIRM_EDIT))
+        .reply_markup(KeyboardMarkup::new(keyboard).resize_keyboard(true))
+        .await?;
+    Ok(())
+}
+
+pub async fn edit_profile(bot: &Bot, chat: &Chat) -> anyhow::Result<()> {
     bot.send_message(chat.id, text::REQUEST_EDIT)
-        .reply_markup(InlineKeyboardMarkup::new(keyboard))
+        .reply_markup(utils::make_edit_keyboard(true))
         .await?;
     Ok(())
 }
@@ -0,0 +1,204 @@
+use std::{sync::Arc, time::Duration};
+
+use anyhow::Context;
+use entities::{sea_orm_active_enums::Campaign, users};
+use teloxide::{
+    prelude::*,
+    types::{InlineKeyboardButton, InlineKeyboardMarkup},
+    ApiError, RequestError,
+};
+use tracing::*;
+
+use crate::{
+    callbacks::Callback,
+    db::Database,
+    text,
+    types::{academic_year, Stage, UserSettings},
+    Bot, State, StateData,
+};
+
+/// How often to look for users with an unconfirmed grade
+const CHECK_PERIOD: Duration = Duration::from_hours(6);
+
+/// Yearly confirmation of the grade. Once the academic year starts, every
+/// school student is asked if the grade moved up as expected, so students
+/// who repeated a year or made a mistake can fix it, and those who have
+/// just finished school become graduates. Users who don't answer in
+/// `GRADE_CHECK_DEADLINE_DAYS` env var days are deactivated. The same
+/// question is asked by `/date` and `/enable`, so nobody matches with a
+/// stale grade before the job gets to them.
+#[derive(Clone, Debug)]
+pub struct GradeCheck {
+    /// Days to answer before the profile is disabled
+    pub deadline_days: i32,
+}
+
+impl Default for GradeCheck {
+    fn default() -> Self {
+        Self { deadline_days: 14 }
+    }
+}
+
+impl GradeCheck {
+    pub fn from_env() -> anyhow::Result<Self> {
+        Ok(Self {
+            deadline_days: std::env::var("GRADE_CHECK_DEADLINE_DAYS").map_or(
+                Ok(Self::default().deadline_days),
+                |v| v.parse().context("can't parse GRADE_CHECK_DEADLINE_DAYS"),
+            )?,
+        })
+    }
+
+    /// Check grades forever
+    pub async fn run(self, bot: Bot, db: Arc<Database>) {
+        let mut interval = tokio::time::interval(CHECK_PERIOD);
+        loop {
+            interval.tick().await;
+            if let Err(e) = self.remind(&bot, &db).await {
+                sentry_anyhow::capture_anyhow(
+                    &e.context("error while checking grades"),
+                );
+            }
+        }
+    }
+
+    /// Disable profiles of users who missed the deadline and ask everyone
+    /// who wasn't asked in this academic year
+    #[instrument(skip(self, bot, db))]
+    pub async fn remind(&self, bot: &Bot, db: &Database) -> anyhow::Result<()> {
+        let academic_year = academic_year();
+
+        for id in db
+            .get_users_missed_grade_check(academic_year, self.deadline_days)
+            .await?
+        {
+            db.create_or_update_user(UserSettings {
+                active: Some(false),
+                ..UserSettings::with_id(id)
+            })
+            .await?;
+            if let Err(e) =
+                bot.send_message(ChatId(id), text::GRADE_NOT_CONFIRMED).await
+            {
+                crate::metrics::api_error(&e);
+                warn!("can't notify about the deactivation: {e}");
+            }
+        }
+
+        for user in db.get_users_to_check_grade(academic_year).await? {
+            ask(bot, db, &user).await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Whether the user has to confirm the grade or the course of the current
+/// academic year
+pub fn is_stale(user: &users::Model) -> bool {
+    user.grade_confirmed < academic_year()
+        && match Stage::from(user) {
+            Stage::School(_) | Stage::University(_) => true,
+            // Only those who have just finished school
+            Stage::Graduate(year) => year == academic_year(),
+        }
+}
+
+/// Ask the user to confirm the grade if it's stale. Returns whether the
+/// question was sent.
+pub async fn ask_if_stale(
+    bot: &Bot,
+    db: &Database,
+    user: &users::Model,
+) -> anyhow::Result<bool> {
+    if !is_stale(user) {
+        return Ok(false);
+    }
+    ask(bot, db, user).await?;
+    Ok(true)
+}
+
+/// Handle the answer to the question, returns the state to change the grade
+/// if it's wrong
+pub async fn answer(
+    bot: &Bot,
+    db: &Database,
+    chat: ChatId,
+    confirmed: bool,
+) -> anyhow::Result<Option<State>> {
+    if confirmed {
+        db.set_grade_confirmed(chat.0, academic_year()).await?;
+        bot.send_message(chat, text::GRADE_CONFIRMED).await?;
+        return Ok(None);
+    }
+    let user = db.get_user(chat.0).await?.context("user not found")?;
+    Ok(Some(State::SetGraduationYear(StateData::with_settings(
+        user.try_into()?,
+    ))))
+}
+
+/// Ask the user to confirm the grade and start the deadline
+async fn ask(
+    bot: &Bot,
+    db: &Database,
+    user: &users::Model,
+) -> anyhow::Result<()> {
+    let (msg, yes, no) = match Stage::from(user) {
+        Stage::School(grade) => (
+            format!("Начался новый учебный год! Вы перешли в {grade}?"),
+            "Да",
+            "Нет, изменить класс",
+        ),
+        Stage::University(course) => (
+            format!("Начался новый учебный год! Вы перешли на {course}?"),
+            "Да",
+            "Нет, изменить курс",
+        ),
+        Stage::Graduate(_) => (
+            "Начался новый учебный год! Вы окончили школу? Если вы поступили \
+             в вуз, укажите курс"
+                .to_owned(),
+            "Да, я выпускник",
+            "Указать класс или курс",
+        ),
+    };
+    let keyboard = vec![vec![
+        InlineKeyboardButton::callback(
+            yes,
+            Callback::ConfirmGrade { confirmed: true }.to_string(),
+        ),
+        InlineKeyboardButton::callback(
+            no,
+            Callback::ConfirmGrade { confirmed: false }.to_string(),
+        ),
+    ]];
+
+    match bot
+        .send_message(ChatId(user.id), msg)
+        .reply_markup(InlineKeyboardMarkup::new(keyboard))
+        .await
+    {
+        Err(
+            e @ RequestError::Api(
+                ApiError::BotBlocked | ApiError::UserDeactivated,
+            ),
+        ) => {
+            warn!("bot was blocked");
+            crate::metrics::api_error(&e);
+            db.create_or_update_user(UserSettings {
+                active: Some(false),
+                ..UserSettings::with_id(user.id)
+            })
+            .await?;
+        }
+        Err(e) => {
+            crate::metrics::api_error(&e);
+            sentry_anyhow::capture_anyhow(
+                &anyhow::Error::from(e).context("error asking for the grade"),
+            );
+            return Ok(());
+        }
+        Ok(_) => {}
+    }
+    db.set_campaign_delivered(user.id, Campaign::GradeCheck).await
+}
@@ -0,0 +1,2119 @@
+use anyhow::Context;
+use entities::sea_orm_active_enums::{
+    Gender, ImageKind, ReportReason, ReportResolution,
+};
+use sea_orm::Iterable;
+use serde_json::json;
+
+use super::{TestEnv, TestUser};
+use crate::{
+    broadcasts::{Broadcaster, Delivery},
+    callbacks::{
+        Callback, EditField, PhotosAction, RateCode, ReportStep,
+        SubjectsAction, UpdateBitflags,
+    },
+    ranking::RankingWeights,
+    reminders::{LikesReminder, Reengagement},
+    rollover::GradeCheck,
+    text,
+    types::{academic_year, DatingPurpose, GradeFilter, Subject},
+};
+
+const ALICE: TestUser = TestUser::new(1001, "Alice");
+const BOB: TestUser = TestUser::new(1002, "Bob");
+const CAROL: TestUser = TestUser::new(1003, "Carol");
+const DAVE: TestUser = TestUser::new(1004, "Dave");
+const ADMIN: TestUser = TestUser::new(1999, "Admin");
+
+/// Walk through the whole `/create` wizard
+async fn create_profile(
+    env: &TestEnv,
+    user: &TestUser,
+    gender: &str,
+) -> anyhow::Result<()> {
+    fill_profile(env, user, gender).await?;
+    env.send_text(user, "Без фото").await?;
+    Ok(())
+}
+
+/// Walk through the `/create` wizard up to the photos
+async fn fill_profile(
+    env: &TestEnv,
+    user: &TestUser,
+    gender: &str,
+) -> anyhow::Result<()> {
+    env.register(user);
+    env.send_text(user, "/start").await?;
+    env.press(user, &Callback::CreateProfile.to_string()).await?;
+    env.send_text(user, user.first_name).await?;
+    env.send_text(user, gender).await?;
+    env.send_text(user, "Не важно").await?;
+    env.send_text(user, "10").await?;
+    env.press(
+        user,
+        &Callback::SetGradeFilter { up: 1, down: 1 }.to_string(),
+    )
+    .await?;
+    env.press(user, &Callback::SetSubjects(SubjectsAction::Page(1)).to_string())
+        .await?;
+    env.press(
+        user,
+        &Callback::SetSubjects(SubjectsAction::Toggle(Subject::Math))
+            .to_string(),
+    )
+    .await?;
+    env.press(
+        user,
+        &Callback::SetSubjects(SubjectsAction::Continue).to_string(),
+    )
+    .await?;
+    env.press(
+        user,
+        &Callback::SetSubjectsFilter(SubjectsAction::Continue).to_string(),
+    )
+    .await?;
+    env.press(
+        user,
+        &Callback::SetDatingPurpose(UpdateBitflags::Update(
+            DatingPurpose::Friendship,
+        ))
+        .to_string(),
+    )
+    .await?;
+    env.press(
+        user,
+        &Callback::SetDatingPurpose(UpdateBitflags::Continue).to_string(),
+    )
+    .await?;
+    env.send_text(user, "Москва").await?;
+    env.send_text(user, "Верно").await?;
+    env.send_text(user, "Вся Россия").await?;
+    env.send_text(user, "Люблю олимпиады").await?;
+    env.send_text(user, crate::text::ACHIEVEMENTS_DONE).await?;
+    env.send_text(user, "Не важно").await?;
+    Ok(())
+}
+
+/// Dating id from the recommendation's buttons
+fn dating_id(buttons: &[String]) -> anyhow::Result<i32> {
+    let data = buttons.first().context("no buttons")?;
+    match data.parse()? {
+        Callback::Dating { dating_id, .. } => Ok(dating_id),
+        _ => anyhow::bail!("not a dating button"),
+    }
+}
+
+#[tokio::test]
+#[ignore = "needs TEST_DATABASE_URL"]
+async fn profile_creation() -> anyhow::Result<()> {
+    let env = TestEnv::new().await?;
+    create_profile(&env, &ALICE, "Я девушка").await?;
+
+    let user = env.db.get_user(ALICE.id).await?.context("user not created")?;
+    assert_eq!(user.name, "Alice");
+    assert_eq!(user.subjects, [i32::from(Subject::Math)]);
+    assert_eq!(user.dating_purpose, DatingPurpose::Friendship.bits());
+    assert!(user.city.is_some());
+    assert!(env
+        .texts_to(&ALICE)
+        .iter()
+        .any(|t| t.starts_with("Так выглядит ваша анкета")));
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore = "needs TEST_DATABASE_URL"]
+async fn profile_saved_at_the_end() -> anyhow::Result<()> {
+    let env = TestEnv::new().await?;
+    fill_profile(&env, &ALICE, "Я девушка").await?;
+    assert!(env.db.get_user(ALICE.id).await?.is_none());
+
+    env.send_text(&ALICE, "Сохранить").await?;
+    assert!(env.db.get_user(ALICE.id).await?.is_some());
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore = "needs TEST_DATABASE_URL"]
+async fn edit_grade_filter() -> anyhow::Result<()> {
+    let env = TestEnv::new().await?;
+    create_profile(&env, &ALICE, "Я девушка").await?;
+
+    env.send_text(&ALICE, "/edit").await?;
+    env.press(&ALICE, "eКлассы").await?;
+    env.press(
+        &ALICE,
+        &Callback::SetGradeFilter { up: GradeFilter::ANY, down: 0 }
+            .to_string(),
+    )
+    .await?;
+    env.send_text(&ALICE, "Сохранить").await?;
+
+    let user = env.db.get_user(ALICE.id).await?.context("user not found")?;
+    assert_eq!(user.grade_up_filter, GradeFilter::ANY);
+    assert_eq!(user.grade_down_filter, 0);
+    assert!(env
+        .texts_to(&ALICE)
+        .iter()
+        .any(|t| t.contains("Вы ищете: 10 класс и старше")));
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore = "needs TEST_DATABASE_URL"]
+async fn subjects_picker() -> anyhow::Result<()> {
+    let env = TestEnv::new().await?;
+    create_profile(&env, &ALICE, "Я девушка").await?;
+    let subjects = |action| Callback::SetSubjects(action).to_string();
+
+    env.send_text(&ALICE, "/edit").await?;
+    env.press(&ALICE, "eПредметы").await?;
+    let buttons = env.last_buttons(&ALICE);
+    assert!(buttons.contains(&subjects(SubjectsAction::Page(1))));
+    let math = subjects(SubjectsAction::Toggle(Subject::Math));
+    assert!(!buttons.contains(&math));
+
+    env.press(&ALICE, &subjects(SubjectsAction::Page(1))).await?;
+    assert!(env.last_buttons(&ALICE).contains(&math));
+    env.press(&ALICE, &subjects(SubjectsAction::Page(2))).await?;
+    assert!(env
+        .last_buttons(&ALICE)
+        .contains(&subjects(SubjectsAction::Toggle(Subject::German))));
+    env.press(&ALICE, &subjects(SubjectsAction::TogglePage(2))).await?;
+    env.press(&ALICE, &subjects(SubjectsAction::Toggle(Subject::German)))
+        .await?;
+    assert!(env.last_buttons(&ALICE).iter().all(|data| data.len() <= 64));
+    env.press(&ALICE, &subjects(SubjectsAction::Continue)).await?;
+    assert!(env.texts_to(&ALICE).iter().any(|t| t
+        == "Предметы, которые вы ботаете: Английский 🇬🇧, Испанский 🇪🇸, \
+            Итальянский 🇮🇹, Китайский 🇨🇳, Математика 📐, Французский 🇫🇷."));
+
+    env.send_text(&ALICE, "/edit").await?;
+    env.press(&ALICE, "eПредметы").await?;
+    env.press(&ALICE, &subjects(SubjectsAction::Clear(0))).await?;
+    assert!(!env
+        .last_buttons(&ALICE)
+        .contains(&subjects(SubjectsAction::Clear(0))));
+    env.press(&ALICE, &subjects(SubjectsAction::Continue)).await?;
+    assert!(env.texts_to(&ALICE).iter().any(|t| t == "Вы ничего не ботаете."));
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore = "needs TEST_DATABASE_URL"]
+async fn edit_every_field() -> anyhow::Result<()> {
+    let env = TestEnv::new().await?;
+    create_profile(&env, &ALICE, "Я девушка").await?;
+    let edit = |field| Callback::Edit(Some(field)).to_string();
+
+    // The preview has a button for every field
+    let preview = env
+        .api
+        .messages_to(ALICE.id)
+        .into_iter()
+        .find(|m| {
+            m["text"].as_str().is_some_and(|t| t.starts_with("Так выглядит"))
+        })
+        .context("no profile preview")?;
+    let buttons = super::buttons(&preview);
+    assert!(EditField::iter().all(|field| buttons.contains(&edit(field))));
+
+    env.press(&ALICE, &edit(EditField::Gender)).await?;
+    env.send_text(&ALICE, "Я парень").await?;
+    assert_eq!(
+        env.texts_to(&ALICE).last().unwrap(),
+        &format!("{}\n\nПол: ♀️ → ♂️", text::CONFIRM_EDIT)
+    );
+    let user = env.db.get_user(ALICE.id).await?.context("user not found")?;
+    assert_eq!(user.gender, Gender::Female, "edits wait for confirmation");
+    env.send_text(&ALICE, "Сохранить").await?;
+    let user = env.db.get_user(ALICE.id).await?.context("user not found")?;
+    assert_eq!(user.gender, Gender::Male);
+    assert_eq!(env.texts_to(&ALICE).last().unwrap(), text::REQUEST_EDIT);
+
+    // The grade filter is only asked when it doesn't fit the new stage
+    env.press(&ALICE, &edit(EditField::Grade)).await?;
+    env.send_text(&ALICE, "11").await?;
+    assert!(env
+        .texts_to(&ALICE)
+        .last()
+        .unwrap()
+        .starts_with(text::CONFIRM_EDIT));
+    env.send_text(&ALICE, "Отменить").await?;
+    env.press(&ALICE, &edit(EditField::Grade)).await?;
+    env.send_text(&ALICE, "1 курс").await?;
+    assert_eq!(env.last_buttons(&ALICE).len(), GradeFilter::PRESETS.len());
+    env.press(&ALICE, &Callback::SetGradeFilter { up: 1, down: 1 }.to_string())
+        .await?;
+    env.send_text(&ALICE, "Отменить").await?;
+
+    // Subjects don't continue into the partner's subjects
+    env.press(&ALICE, &edit(EditField::Subjects)).await?;
+    env.press(
+        &ALICE,
+        &Callback::SetSubjects(SubjectsAction::Continue).to_string(),
+    )
+    .await?;
+    assert_eq!(env.texts_to(&ALICE).last().unwrap(), text::REQUEST_EDIT);
+
+    env.press(&ALICE, &edit(EditField::DatingPurpose)).await?;
+    for action in [
+        UpdateBitflags::Update(DatingPurpose::Studies),
+        UpdateBitflags::Continue,
+    ] {
+        env.press(&ALICE, &Callback::SetDatingPurpose(action).to_string())
+            .await?;
+    }
+    env.send_text(&ALICE, "Отменить").await?;
+    let user = env.db.get_user(ALICE.id).await?.context("user not found")?;
+    assert_eq!(user.dating_purpose, DatingPurpose::Friendship.bits());
+    assert!(env.texts_to(&ALICE).iter().any(|t| t == text::EDIT_CANCELLED));
+
+    env.press(&ALICE, &Callback::Edit(None).to_string()).await?;
+    assert_eq!(
+        env.texts_to(&ALICE).last().unwrap(),
+        text::READY_FOR_DATINGS
+    );
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore = "needs TEST_DATABASE_URL"]
+async fn photo_manager() -> anyhow::Result<()> {
+    let env = TestEnv::new().await?;
+    create_profile(&env, &ALICE, "Я девушка").await?;
+    for photo in ["first", "second", "third"] {
+        env.db
+            .create_image(ALICE.id, photo.to_owned(), ImageKind::Image)
+            .await?;
+    }
+    let images = env.db.get_user_images(ALICE.id).await?;
+    let id = |n: usize| images[n].id;
+    let photos = |action| Callback::ManagePhotos(action).to_string();
+
+    env.send_text(&ALICE, "/edit").await?;
+    env.press(&ALICE, "eФото").await?;
+    let buttons = env.last_buttons(&ALICE);
+    assert!(!buttons.contains(&photos(PhotosAction::Up(id(0)))));
+    assert!(!buttons.contains(&photos(PhotosAction::Down(id(2)))));
+    assert!(buttons.contains(&photos(PhotosAction::Add)));
+
+    env.press(&ALICE, &photos(PhotosAction::First(id(2)))).await?;
+    env.press(&ALICE, &photos(PhotosAction::Delete(id(0)))).await?;
+    env.press(&ALICE, &photos(PhotosAction::Down(id(2)))).await?;
+    // Buttons of the images before the change are gone
+    assert!(env
+        .press(&ALICE, &photos(PhotosAction::Up(id(0))))
+        .await
+        .is_err());
+    env.press(&ALICE, &photos(PhotosAction::Done)).await?;
+    let changes = env.texts_to(&ALICE).last().unwrap().clone();
+    assert!(changes.contains("Фото и видео: 3 → 2"), "{changes}");
+    // Nothing is stored until the changes are confirmed
+    assert_eq!(env.db.get_user_images(ALICE.id).await?.len(), 3);
+    env.send_text(&ALICE, "Сохранить").await?;
+
+    let order: Vec<_> = env
+        .db
+        .get_user_images(ALICE.id)
+        .await?
+        .into_iter()
+        .map(|image| image.telegram_id)
+        .collect();
+    assert_eq!(order, ["second", "third"]);
+    assert_eq!(
+        env.db.get_images(ALICE.id).await?[0].0,
+        "second",
+        "profiles show photos in the chosen order"
+    );
+    Ok(())
+}
+
+/// Replies to uploaded photos, albums are stored after a while so wait for
+/// `count` of them
+async fn photo_replies(env: &TestEnv, count: usize) -> Vec<String> {
+    for _ in 0..50 {
+        let replies: Vec<_> = env
+            .texts_to(&ALICE)
+            .into_iter()
+            .filter(|t| t.contains("10 фото/видео"))
+            .collect();
+        if replies.len() >= count {
+            return replies;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+    panic!("no reply to the photos")
+}
+
+#[tokio::test]
+#[ignore = "needs TEST_DATABASE_URL"]
+async fn photo_albums() -> anyhow::Result<()> {
+    let env = TestEnv::new().await?;
+    create_profile(&env, &ALICE, "Я девушка").await?;
+
+    env.send_text(&ALICE, "/edit").await?;
+    env.press(&ALICE, "eФото").await?;
+    env.press(&ALICE, &Callback::ManagePhotos(PhotosAction::Add).to_string())
+        .await?;
+    env.send_photo(&ALICE, "single", None).await?;
+    for photo in ["first", "second", "third"] {
+        env.send_photo(&ALICE, photo, Some("album")).await?;
+    }
+    // Items of the album don't get a reply each
+    assert_eq!(photo_replies(&env, 1).await.len(), 1);
+    assert_eq!(photo_replies(&env, 2).await, [
+        "Добавлено 1/10 фото/видео. Добавить ещё?",
+        "Добавлено 4/10 фото/видео. Добавить ещё?",
+    ]);
+    env.send_text(&ALICE, "Сохранить").await?;
+    env.press(&ALICE, &Callback::ManagePhotos(PhotosAction::Done).to_string())
+        .await?;
+    env.send_text(&ALICE, "Сохранить").await?;
+    let images: Vec<_> = env
+        .db
+        .get_images(ALICE.id)
+        .await?
+        .into_iter()
+        .map(|(id, _)| id)
+        .collect();
+    assert_eq!(images, ["single", "first", "second", "third"]);
+
+    // Albums over the limit are rejected as a whole
+    env.press(&ALICE, "eФото").await?;
+    env.press(&ALICE, &Callback::ManagePhotos(PhotosAction::Add).to_string())
+        .await?;
+    for n in 0..7 {
+        env.send_photo(&ALICE, &format!("big{n}"), Some("big")).await?;
+    }
+    assert_eq!(
+        photo_replies(&env, 3).await[2],
+        "Невозможно добавить более 10 фото/видео"
+    );
+    env.send_text(&ALICE, "Сохранить").await?;
+    env.press(&ALICE, &Callback::ManagePhotos(PhotosAction::Done).to_string())
+        .await?;
+    assert!(env.texts_to(&ALICE).iter().any(|t| t == text::EDIT_UNCHANGED));
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore = "needs TEST_DATABASE_URL"]
+async fn slow_album() -> anyhow::Result<()> {
+    let env = TestEnv::new().await?;
+    let dir = std::env::temp_dir()
+        .join(format!("bvilove-slow-album-{}", std::process::id()));
+    std::env::set_var("MEDIA_DIR", &dir);
+    create_profile(&env, &ALICE, "Я девушка").await?;
+
+    env.send_text(&ALICE, "/edit").await?;
+    env.press(&ALICE, "eФото").await?;
+    env.press(&ALICE, &Callback::ManagePhotos(PhotosAction::Add).to_string())
+        .await?;
+    // Archiving the whole album would take longer than the album window
+    env.api.delay_downloads(std::time::Duration::from_millis(600));
+    for n in 0..3 {
+        env.send_photo(&ALICE, &format!("slow{n}"), Some("slow")).await?;
+    }
+    assert_eq!(photo_replies(&env, 1).await, [
+        "Добавлено 3/10 фото/видео. Добавить ещё?"
+    ]);
+    tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+    assert_eq!(photo_replies(&env, 1).await.len(), 1);
+    // Media is archived only once the profile is saved
+    assert!(!dir.exists());
+
+    std::env::remove_var("MEDIA_DIR");
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore = "needs TEST_DATABASE_URL"]
+async fn late_album_items() -> anyhow::Result<()> {
+    let env = TestEnv::new().await?;
+    create_profile(&env, &ALICE, "Я девушка").await?;
+
+    env.send_text(&ALICE, "/edit").await?;
+    env.press(&ALICE, "eФото").await?;
+    env.press(&ALICE, &Callback::ManagePhotos(PhotosAction::Add).to_string())
+        .await?;
+    for n in 0..2 {
+        env.send_photo(&ALICE, &format!("late{n}"), Some("late")).await?;
+    }
+    assert_eq!(photo_replies(&env, 1).await, [
+        "Добавлено 2/10 фото/видео. Добавить ещё?"
+    ]);
+    // An item handled after the reply joins the album without a new one
+    env.send_photo(&ALICE, "late2", Some("late")).await?;
+
+    // An item that doesn't fit rejects the whole album even if it's late
+    for n in 0..7 {
+        env.send_photo(&ALICE, &format!("full{n}"), Some("full")).await?;
+    }
+    assert_eq!(
+        photo_replies(&env, 2).await[1],
+        "Добавлено 10/10 фото/видео. Добавить ещё?"
+    );
+    env.send_photo(&ALICE, "full7", Some("full")).await?;
+    tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+    assert_eq!(
+        photo_replies(&env, 3).await[2..],
+        ["Невозможно добавить более 10 фото/видео"]
+    );
+
+    env.send_text(&ALICE, "Сохранить").await?;
+    env.press(&ALICE, &Callback::ManagePhotos(PhotosAction::Done).to_string())
+        .await?;
+    env.send_text(&ALICE, "Сохранить").await?;
+    let images: Vec<_> = env
+        .db
+        .get_images(ALICE.id)
+        .await?
+        .into_iter()
+        .map(|(id, _)| id)
+        .collect();
+    assert_eq!(images, ["late0", "late1", "late2"]);
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore = "needs TEST_DATABASE_URL"]
+async fn voice_and_video_notes() -> anyhow::Result<()> {
+    let env = TestEnv::new().await?;
+    create_profile(&env, &ALICE, "Я девушка").await?;
+    let media = |kind: &str, id: &str, duration: u32, size: u32| {
+        let file = json!({
+            "file_id": id,
+            "file_unique_id": id,
+            "file_size": size,
+            "duration": duration,
+            "mime_type": "video/mp4",
+            "length": 240,
+            "width": 240,
+            "height": 240,
+        });
+        json!({ kind: file })
+    };
+
+    env.send_text(&ALICE, "/edit").await?;
+    env.press(&ALICE, "eФото").await?;
+    env.press(&ALICE, &Callback::ManagePhotos(PhotosAction::Add).to_string())
+        .await?;
+    env.send_photo(&ALICE, "photo", None).await?;
+    env.send_media(&ALICE, media("voice", "long", 61, 1)).await?;
+    assert_eq!(env.texts_to(&ALICE).last().unwrap(), text::MEDIA_TOO_LONG);
+    let big = 30 * 1024 * 1024;
+    env.send_media(&ALICE, media("animation", "big", 1, big)).await?;
+    assert_eq!(env.texts_to(&ALICE).last().unwrap(), text::MEDIA_TOO_BIG);
+    env.send_media(&ALICE, media("voice", "voice", 30, 1)).await?;
+    env.send_media(&ALICE, media("video_note", "note", 30, 1)).await?;
+    env.send_media(&ALICE, media("animation", "gif", 3, 1)).await?;
+    assert_eq!(
+        photo_replies(&env, 4).await[3],
+        "Добавлено 4/10 фото/видео. Добавить ещё?"
+    );
+
+    // They can't be in the album with photos
+    let sent = || {
+        env.api.sent().into_iter().map(|s| s.method).collect::<Vec<_>>()
+    };
+    let before = sent().len();
+    env.send_text(&ALICE, "Сохранить").await?;
+    env.press(&ALICE, &Callback::ManagePhotos(PhotosAction::Done).to_string())
+        .await?;
+    let methods = sent()[before..].to_vec();
+    let separate = ["sendvoice", "sendvideonote", "sendanimation"];
+    for method in separate.into_iter().chain(["sendmediagroup"]) {
+        assert!(methods.iter().any(|m| m == method), "no {method}");
+    }
+
+    env.send_text(&ALICE, "Сохранить").await?;
+    let kinds: Vec<_> = env
+        .db
+        .get_images(ALICE.id)
+        .await?
+        .into_iter()
+        .map(|(_, kind)| kind)
+        .collect();
+    assert_eq!(kinds, [
+        ImageKind::Image,
+        ImageKind::Voice,
+        ImageKind::VideoNote,
+        ImageKind::Animation,
+    ]);
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore = "needs TEST_DATABASE_URL"]
+async fn media_archive() -> anyhow::Result<()> {
+    let env = TestEnv::new().await?;
+    let dir = std::env::temp_dir()
+        .join(format!("bvilove-media-{}", std::process::id()));
+    std::env::set_var("MEDIA_DIR", &dir);
+    create_profile(&env, &ALICE, "Я девушка").await?;
+    let voice = |id: &str| {
+        json!({ "voice": {
+            "file_id": id,
+            "file_unique_id": "voice",
+            "file_size": 1,
+            "duration": 5,
+            "mime_type": "audio/ogg",
+        }})
+    };
+
+    let photos = |action| Callback::ManagePhotos(action).to_string();
+    let downloads = || {
+        env.api.sent().iter().filter(|s| s.method == "download").count()
+    };
+
+    // Discarded uploads aren't archived
+    env.send_text(&ALICE, "/edit").await?;
+    env.press(&ALICE, "eФото").await?;
+    env.press(&ALICE, &photos(PhotosAction::Add)).await?;
+    env.send_media(&ALICE, voice("dead-id")).await?;
+    env.send_text(&ALICE, "Сохранить").await?;
+    env.press(&ALICE, &photos(PhotosAction::Done)).await?;
+    env.send_text(&ALICE, "Отменить").await?;
+    tokio::time::sleep(std::time::Duration::from_millis(300)).await;
+    assert_eq!(downloads(), 0);
+
+    env.press(&ALICE, "eФото").await?;
+    env.press(&ALICE, &photos(PhotosAction::Add)).await?;
+    env.send_media(&ALICE, voice("dead-id")).await?;
+    env.send_media(&ALICE, voice("other-id")).await?;
+    env.send_text(&ALICE, "Сохранить").await?;
+    env.press(&ALICE, &photos(PhotosAction::Done)).await?;
+    env.send_text(&ALICE, "Сохранить").await?;
+    // Saved media is archived in the background
+    for _ in 0..50 {
+        if dir.join("voice").exists() {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+    assert!(dir.join("voice").exists());
+    tokio::time::sleep(std::time::Duration::from_millis(300)).await;
+    assert_eq!(downloads(), 1, "the same file is archived once");
+    let files: Vec<_> = std::fs::read_dir(&dir)?
+        .map(|entry| Ok(entry?.file_name()))
+        .collect::<std::io::Result<_>>()?;
+    assert_eq!(files, ["voice"], "no partial downloads are left");
+
+    // The dead file is uploaded from the archive and gets a new id
+    env.api.kill_file("dead-id");
+    env.press(&ALICE, &Callback::Edit(None).to_string()).await?;
+    let ids: Vec<_> = env
+        .db
+        .get_images(ALICE.id)
+        .await?
+        .into_iter()
+        .map(|(id, _)| id)
+        .collect();
+    assert!(ids[0].starts_with("uploaded"), "{ids:?}");
+    assert_eq!(ids[1], "other-id");
+
+    // The copy is removed with the last image of the file
+    env.press(&ALICE, "eФото").await?;
+    for image in env.db.get_user_images(ALICE.id).await? {
+        env.press(&ALICE, &photos(PhotosAction::Delete(image.id))).await?;
+    }
+    env.press(&ALICE, &photos(PhotosAction::Done)).await?;
+    env.send_text(&ALICE, "Сохранить").await?;
+    assert!(env.db.get_user_images(ALICE.id).await?.is_empty());
+    assert!(!dir.join("voice").exists());
+
+    std::env::remove_var("MEDIA_DIR");
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore = "needs TEST_DATABASE_URL"]
+async fn dialogue_survives_restart() -> anyhow::Result<()> {
+    let mut env = TestEnv::new().await?;
+    env.register(&ALICE);
+    env.send_text(&ALICE, "/create").await?;
+    env.send_text(&ALICE, "Alice").await?;
+
+    env.restart().await?;
+
+    env.send_text(&ALICE, "Я девушка").await?;
+    assert_eq!(
+        env.texts_to(&ALICE).last().map(String::as_str),
+        Some(crate::text::REQUEST_GENDER_FILTER)
+    );
+
+    // States saved before the achievement draft was added still load
+    let mut dialogue =
+        env.db.load_dialogue(ALICE.id).await?.context("no dialogue")?;
+    dialogue["SetGenderFilter"]
+        .as_object_mut()
+        .context("not SetGenderFilter")?
+        .remove("achievement")
+        .context("no achievement")?;
+    env.db.save_dialogue(ALICE.id, Some(dialogue)).await?;
+    env.send_text(&ALICE, "Не важно").await?;
+    assert_eq!(
+        env.texts_to(&ALICE).last().map(String::as_str),
+        Some(crate::text::REQUEST_GRADE)
+    );
+
+    // A state that can't be read anymore starts the dialogue over
+    env.db
+        .save_dialogue(ALICE.id, Some(json!({ "RemovedState": null })))
+        .await?;
+    env.send_text(&ALICE, "/create").await?;
+    assert_eq!(
+        env.texts_to(&ALICE).last().map(String::as_str),
+        Some(crate::text::REQUEST_NAME)
+    );
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore = "needs TEST_DATABASE_URL"]
+async fn mutual_like() -> anyhow::Result<()> {
+    let env = TestEnv::new().await?;
+    create_profile(&env, &ALICE, "Я девушка").await?;
+    create_profile(&env, &BOB, "Я парень").await?;
+
+    env.send_text(&ALICE, "/date").await?;
+    assert!(env.texts_to(&ALICE).last().unwrap().contains("Bob"));
+    let dating_id = dating_id(&env.last_buttons(&ALICE))?;
+
+    env.press(
+        &ALICE,
+        &Callback::Dating { dating_id, code: RateCode::Like }.to_string(),
+    )
+    .await?;
+    assert!(env
+        .texts_to(&BOB)
+        .last()
+        .unwrap()
+        .starts_with("Кому-то понравилась ваша анкета"));
+
+    env.press(
+        &BOB,
+        &Callback::Dating { dating_id, code: RateCode::ResponseLike }
+            .to_string(),
+    )
+    .await?;
+    assert!(env
+        .texts_to(&ALICE)
+        .iter()
+        .any(|t| t.starts_with("Взаимный лайк!")));
+    let edits = env.sent_to(&BOB, "editMessageReplyMarkup");
+    let chat_button = &edits.last().unwrap().body["reply_markup"]
+        ["inline_keyboard"][0][0];
+    assert_eq!(chat_button["url"], "tg://resolve?domain=Alice");
+
+    let dating = env.db.get_dating(dating_id).await?;
+    assert_eq!(dating.initiator_reaction, Some(true));
+    assert_eq!(dating.partner_reaction, Some(true));
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore = "needs TEST_DATABASE_URL"]
+async fn like_with_message() -> anyhow::Result<()> {
+    let env = TestEnv::new().await?;
+    create_profile(&env, &ALICE, "Я девушка").await?;
+    create_profile(&env, &BOB, "Я парень").await?;
+
+    env.send_text(&ALICE, "/date").await?;
+    let dating_id = dating_id(&env.last_buttons(&ALICE))?;
+    env.press(
+        &ALICE,
+        &Callback::Dating { dating_id, code: RateCode::LikeWithMsg }
+            .to_string(),
+    )
+    .await?;
+    env.send_text(&ALICE, "Привет!").await?;
+
+    assert!(env.texts_to(&BOB).last().unwrap().contains("Привет!"));
+    assert_eq!(
+        env.db.get_dating(dating_id).await?.initiator_reaction,
+        Some(true)
+    );
+
+    env.send_text(&BOB, "/likes").await?;
+    assert!(env
+        .texts_to(&BOB)
+        .last()
+        .unwrap()
+        .starts_with("Лайк 1 из 1, вам оставили сообщение:\nПривет!\n\n"));
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore = "needs TEST_DATABASE_URL"]
+async fn liked_partner_ranked_first() -> anyhow::Result<()> {
+    let mut env = TestEnv::new().await?;
+    env.set_ranking(RankingWeights {
+        subjects: 0.0,
+        grade: 0.0,
+        same_city: 0.0,
+        same_subject: 0.0,
+        same_county: 0.0,
+        inactivity: 0.0,
+        liked: 1.0,
+        random: 0.0,
+    })
+    .await?;
+    create_profile(&env, &ALICE, "Я девушка").await?;
+    create_profile(&env, &BOB, "Я парень").await?;
+
+    env.send_text(&BOB, "/date").await?;
+    let dating_id = dating_id(&env.last_buttons(&BOB))?;
+    env.press(
+        &BOB,
+        &Callback::Dating { dating_id, code: RateCode::Like }.to_string(),
+    )
+    .await?;
+
+    create_profile(&env, &CAROL, "Я девушка").await?;
+
+    env.send_text(&ALICE, "/date").await?;
+    assert!(env.texts_to(&ALICE).last().unwrap().contains("Bob"));
+    Ok(())
+}
+
+/// Alice and Carol like Bob, who doesn't answer
+async fn two_likes_to_bob(env: &TestEnv) -> anyhow::Result<()> {
+    create_profile(env, &BOB, "Я парень").await?;
+    for user in [&ALICE, &CAROL] {
+        like_bob(env, user).await?;
+    }
+    Ok(())
+}
+
+async fn like_bob(env: &TestEnv, user: &TestUser) -> anyhow::Result<()> {
+    create_profile(env, user, "Я девушка").await?;
+    env.send_text(user, "/date").await?;
+    // Girls who joined earlier may be recommended first
+    loop {
+        let is_bob = env.texts_to(user).last().unwrap().contains("Bob");
+        let dating_id = dating_id(&env.last_buttons(user))?;
+        let code = if is_bob { RateCode::Like } else { RateCode::Dislike };
+        env.press(user, &Callback::Dating { dating_id, code }.to_string())
+            .await?;
+        if is_bob {
+            return Ok(());
+        }
+    }
+}
+
+#[tokio::test]
+#[ignore = "needs TEST_DATABASE_URL"]
+async fn likes_inbox() -> anyhow::Result<()> {
+    let env = TestEnv::new().await?;
+    two_likes_to_bob(&env).await?;
+
+    env.send_text(&BOB, "/likes").await?;
+    let last = env.texts_to(&BOB).last().cloned().unwrap();
+    assert!(last.starts_with("Лайк 1 из 2"));
+    assert!(last.contains("Alice"));
+
+    env.press(&BOB, &Callback::Likes { page: 1 }.to_string()).await?;
+    let last = env.texts_to(&BOB).last().cloned().unwrap();
+    assert!(last.starts_with("Лайк 2 из 2"));
+    assert!(last.contains("Carol"));
+
+    let dating_id = dating_id(&env.last_buttons(&BOB))?;
+    env.press(
+        &BOB,
+        &Callback::Dating { dating_id, code: RateCode::ResponseLike }
+            .to_string(),
+    )
+    .await?;
+    assert!(env
+        .texts_to(&CAROL)
+        .iter()
+        .any(|t| t.starts_with("Взаимный лайк!")));
+
+    env.send_text(&BOB, "/likes").await?;
+    let last = env.texts_to(&BOB).last().cloned().unwrap();
+    assert!(last.starts_with("Лайк 1 из 1"));
+    assert!(last.contains("Alice"));
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore = "needs TEST_DATABASE_URL"]
+async fn likes_reminder() -> anyhow::Result<()> {
+    let env = TestEnv::new().await?;
+    two_likes_to_bob(&env).await?;
+
+    let reminder = LikesReminder { threshold: 2, interval_hours: 24 };
+    reminder.remind(&env.bot, &env.db).await?;
+    assert!(env
+        .texts_to(&BOB)
+        .last()
+        .unwrap()
+        .starts_with("Лайков без ответа: 2"));
+    assert!(!env
+        .texts_to(&ALICE)
+        .iter()
+        .any(|t| t.starts_with("Лайков без ответа")));
+
+    // Don't remind twice in a row
+    let sent = env.texts_to(&BOB).len();
+    reminder.remind(&env.bot, &env.db).await?;
+    assert_eq!(env.texts_to(&BOB).len(), sent);
+
+    // Nor about the same likes once the interval has passed
+    let reminder = LikesReminder { threshold: 2, interval_hours: 0 };
+    reminder.remind(&env.bot, &env.db).await?;
+    assert_eq!(env.texts_to(&BOB).len(), sent);
+
+    like_bob(&env, &DAVE).await?;
+    reminder.remind(&env.bot, &env.db).await?;
+    assert!(env
+        .texts_to(&BOB)
+        .last()
+        .unwrap()
+        .starts_with("Лайков без ответа: 3"));
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore = "needs TEST_DATABASE_URL"]
+async fn reengage_inactive_and_unfinished() -> anyhow::Result<()> {
+    let env = TestEnv::new().await?;
+    create_profile(&env, &ALICE, "Я девушка").await?;
+    create_profile(&env, &BOB, "Я парень").await?;
+    env.register(&CAROL);
+    env.send_text(&CAROL, "/start").await?;
+    // Group chats, like the admin one, have no profiles either
+    env.db.save_dialogue(-ADMIN.id, Some(json!("Start"))).await?;
+
+    let reengagement = Reengagement {
+        inactive_days: 0,
+        unfinished_days: 0,
+        ..Default::default()
+    };
+    reengagement.remind(&env.bot, &env.db).await?;
+    assert!(!env
+        .api
+        .sent()
+        .iter()
+        .any(|s| s.body["chat_id"].as_i64() == Some(-ADMIN.id)));
+
+    // Bob's profile appeared after Alice was last seen
+    assert!(env
+        .texts_to(&ALICE)
+        .last()
+        .unwrap()
+        .starts_with("Пока вас не было, рядом появились новые анкеты: 1."));
+    assert!(!env
+        .texts_to(&BOB)
+        .iter()
+        .any(|t| t.starts_with("Пока вас не было")));
+    assert_eq!(
+        env.texts_to(&CAROL).last().unwrap(),
+        crate::text::FINISH_PROFILE
+    );
+    assert_eq!(
+        env.last_buttons(&CAROL),
+        vec![Callback::CreateProfile.to_string()]
+    );
+
+    // Campaigns have a cooldown
+    let sent = env.api.sent().len();
+    reengagement.remind(&env.bot, &env.db).await?;
+    assert_eq!(env.api.sent().len(), sent);
+
+    // And the nudge to finish the profile is sent only once
+    let sent = env.texts_to(&CAROL).len();
+    Reengagement { unfinished_days: 0, cooldown_days: 0, ..Default::default() }
+        .remind(&env.bot, &env.db)
+        .await?;
+    assert_eq!(env.texts_to(&CAROL).len(), sent);
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore = "needs TEST_DATABASE_URL"]
+async fn deactivate_silent_users() -> anyhow::Result<()> {
+    let env = TestEnv::new().await?;
+    create_profile(&env, &ALICE, "Я девушка").await?;
+
+    let reengagement =
+        Reengagement { deactivate_days: 0, ..Default::default() };
+    reengagement.remind(&env.bot, &env.db).await?;
+    assert!(!env.db.get_user(ALICE.id).await?.context("no Alice")?.active);
+    assert_eq!(
+        env.texts_to(&ALICE).last().unwrap(),
+        crate::text::PROFILE_DEACTIVATED
+    );
+
+    // The user came back, don't disable the profile again right away
+    env.send_text(&ALICE, "/enable").await?;
+    reengagement.remind(&env.bot, &env.db).await?;
+    assert!(env.db.get_user(ALICE.id).await?.context("no Alice")?.active);
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore = "needs TEST_DATABASE_URL"]
+async fn answering_likes_keeps_profile_active() -> anyhow::Result<()> {
+    use entities::{prelude::Users, users};
+    use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, Set};
+
+    let env = TestEnv::new().await?;
+    create_profile(&env, &ALICE, "Я девушка").await?;
+    create_profile(&env, &BOB, "Я парень").await?;
+
+    env.send_text(&ALICE, "/date").await?;
+    let dating_id = dating_id(&env.last_buttons(&ALICE))?;
+    env.press(
+        &ALICE,
+        &Callback::Dating { dating_id, code: RateCode::Like }.to_string(),
+    )
+    .await?;
+
+    // Bob hasn't searched for partners for a long time
+    let conn = sea_orm::Database::connect(&env.db_url).await?;
+    Users::update_many()
+        .set(users::ActiveModel {
+            last_activity: Set(
+                chrono::Utc::now().naive_utc() - chrono::Duration::days(40)
+            ),
+            ..Default::default()
+        })
+        .filter(users::Column::Id.eq(BOB.id))
+        .exec(&conn)
+        .await?;
+    conn.close().await?;
+
+    env.press(
+        &BOB,
+        &Callback::Dating { dating_id, code: RateCode::ResponseLike }
+            .to_string(),
+    )
+    .await?;
+
+    let reengagement = Reengagement::default();
+    reengagement.remind(&env.bot, &env.db).await?;
+    assert!(env.db.get_user(BOB.id).await?.context("no Bob")?.active);
+    assert_ne!(
+        env.texts_to(&BOB).last().unwrap(),
+        crate::text::PROFILE_DEACTIVATED
+    );
+    Ok(())
+}
+
+/// Move the user to another graduation year, confirmed in this academic
+/// year
+async fn set_graduation_year(
+    env: &TestEnv,
+    user: &TestUser,
+    graduation_year: i16,
+) -> anyhow::Result<()> {
+    use entities::{prelude::Users, users};
+    use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, Set};
+
+    let conn = sea_orm::Database::connect(&env.db_url).await?;
+    Users::update_many()
+        .set(users::ActiveModel {
+            graduation_year: Set(graduation_year),
+            grade_confirmed: Set(academic_year()),
+            ..Default::default()
+        })
+        .filter(users::Column::Id.eq(user.id))
+        .exec(&conn)
+        .await?;
+    conn.close().await?;
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore = "needs TEST_DATABASE_URL"]
+async fn grade_check() -> anyhow::Result<()> {
+    let env = TestEnv::new().await?;
+    create_profile(&env, &ALICE, "Я девушка").await?;
+    create_profile(&env, &BOB, "Я парень").await?;
+    // Both grades were entered in the last academic year
+    env.db.set_grade_confirmed(ALICE.id, academic_year() - 1).await?;
+    env.db.set_grade_confirmed(BOB.id, academic_year() - 1).await?;
+
+    let grade_check = GradeCheck::default();
+    grade_check.remind(&env.bot, &env.db).await?;
+    assert_eq!(
+        env.texts_to(&ALICE).last().unwrap(),
+        "Начался новый учебный год! Вы перешли в 10 класс?"
+    );
+    env.press(&ALICE, &Callback::ConfirmGrade { confirmed: true }.to_string())
+        .await?;
+    assert_eq!(
+        env.texts_to(&ALICE).last().unwrap(),
+        crate::text::GRADE_CONFIRMED
+    );
+    let alice = env.db.get_user(ALICE.id).await?.context("no Alice")?;
+    assert_eq!(alice.grade_confirmed, academic_year());
+
+    // Everyone is asked once a year
+    let sent = env.api.sent().len();
+    grade_check.remind(&env.bot, &env.db).await?;
+    assert_eq!(env.api.sent().len(), sent);
+
+    // Bob can't search partners until he confirms the grade
+    env.send_text(&BOB, "/date").await?;
+    assert!(env
+        .texts_to(&BOB)
+        .last()
+        .unwrap()
+        .starts_with("Начался новый учебный год!"));
+    env.press(&BOB, &Callback::ConfirmGrade { confirmed: false }.to_string())
+        .await?;
+    // The stored filter still fits the next grade
+    env.send_text(&BOB, "11").await?;
+    let bob = env.db.get_user(BOB.id).await?.context("no Bob")?;
+    assert_eq!(bob.graduation_year, academic_year() + 1);
+    assert_eq!(bob.grade_confirmed, academic_year());
+
+    env.send_text(&BOB, "/date").await?;
+    assert!(env.texts_to(&BOB).last().unwrap().contains("Alice"));
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore = "needs TEST_DATABASE_URL"]
+async fn grade_check_deadline() -> anyhow::Result<()> {
+    let env = TestEnv::new().await?;
+    create_profile(&env, &ALICE, "Я девушка").await?;
+    env.db.set_grade_confirmed(ALICE.id, academic_year() - 1).await?;
+
+    let grade_check = GradeCheck { deadline_days: 0 };
+    grade_check.remind(&env.bot, &env.db).await?;
+    assert!(env.db.get_user(ALICE.id).await?.context("no Alice")?.active);
+
+    grade_check.remind(&env.bot, &env.db).await?;
+    assert!(!env.db.get_user(ALICE.id).await?.context("no Alice")?.active);
+    assert_eq!(
+        env.texts_to(&ALICE).last().unwrap(),
+        crate::text::GRADE_NOT_CONFIRMED
+    );
+
+    // The question comes back with the profile
+    env.send_text(&ALICE, "/enable").await?;
+    assert!(env
+        .texts_to(&ALICE)
+        .last()
+        .unwrap()
+        .starts_with("Начался новый учебный год!"));
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore = "needs TEST_DATABASE_URL"]
+async fn graduates_match_graduates() -> anyhow::Result<()> {
+    let env = TestEnv::new().await?;
+    create_profile(&env, &ALICE, "Я девушка").await?;
+    create_profile(&env, &BOB, "Я парень").await?;
+    create_profile(&env, &CAROL, "Я девушка").await?;
+    // Alice graduated last year, Bob has just graduated and Carol is in the
+    // last grade, all within each other's filters
+    set_graduation_year(&env, &ALICE, academic_year() - 1).await?;
+    set_graduation_year(&env, &BOB, academic_year()).await?;
+    set_graduation_year(&env, &CAROL, academic_year() + 1).await?;
+
+    env.send_text(&BOB, "/date").await?;
+    let recommendation = env.texts_to(&BOB).last().unwrap().clone();
+    assert!(recommendation.contains("Alice"));
+    assert!(recommendation
+        .contains(&format!("выпуск {} года", academic_year() - 1)));
+    let dating_id = dating_id(&env.last_buttons(&BOB))?;
+    env.press(
+        &BOB,
+        &Callback::Dating { dating_id, code: RateCode::Dislike }.to_string(),
+    )
+    .await?;
+    assert!(!env.texts_to(&BOB).last().unwrap().contains("Carol"));
+
+    env.send_text(&CAROL, "/date").await?;
+    assert!(!env.texts_to(&CAROL).last().unwrap().contains("Bob"));
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore = "needs TEST_DATABASE_URL"]
+async fn university_students() -> anyhow::Result<()> {
+    let env = TestEnv::new().await?;
+    create_profile(&env, &ALICE, "Я девушка").await?;
+    create_profile(&env, &BOB, "Я парень").await?;
+
+    // Bob went to a university instead of the next grade
+    env.db.set_grade_confirmed(BOB.id, academic_year() - 1).await?;
+    env.send_text(&BOB, "/date").await?;
+    env.press(&BOB, &Callback::ConfirmGrade { confirmed: false }.to_string())
+        .await?;
+    env.send_text(&BOB, "1 курс").await?;
+    // Students don't get the toggle for students
+    assert_eq!(env.last_buttons(&BOB).len(), GradeFilter::PRESETS.len());
+    env.press(&BOB, &Callback::SetGradeFilter { up: 2, down: 2 }.to_string())
+        .await?;
+    let bob = env.db.get_user(BOB.id).await?.context("no Bob")?;
+    assert!(bob.university);
+    assert_eq!(bob.graduation_year, academic_year());
+    assert!(env
+        .texts_to(&BOB)
+        .iter()
+        .any(|t| t.contains("Вы ищете: с 1 по 3 курс.")));
+
+    // Alice is in the tenth grade and doesn't want to see students yet
+    env.send_text(&ALICE, "/edit").await?;
+    env.press(&ALICE, "eКлассы").await?;
+    env.press(&ALICE, &Callback::SetGradeFilter { up: 2, down: 2 }.to_string())
+        .await?;
+    env.send_text(&ALICE, "Сохранить").await?;
+    env.send_text(&BOB, "/date").await?;
+    assert!(!env.texts_to(&BOB).last().unwrap().contains("Alice"));
+
+    env.send_text(&ALICE, "/edit").await?;
+    env.press(&ALICE, "eКлассы").await?;
+    env.press(
+        &ALICE,
+        &Callback::SetUniversityFilter { show: true }.to_string(),
+    )
+    .await?;
+    assert_eq!(
+        env.last_buttons(&ALICE).last().unwrap(),
+        &Callback::SetUniversityFilter { show: false }.to_string()
+    );
+    env.press(&ALICE, &Callback::SetGradeFilter { up: 2, down: 2 }.to_string())
+        .await?;
+    assert!(env
+        .texts_to(&ALICE)
+        .iter()
+        .any(|t| t.ends_with(crate::text::UNIVERSITY_SHOWN)));
+    env.send_text(&ALICE, "Сохранить").await?;
+
+    env.send_text(&ALICE, "/date").await?;
+    let recommendation = env.texts_to(&ALICE).last().unwrap().clone();
+    assert!(recommendation.contains(" Bob, 1 курс.\n"));
+    env.send_text(&BOB, "/date").await?;
+    assert!(env.texts_to(&BOB).last().unwrap().contains("Alice"));
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore = "needs TEST_DATABASE_URL"]
+async fn achievements() -> anyhow::Result<()> {
+    let env = TestEnv::new().await?;
+    create_profile(&env, &ALICE, "Я девушка").await?;
+    create_profile(&env, &BOB, "Я парень").await?;
+    create_profile(&env, &CAROL, "Я девушка").await?;
+
+    // Alice only wants to meet finalists
+    env.send_text(&ALICE, "/edit").await?;
+    env.press(&ALICE, "eОлимпиады партнёра").await?;
+    env.send_text(&ALICE, "Только заключительный этап").await?;
+    env.send_text(&ALICE, "Сохранить").await?;
+    env.send_text(&ALICE, "/date").await?;
+    assert_eq!(
+        env.texts_to(&ALICE).last().unwrap(),
+        crate::text::PARTNER_NOT_FOUND
+    );
+
+    let year = academic_year().to_string();
+    env.send_text(&BOB, "/edit").await?;
+    env.press(&BOB, "eОлимпиады").await?;
+    for answer in [
+        // Olympiads are looked up by the closest name
+        "всероссийская олимпиада",
+        "Математика 📐",
+        "Заключительный этап",
+        &year,
+        "Победитель",
+        crate::text::ACHIEVEMENTS_DONE,
+    ] {
+        env.send_text(&BOB, answer).await?;
+    }
+    // Nothing is stored until the edit is confirmed
+    assert!(env.db.get_achievements(BOB.id).await?.is_empty());
+    assert!(env.texts_to(&BOB).last().unwrap().contains("Олимпиады: 0 → 1"));
+    env.send_text(&BOB, "Сохранить").await?;
+    let achievement = format!(
+        "🏆 Победитель, Всероссийская олимпиада школьников (Математика 📐, \
+         заключительный этап, {year})"
+    );
+    let added = achievement.replace('🏆', "Добавлено:");
+    assert!(env.texts_to(&BOB).contains(&added));
+
+    env.send_text(&ALICE, "/date").await?;
+    let recommendation = env.texts_to(&ALICE).last().unwrap().clone();
+    assert!(recommendation.contains("Bob"));
+    assert!(recommendation.contains(&achievement));
+
+    // Carol has no achievements, so she doesn't meet Alice
+    env.send_text(&CAROL, "/date").await?;
+    assert!(env.texts_to(&CAROL).last().unwrap().contains("Bob"));
+    let dating_id = dating_id(&env.last_buttons(&CAROL))?;
+    env.press(
+        &CAROL,
+        &Callback::Dating { dating_id, code: RateCode::Dislike }.to_string(),
+    )
+    .await?;
+    assert!(!env.texts_to(&CAROL).last().unwrap().contains("Alice"));
+
+    env.send_text(&BOB, "/edit").await?;
+    env.press(&BOB, "eОлимпиады").await?;
+    env.send_text(&BOB, crate::text::ACHIEVEMENTS_CLEAR).await?;
+    env.send_text(&BOB, crate::text::ACHIEVEMENTS_DONE).await?;
+    env.send_text(&BOB, "Отменить").await?;
+    assert_eq!(env.db.get_achievements(BOB.id).await?.len(), 1);
+
+    env.press(&BOB, "eОлимпиады").await?;
+    env.send_text(&BOB, crate::text::ACHIEVEMENTS_CLEAR).await?;
+    env.send_text(&BOB, crate::text::ACHIEVEMENTS_DONE).await?;
+    env.send_text(&BOB, "Сохранить").await?;
+    assert!(env.db.get_achievements(BOB.id).await?.is_empty());
+
+    env.press(&BOB, "eОлимпиады").await?;
+
+    // The Euler olympiad has no school and municipal stages
+    env.send_text(&BOB, "Олимпиада Эйлера").await?;
+    env.send_text(&BOB, "Математика 📐").await?;
+    let request = env.sent_to(&BOB, "sendMessage").pop().unwrap().body;
+    assert_eq!(request["text"], crate::text::REQUEST_OLYMPIAD_LEVEL);
+    let levels: Vec<_> = request["reply_markup"]["keyboard"][0]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|button| button["text"].clone())
+        .collect();
+    assert_eq!(levels, ["Региональный этап", "Заключительный этап"]);
+    env.send_text(&BOB, "Школьный этап").await?;
+    assert_eq!(
+        env.texts_to(&BOB).last().unwrap(),
+        crate::text::REQUEST_OLYMPIAD_LEVEL
+    );
+    Ok(())
+}
+
+/// Move the user to the city, only looking for partners there
+async fn set_city(
+    env: &TestEnv,
+    user: &TestUser,
+    city: &str,
+) -> anyhow::Result<()> {
+    use entities::{prelude::Users, sea_orm_active_enums::LocationFilter, users};
+    use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, Set};
+
+    let city: crate::cities::UserCity =
+        city.parse().map_err(|()| anyhow::anyhow!("no city"))?;
+    let conn = sea_orm::Database::connect(&env.db_url).await?;
+    Users::update_many()
+        .set(users::ActiveModel {
+            city: Set(city.into()),
+            location_filter: Set(LocationFilter::SameCity),
+            ..Default::default()
+        })
+        .filter(users::Column::Id.eq(user.id))
+        .exec(&conn)
+        .await?;
+    conn.close().await?;
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore = "needs TEST_DATABASE_URL"]
+async fn events() -> anyhow::Result<()> {
+    std::env::set_var("ADMIN_IDS", ADMIN.id.to_string());
+    let env = TestEnv::new().await?;
+    env.register(&ADMIN);
+    create_profile(&env, &ALICE, "Я девушка").await?;
+    create_profile(&env, &BOB, "Я парень").await?;
+    create_profile(&env, &CAROL, "Я девушка").await?;
+    // Alice only looks for partners in Kazan
+    set_city(&env, &ALICE, "Казань").await?;
+    env.send_text(&ALICE, "/date").await?;
+    assert_eq!(
+        env.texts_to(&ALICE).last().unwrap(),
+        crate::text::PARTNER_NOT_FOUND
+    );
+
+    env.send_text(&ALICE, "/event").await?;
+    assert_eq!(env.texts_to(&ALICE).last().unwrap(), crate::text::NO_EVENTS);
+
+    let today = chrono::Local::now().date_naive();
+    env.send_text(
+        &ADMIN,
+        &format!(
+            "/addevent {} {} Сириус, летняя смена\nСочи",
+            (today - chrono::Duration::days(1)).format("%d.%m.%Y"),
+            (today + chrono::Duration::days(7)).format("%d.%m.%Y"),
+        ),
+    )
+    .await?;
+    assert!(env
+        .texts_to(&ADMIN)
+        .last()
+        .unwrap()
+        .starts_with("Событие добавлено:\n#1 Сириус, летняя смена"));
+
+    let event = Callback::SetEvent { event_id: Some(1) }.to_string();
+    for user in [&ALICE, &BOB] {
+        env.send_text(user, "/event").await?;
+        assert_eq!(env.last_buttons(user).first(), Some(&event));
+        env.press(user, &event).await?;
+    }
+
+    // Alice and Bob are at the same event, so the city doesn't matter
+    env.send_text(&ALICE, "/date").await?;
+    let recommendation = env.texts_to(&ALICE).last().unwrap().clone();
+    assert!(recommendation.contains("Bob"));
+    assert!(recommendation.contains("📍 Сейчас на «Сириус, летняя смена»"));
+
+    // Alice is in Sochi now, so Carol from Moscow doesn't meet her
+    env.send_text(&CAROL, "/date").await?;
+    assert!(env.texts_to(&CAROL).last().unwrap().contains("Bob"));
+    let dating_id = dating_id(&env.last_buttons(&CAROL))?;
+    env.press(
+        &CAROL,
+        &Callback::Dating { dating_id, code: RateCode::Dislike }.to_string(),
+    )
+    .await?;
+    assert!(!env.texts_to(&CAROL).last().unwrap().contains("Alice"));
+
+    // The status expires with the event
+    {
+        use entities::{events, prelude::Events};
+        use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, Set};
+
+        let conn = sea_orm::Database::connect(&env.db_url).await?;
+        Events::update_many()
+            .set(events::ActiveModel {
+                ends: Set(today - chrono::Duration::days(1)),
+                ..Default::default()
+            })
+            .filter(events::Column::Id.eq(1))
+            .exec(&conn)
+            .await?;
+        conn.close().await?;
+    }
+    env.send_text(&BOB, "/profile").await?;
+    let profile = env
+        .texts_to(&BOB)
+        .into_iter()
+        .rev()
+        .find(|t| t.starts_with("Так выглядит ваша анкета"))
+        .context("no profile")?;
+    assert!(!profile.contains("📍"));
+
+    env.send_text(&ADMIN, "/delevent 1").await?;
+    assert_eq!(env.texts_to(&ADMIN).last().unwrap(), "Событие удалено");
+    let alice = env.db.get_user(ALICE.id).await?.context("no Alice")?;
+    assert_eq!(alice.event_id, None);
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore = "needs TEST_DATABASE_URL"]
+async fn event_ranked_by_event_city() -> anyhow::Result<()> {
+    std::env::set_var("ADMIN_IDS", ADMIN.id.to_string());
+    let mut env = TestEnv::new().await?;
+    env.set_ranking(RankingWeights {
+        subjects: 0.0,
+        grade: 0.0,
+        same_city: 2.0,
+        same_subject: 0.0,
+        same_county: 0.0,
+        inactivity: 0.0,
+        liked: 1.0,
+        random: 0.0,
+    })
+    .await?;
+    env.register(&ADMIN);
+    create_profile(&env, &BOB, "Я парень").await?;
+    create_profile(&env, &CAROL, "Я девушка").await?;
+    env.send_text(&CAROL, "/date").await?;
+    let dating_id = dating_id(&env.last_buttons(&CAROL))?;
+    env.press(
+        &CAROL,
+        &Callback::Dating { dating_id, code: RateCode::Like }.to_string(),
+    )
+    .await?;
+    create_profile(&env, &ALICE, "Я девушка").await?;
+
+    let today = chrono::Local::now().date_naive();
+    env.send_text(
+        &ADMIN,
+        &format!(
+            "/addevent {} {} Сириус, летняя смена\nСочи",
+            (today - chrono::Duration::days(1)).format("%d.%m.%Y"),
+            (today + chrono::Duration::days(7)).format("%d.%m.%Y"),
+        ),
+    )
+    .await?;
+    let event = Callback::SetEvent { event_id: Some(1) }.to_string();
+    for user in [&ALICE, &BOB] {
+        env.send_text(user, "/event").await?;
+        env.press(user, &event).await?;
+    }
+
+    // Alice from Moscow is in Sochi with Bob, which outweighs Carol's like
+    env.send_text(&BOB, "/date").await?;
+    assert!(env.texts_to(&BOB).last().unwrap().contains("Alice"));
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore = "needs TEST_DATABASE_URL"]
+async fn matches_history() -> anyhow::Result<()> {
+    let env = TestEnv::new().await?;
+    two_likes_to_bob(&env).await?;
+
+    env.send_text(&BOB, "/matches").await?;
+    assert_eq!(env.texts_to(&BOB).last().unwrap(), crate::text::NO_MATCHES);
+
+    for _ in 0..2 {
+        env.send_text(&BOB, "/likes").await?;
+        let dating_id = dating_id(&env.last_buttons(&BOB))?;
+        env.press(
+            &BOB,
+            &Callback::Dating { dating_id, code: RateCode::ResponseLike }
+                .to_string(),
+        )
+        .await?;
+    }
+
+    env.send_text(&BOB, "/matches").await?;
+    let last = env.texts_to(&BOB).last().cloned().unwrap();
+    assert!(last.starts_with("Пара 1 из 2"));
+    assert!(last.contains("Carol"));
+
+    env.press(&BOB, &Callback::Matches { page: 1 }.to_string()).await?;
+    let last = env.texts_to(&BOB).last().cloned().unwrap();
+    assert!(last.starts_with("Пара 2 из 2"));
+    assert!(last.contains("Alice"));
+    let edits = env.sent_to(&BOB, "editMessageText");
+    let chat_button =
+        &edits.last().unwrap().body["reply_markup"]["inline_keyboard"][0][0];
+    assert_eq!(chat_button["url"], "tg://resolve?domain=Alice");
+
+    env.send_text(&ALICE, "/matches").await?;
+    assert!(env.texts_to(&ALICE).last().unwrap().contains("Bob"));
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore = "needs TEST_DATABASE_URL"]
+async fn block_recommendation() -> anyhow::Result<()> {
+    let env = TestEnv::new().await?;
+    create_profile(&env, &ALICE, "Я девушка").await?;
+    create_profile(&env, &BOB, "Я парень").await?;
+
+    env.send_text(&ALICE, "/date").await?;
+    let dating_id = dating_id(&env.last_buttons(&ALICE))?;
+    env.press(&ALICE, &Callback::Block { dating_id }.to_string()).await?;
+
+    let texts = env.texts_to(&ALICE);
+    assert_eq!(texts[texts.len() - 2], crate::text::USER_BLOCKED);
+    assert_eq!(texts[texts.len() - 1], crate::text::PARTNER_NOT_FOUND);
+
+    env.send_text(&BOB, "/date").await?;
+    assert_eq!(
+        env.texts_to(&BOB).last().unwrap(),
+        crate::text::PARTNER_NOT_FOUND
+    );
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore = "needs TEST_DATABASE_URL"]
+async fn block_unmatches() -> anyhow::Result<()> {
+    let env = TestEnv::new().await?;
+    create_profile(&env, &ALICE, "Я девушка").await?;
+    create_profile(&env, &BOB, "Я парень").await?;
+
+    env.send_text(&ALICE, "/date").await?;
+    let dating_id = dating_id(&env.last_buttons(&ALICE))?;
+    env.press(
+        &ALICE,
+        &Callback::Dating { dating_id, code: RateCode::Like }.to_string(),
+    )
+    .await?;
+    env.press(
+        &BOB,
+        &Callback::Dating { dating_id, code: RateCode::ResponseLike }
+            .to_string(),
+    )
+    .await?;
+
+    env.send_text(&BOB, "/matches").await?;
+    env.press(&BOB, &Callback::Block { dating_id }.to_string()).await?;
+
+    for user in [&ALICE, &BOB] {
+        env.send_text(user, "/matches").await?;
+        assert_eq!(
+            env.texts_to(user).last().unwrap(),
+            crate::text::NO_MATCHES
+        );
+    }
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore = "needs TEST_DATABASE_URL"]
+async fn blocked_user_cant_like() -> anyhow::Result<()> {
+    let env = TestEnv::new().await?;
+    create_profile(&env, &ALICE, "Я девушка").await?;
+    create_profile(&env, &BOB, "Я парень").await?;
+
+    env.send_text(&ALICE, "/date").await?;
+    let dating_id = dating_id(&env.last_buttons(&ALICE))?;
+    env.db.block_user(BOB.id, ALICE.id).await?;
+
+    let sent_to_bob = env.texts_to(&BOB).len();
+    env.press(
+        &ALICE,
+        &Callback::Dating { dating_id, code: RateCode::Like }.to_string(),
+    )
+    .await?;
+    assert_eq!(env.texts_to(&BOB).len(), sent_to_bob);
+    assert!(env
+        .texts_to(&ALICE)
+        .contains(&crate::text::USER_UNAVAILABLE.to_owned()));
+    assert_eq!(
+        env.db.get_dating(dating_id).await?.initiator_reaction,
+        Some(false)
+    );
+
+    env.send_text(&BOB, "/likes").await?;
+    assert_eq!(
+        env.texts_to(&BOB).last().unwrap(),
+        crate::text::NO_INCOMING_LIKES
+    );
+
+    let sent_to_bob = env.texts_to(&BOB).len();
+    let reminder = LikesReminder { threshold: 1, interval_hours: 24 };
+    reminder.remind(&env.bot, &env.db).await?;
+    assert_eq!(env.texts_to(&BOB).len(), sent_to_bob);
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore = "needs TEST_DATABASE_URL"]
+async fn blocked_user_cant_like_with_message() -> anyhow::Result<()> {
+    let env = TestEnv::new().await?;
+    create_profile(&env, &ALICE, "Я девушка").await?;
+    create_profile(&env, &BOB, "Я парень").await?;
+
+    env.send_text(&ALICE, "/date").await?;
+    let dating_id = dating_id(&env.last_buttons(&ALICE))?;
+    env.press(
+        &ALICE,
+        &Callback::Dating { dating_id, code: RateCode::LikeWithMsg }
+            .to_string(),
+    )
+    .await?;
+    env.db.block_user(BOB.id, ALICE.id).await?;
+
+    let sent_to_bob = env.texts_to(&BOB).len();
+    env.send_text(&ALICE, "Привет!").await?;
+    assert_eq!(env.texts_to(&BOB).len(), sent_to_bob);
+    assert!(env
+        .texts_to(&ALICE)
+        .contains(&crate::text::USER_UNAVAILABLE.to_owned()));
+    let dating = env.db.get_dating(dating_id).await?;
+    assert_eq!(dating.initiator_reaction, Some(false));
+    assert_eq!(dating.initiator_msg, None);
+    Ok(())
+}
+
+/// Alice reports Bob's profile from the recommendation
+async fn report_bob(env: &TestEnv) -> anyhow::Result<()> {
+    std::env::set_var("ADMIN_CHAT_ID", ADMIN.id.to_string());
+    std::env::set_var("ADMIN_IDS", ADMIN.id.to_string());
+    env.register(&ADMIN);
+    create_profile(env, &ALICE, "Я девушка").await?;
+    create_profile(env, &BOB, "Я парень").await?;
+    env.db
+        .create_image(BOB.id, "photo".to_owned(), ImageKind::Image)
+        .await?;
+
+    env.send_text(&ALICE, "/date").await?;
+    let dating_id = dating_id(&env.last_buttons(&ALICE))?;
+    env.press(
+        &ALICE,
+        &Callback::Report { dating_id, step: ReportStep::ChooseReason }
+            .to_string(),
+    )
+    .await?;
+    env.press(
+        &ALICE,
+        &Callback::Report {
+            dating_id,
+            step: ReportStep::Reason(ReportReason::Photos),
+        }
+        .to_string(),
+    )
+    .await
+}
+
+#[tokio::test]
+#[ignore = "needs TEST_DATABASE_URL"]
+async fn report_cancel() -> anyhow::Result<()> {
+    let env = TestEnv::new().await?;
+    create_profile(&env, &ALICE, "Я девушка").await?;
+    create_profile(&env, &BOB, "Я парень").await?;
+
+    env.send_text(&ALICE, "/date").await?;
+    let buttons = env.last_buttons(&ALICE);
+    let dating_id = dating_id(&buttons)?;
+    env.press(
+        &ALICE,
+        &Callback::Report { dating_id, step: ReportStep::ChooseReason }
+            .to_string(),
+    )
+    .await?;
+    assert_eq!(
+        env.texts_to(&ALICE).last().unwrap(),
+        crate::text::REQUEST_REPORT_REASON
+    );
+
+    env.press(
+        &ALICE,
+        &Callback::Report { dating_id, step: ReportStep::Cancel }.to_string(),
+    )
+    .await?;
+    assert_eq!(env.last_buttons(&ALICE), buttons);
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore = "needs TEST_DATABASE_URL"]
+async fn report_and_ban() -> anyhow::Result<()> {
+    let env = TestEnv::new().await?;
+    report_bob(&env).await?;
+
+    assert!(env
+        .texts_to(&ALICE)
+        .iter()
+        .any(|t| t == crate::text::REPORT_SENT));
+    let report = env.texts_to(&ADMIN).last().cloned().unwrap();
+    assert!(report.starts_with("Жалоба #1\nПричина: Фото"));
+    assert!(report.contains("Bob"));
+
+    // Bob likes Alice before he is banned
+    env.send_text(&BOB, "/date").await?;
+    assert!(env.texts_to(&BOB).last().unwrap().contains("Alice"));
+    let like_id = dating_id(&env.last_buttons(&BOB))?;
+    env.press(
+        &BOB,
+        &Callback::Dating { dating_id: like_id, code: RateCode::Like }
+            .to_string(),
+    )
+    .await?;
+
+    let report_msg = env.api.messages_to(ADMIN.id).pop().unwrap();
+    // Dave is in the admin chat, but isn't an admin
+    let ban = Callback::Moderate {
+        report_id: 1,
+        resolution: ReportResolution::Ban,
+    };
+    env.press_on(&DAVE, report_msg.clone(), &ban.to_string()).await?;
+    assert!(env.api.sent().iter().any(|s| {
+        s.method == "answercallbackquery"
+            && s.body["text"] == crate::text::NOT_MODERATOR
+    }));
+    assert!(!env.db.get_user(BOB.id).await?.context("no Bob")?.banned);
+
+    env.press(
+        &ADMIN,
+        &Callback::Moderate { report_id: 1, resolution: ReportResolution::Ban }
+            .to_string(),
+    )
+    .await?;
+    assert!(env
+        .texts_to(&ADMIN)
+        .last()
+        .unwrap()
+        .ends_with("Решение: 🔨 Забанить"));
+
+    // Another admin presses a button of the same report
+    let dismiss = Callback::Moderate {
+        report_id: 1,
+        resolution: ReportResolution::Dismiss,
+    };
+    env.press_on(&ADMIN, report_msg, &dismiss.to_string()).await?;
+    assert!(env.api.sent().iter().any(|s| {
+        s.method == "answercallbackquery"
+            && s.body["text"] == crate::text::REPORT_ALREADY_RESOLVED
+    }));
+    assert!(env
+        .texts_to(&ADMIN)
+        .last()
+        .unwrap()
+        .ends_with("Решение: 🔨 Забанить"));
+    assert_eq!(
+        env.texts_to(&BOB).last().unwrap(),
+        crate::text::PROFILE_BANNED
+    );
+    assert!(!env.db.get_user(BOB.id).await?.context("no Bob")?.active);
+
+    // The like of the banned user is gone and can't be answered
+    env.send_text(&ALICE, "/likes").await?;
+    assert_eq!(
+        env.texts_to(&ALICE).last().unwrap(),
+        crate::text::NO_INCOMING_LIKES
+    );
+    let sent_to_bob = env.texts_to(&BOB).len();
+    env.press(
+        &ALICE,
+        &Callback::Dating { dating_id: like_id, code: RateCode::ResponseLike }
+            .to_string(),
+    )
+    .await?;
+    assert_eq!(
+        env.texts_to(&ALICE).last().unwrap(),
+        crate::text::USER_UNAVAILABLE
+    );
+    assert_eq!(env.texts_to(&BOB).len(), sent_to_bob);
+
+    env.send_text(&BOB, "/enable").await?;
+    assert_eq!(
+        env.texts_to(&BOB).last().unwrap(),
+        crate::text::PROFILE_BANNED
+    );
+    assert!(!env.db.get_user(BOB.id).await?.context("no Bob")?.active);
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore = "needs TEST_DATABASE_URL"]
+async fn banned_and_disabled_users_cant_date() -> anyhow::Result<()> {
+    use entities::{prelude::Users, users};
+    use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, Set};
+
+    let env = TestEnv::new().await?;
+    create_profile(&env, &ALICE, "Я девушка").await?;
+    create_profile(&env, &BOB, "Я парень").await?;
+
+    // Alice is banned with Bob's profile still on the screen
+    env.send_text(&ALICE, "/date").await?;
+    let dating_id = dating_id(&env.last_buttons(&ALICE))?;
+    env.db.set_banned(ALICE.id, true).await?;
+    let sent_to_bob = env.texts_to(&BOB).len();
+    env.press(
+        &ALICE,
+        &Callback::Dating { dating_id, code: RateCode::Like }.to_string(),
+    )
+    .await?;
+    assert_eq!(
+        env.texts_to(&ALICE).last().unwrap(),
+        crate::text::PROFILE_BANNED
+    );
+    assert_eq!(env.texts_to(&BOB).len(), sent_to_bob);
+    assert_eq!(env.db.get_dating(dating_id).await?.initiator_reaction, None);
+
+    env.send_text(&ALICE, "/date").await?;
+    assert_eq!(
+        env.texts_to(&ALICE).last().unwrap(),
+        crate::text::PROFILE_BANNED
+    );
+
+    // Banned profiles aren't recommended even if they are active
+    create_profile(&env, &CAROL, "Я девушка").await?;
+    let conn = sea_orm::Database::connect(&env.db_url).await?;
+    Users::update_many()
+        .set(users::ActiveModel { banned: Set(true), ..Default::default() })
+        .filter(users::Column::Id.eq(CAROL.id))
+        .exec(&conn)
+        .await?;
+    conn.close().await?;
+    env.send_text(&BOB, "/date").await?;
+    assert_eq!(
+        env.texts_to(&BOB).last().unwrap(),
+        crate::text::PARTNER_NOT_FOUND
+    );
+
+    env.send_text(&BOB, "/disable").await?;
+    env.send_text(&BOB, "/date").await?;
+    assert_eq!(
+        env.texts_to(&BOB).last().unwrap(),
+        crate::text::ENABLE_TO_DATE
+    );
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore = "needs TEST_DATABASE_URL"]
+async fn unavailable_recommendation_dropped() -> anyhow::Result<()> {
+    let env = TestEnv::new().await?;
+    create_profile(&env, &ALICE, "Я девушка").await?;
+    create_profile(&env, &BOB, "Я парень").await?;
+
+    // Bob blocks Alice while his profile is on her screen
+    env.send_text(&ALICE, "/date").await?;
+    let dating_id = dating_id(&env.last_buttons(&ALICE))?;
+    env.db.block_user(BOB.id, ALICE.id).await?;
+    env.send_text(&ALICE, "/date").await?;
+    assert_eq!(
+        env.texts_to(&ALICE).last().unwrap(),
+        crate::text::PARTNER_NOT_FOUND
+    );
+    assert_eq!(
+        env.db.get_dating(dating_id).await?.initiator_reaction,
+        Some(false)
+    );
+
+    // Carol disables her profile while it's on Alice's screen
+    create_profile(&env, &CAROL, "Я девушка").await?;
+    env.send_text(&ALICE, "/date").await?;
+    assert!(env.texts_to(&ALICE).last().unwrap().contains("Carol"));
+    env.send_text(&CAROL, "/disable").await?;
+    env.send_text(&ALICE, "/date").await?;
+    assert_eq!(
+        env.texts_to(&ALICE).last().unwrap(),
+        crate::text::PARTNER_NOT_FOUND
+    );
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore = "needs TEST_DATABASE_URL"]
+async fn report_hide_photos() -> anyhow::Result<()> {
+    let env = TestEnv::new().await?;
+    report_bob(&env).await?;
+
+    env.press(
+        &ADMIN,
+        &Callback::Moderate {
+            report_id: 1,
+            resolution: ReportResolution::HidePhotos,
+        }
+        .to_string(),
+    )
+    .await?;
+    assert!(env.db.get_images(BOB.id).await?.is_empty());
+    assert!(env.db.get_user(BOB.id).await?.context("no Bob")?.active);
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore = "needs TEST_DATABASE_URL"]
+async fn admin_commands() -> anyhow::Result<()> {
+    std::env::set_var("ADMIN_IDS", ADMIN.id.to_string());
+    let env = TestEnv::new().await?;
+    env.register(&ADMIN);
+    create_profile(&env, &ALICE, "Я девушка").await?;
+    create_profile(&env, &BOB, "Я парень").await?;
+
+    env.send_text(&ADMIN, "/stats").await?;
+    assert!(env.texts_to(&ADMIN).last().unwrap().starts_with("Анкет: 2\n"));
+
+    env.send_text(&ADMIN, &format!("/user {}", ALICE.id)).await?;
+    let info = env.texts_to(&ADMIN).last().cloned().unwrap();
+    assert!(info.contains("Alice"));
+    assert!(info.contains(&format!("id: {}", ALICE.id)));
+
+    env.send_text(&ADMIN, "/user 42").await?;
+    assert_eq!(
+        env.texts_to(&ADMIN).last().unwrap(),
+        crate::text::USER_NOT_FOUND
+    );
+
+    env.send_text(&ADMIN, &format!("/ban {}", BOB.id)).await?;
+    assert_eq!(
+        env.texts_to(&BOB).last().unwrap(),
+        crate::text::PROFILE_BANNED
+    );
+    let bob = env.db.get_user(BOB.id).await?.context("no Bob")?;
+    assert!(bob.banned && !bob.active);
+
+    env.send_text(&ADMIN, &format!("/unban {}", BOB.id)).await?;
+    env.send_text(&BOB, "/enable").await?;
+    let bob = env.db.get_user(BOB.id).await?.context("no Bob")?;
+    assert!(!bob.banned && bob.active);
+
+    env.send_text(&ADMIN, &format!("/deactivate {}", ALICE.id)).await?;
+    assert!(!env.db.get_user(ALICE.id).await?.context("no Alice")?.active);
+
+    env.send_text(&ADMIN, &format!("/say {} Привет, Алиса!", ALICE.id))
+        .await?;
+    assert_eq!(env.texts_to(&ALICE).last().unwrap(), "Привет, Алиса!");
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore = "needs TEST_DATABASE_URL"]
+async fn admin_commands_hidden() -> anyhow::Result<()> {
+    std::env::set_var("ADMIN_IDS", ADMIN.id.to_string());
+    let env = TestEnv::new().await?;
+    create_profile(&env, &ALICE, "Я девушка").await?;
+
+    env.send_text(&ALICE, "/stats").await?;
+    assert!(!env.texts_to(&ALICE).iter().any(|t| t.starts_with("Анкет:")));
+
+    crate::admin::set_commands(&env.bot).await?;
+    let scopes: Vec<_> = env
+        .api
+        .sent()
+        .into_iter()
+        .filter(|s| s.method == "setmycommands")
+        .map(|s| s.body["scope"].clone())
+        .collect();
+    assert_eq!(scopes.len(), 2);
+    assert!(scopes[0].is_null());
+    assert_eq!(scopes[1]["chat_id"], ADMIN.id);
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore = "needs TEST_DATABASE_URL"]
+async fn broadcast() -> anyhow::Result<()> {
+    std::env::set_var("ADMIN_IDS", ADMIN.id.to_string());
+    let env = TestEnv::new().await?;
+    env.register(&ADMIN);
+    create_profile(&env, &ALICE, "Я девушка").await?;
+    create_profile(&env, &BOB, "Я парень").await?;
+    create_profile(&env, &CAROL, "Я девушка").await?;
+    env.api.block_bot(BOB.id);
+
+    env.send_text(&ADMIN, "/broadcast all\nНовый сезон олимпиад!").await?;
+    assert!(env
+        .texts_to(&ADMIN)
+        .last()
+        .unwrap()
+        .starts_with("Рассылка #1 запущена, получателей: 3."));
+    Broadcaster::default().send_pending(&env.bot, &env.db).await?;
+
+    for user in [&ALICE, &CAROL] {
+        assert_eq!(env.texts_to(user).last().unwrap(), "Новый сезон олимпиад!");
+    }
+    assert!(!env.db.get_user(BOB.id).await?.context("no Bob")?.active);
+    assert_eq!(
+        env.texts_to(&ADMIN).last().unwrap(),
+        "Рассылка #1 завершена\nДоставлено: 2\nЗаблокировали бота: 1\n\
+         Ошибок: 0"
+    );
+
+    env.press(&ALICE, &Callback::BroadcastOptOut.to_string()).await?;
+    assert_eq!(
+        env.texts_to(&ALICE).last().unwrap(),
+        crate::text::BROADCAST_OPTED_OUT
+    );
+
+    // Alice opted out and Bob was deactivated
+    env.send_text(&ADMIN, "/broadcast active\nВторая новость").await?;
+    assert!(env
+        .texts_to(&ADMIN)
+        .last()
+        .unwrap()
+        .starts_with("Рассылка #2 запущена, получателей: 1."));
+    env.send_text(&ADMIN, "/broadcast year=1900\nНикому").await?;
+    assert!(env
+        .texts_to(&ADMIN)
+        .last()
+        .unwrap()
+        .starts_with("Рассылка #3 запущена, получателей: 0."));
+    Broadcaster::default().send_pending(&env.bot, &env.db).await?;
+
+    assert!(!env.texts_to(&ALICE).iter().any(|t| t == "Вторая новость"));
+    assert_eq!(env.texts_to(&CAROL).last().unwrap(), "Вторая новость");
+
+    env.send_text(&ALICE, "/subscribe").await?;
+    let alice = env.db.get_user(ALICE.id).await?.context("no Alice")?;
+    assert!(!alice.broadcast_opt_out);
+
+    // Everyone studies math
+    env.send_text(&ADMIN, "/broadcast subjects=Physics,Chemistry
+Физикам")
+        .await?;
+    assert!(env
+        .texts_to(&ADMIN)
+        .last()
+        .unwrap()
+        .starts_with("Рассылка #4 запущена, получателей: 0."));
+    env.send_text(&ADMIN, "/broadcast subjects=Math,Physics
+Математикам")
+        .await?;
+    assert!(env
+        .texts_to(&ADMIN)
+        .last()
+        .unwrap()
+        .starts_with("Рассылка #5 запущена, получателей: 3."));
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore = "needs TEST_DATABASE_URL"]
+async fn broadcast_resumes_after_restart() -> anyhow::Result<()> {
+    std::env::set_var("ADMIN_IDS", ADMIN.id.to_string());
+    let mut env = TestEnv::new().await?;
+    env.register(&ADMIN);
+    create_profile(&env, &ALICE, "Я девушка").await?;
+    create_profile(&env, &BOB, "Я парень").await?;
+
+    env.send_text(&ADMIN, "/broadcast all\nНовость").await?;
+    // The bot stopped right after sending the broadcast to Alice
+    env.db.update_broadcast_progress(1, ALICE.id, Delivery::Sent).await?;
+    env.restart().await?;
+    Broadcaster::default().send_pending(&env.bot, &env.db).await?;
+
+    assert!(!env.texts_to(&ALICE).iter().any(|t| t == "Новость"));
+    assert_eq!(env.texts_to(&BOB).last().unwrap(), "Новость");
+    assert!(env
+        .texts_to(&ADMIN)
+        .last()
+        .unwrap()
+        .starts_with("Рассылка #1 завершена\nДоставлено: 2\n"));
+    Ok(())
+}
+
+/// Value of the metric with exactly these labels
+fn metric(metrics: &str, series: &str) -> Option<f64> {
+    metrics
+        .lines()
+        .find_map(|l| l.strip_prefix(series)?.strip_prefix(' '))
+        .and_then(|v| v.parse().ok())
+}
+
+#[tokio::test]
+#[ignore = "needs TEST_DATABASE_URL"]
+async fn metrics() -> anyhow::Result<()> {
+    let env = TestEnv::new().await?;
+    create_profile(&env, &ALICE, "Я девушка").await?;
+    create_profile(&env, &BOB, "Я парень").await?;
+
+    env.send_text(&ALICE, "/date").await?;
+    let dating_id = dating_id(&env.last_buttons(&ALICE))?;
+    env.press(
+        &ALICE,
+        &Callback::Dating { dating_id, code: RateCode::Like }.to_string(),
+    )
+    .await?;
+    env.press(
+        &BOB,
+        &Callback::Dating { dating_id, code: RateCode::ResponseLike }
+            .to_string(),
+    )
+    .await?;
+    crate::metrics::api_error(&teloxide::RequestError::Api(
+        teloxide::ApiError::BotBlocked,
+    ));
+
+    // Counters are global, so other tests may have increased them too
+    let metrics = crate::metrics::render(&env.db).await?;
+    for series in [
+        r#"bvilovebot_updates_total{kind="Date",type="command"}"#,
+        r#"bvilovebot_updates_total{kind="👍",type="callback"}"#,
+        r#"bvilovebot_updates_total{kind="SetName",type="message"}"#,
+        r#"bvilovebot_state_transitions_total{from="Start",to="SetName"}"#,
+        r#"bvilovebot_partner_searches_total{result="hit"}"#,
+        r#"bvilovebot_partner_search_seconds_bucket{le="+Inf"}"#,
+        "bvilovebot_likes_total",
+        "bvilovebot_matches_total",
+        r#"bvilovebot_api_errors_total{error="BotBlocked"}"#,
+    ] {
+        let value = metric(&metrics, series)
+            .with_context(|| format!("no {series} in metrics"))?;
+        assert!(value >= 1.0, "{series} is {value}");
+    }
+    assert_eq!(metric(&metrics, "bvilovebot_active_users"), Some(2.0));
+    Ok(())
+}
@@ -0,0 +1,312 @@
+use std::{
+    collections::{HashMap, HashSet},
+    convert::Infallible,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use hyper::{
+    body::to_bytes,
+    service::{make_service_fn, service_fn},
+    Body, Request, Response, Server,
+};
+use serde_json::{json, Value};
+
+/// A request the bot has sent to the Bot API
+#[derive(Clone, Debug)]
+pub struct Sent {
+    pub method: String,
+    /// JSON payload, `Null` for multipart requests (e.g. `sendMediaGroup`)
+    pub body: Value,
+}
+
+#[derive(Default)]
+struct Inner {
+    sent: Vec<Sent>,
+    /// Messages sent by the bot, as they would be returned by Telegram
+    messages: HashMap<i32, Value>,
+    /// Chats known to the fake server, returned from `getChat`
+    chats: HashMap<i64, Value>,
+    /// Chats where the user has blocked the bot
+    blocked: HashSet<i64>,
+    /// File ids that Telegram doesn't know anymore
+    dead_files: HashSet<String>,
+    /// Time every file download takes
+    download_delay: Duration,
+    last_message_id: i32,
+}
+
+/// Local HTTP server that pretends to be the Telegram Bot API and records
+/// every outgoing request
+#[derive(Clone)]
+pub struct FakeApi {
+    inner: Arc<Mutex<Inner>>,
+    addr: SocketAddr,
+}
+
+impl FakeApi {
+    pub fn start() -> Self {
+        let inner = Arc::new(Mutex::new(Inner::default()));
+
+        let service_inner = inner.clone();
+        let make_service = make_service_fn(move |_| {
+            let inner = service_inner.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |req| {
+                    handle_request(inner.clone(), req)
+                }))
+            }
+        });
+
+        let server = Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0)))
+            .serve(make_service);
+        let addr = server.local_addr();
+        tokio::spawn(server);
+
+        Self { inner, addr }
+    }
+
+    pub fn url(&self) -> url::Url {
+        url::Url::parse(&format!("http://{}", self.addr))
+            .expect("fake api url must be parsed")
+    }
+
+    pub fn add_chat(&self, chat: Value) {
+        let id = chat["id"].as_i64().expect("chat must have an id");
+        self.inner.lock().unwrap().chats.insert(id, chat);
+    }
+
+    /// Fail sending messages to the chat as if the user blocked the bot
+    pub fn block_bot(&self, chat: i64) {
+        self.inner.lock().unwrap().blocked.insert(chat);
+    }
+
+    /// Reject sending the file by its id, as after a bot token change
+    pub fn kill_file(&self, file_id: &str) {
+        self.inner.lock().unwrap().dead_files.insert(file_id.to_owned());
+    }
+
+    /// Make file downloads slow, as for big files
+    pub fn delay_downloads(&self, delay: Duration) {
+        self.inner.lock().unwrap().download_delay = delay;
+    }
+
+    pub fn next_message_id(&self) -> i32 {
+        let mut inner = self.inner.lock().unwrap();
+        inner.last_message_id += 1;
+        inner.last_message_id
+    }
+
+    pub fn sent(&self) -> Vec<Sent> {
+        self.inner.lock().unwrap().sent.clone()
+    }
+
+    /// All messages sent by the bot to the chat, oldest first
+    pub fn messages_to(&self, chat: i64) -> Vec<Value> {
+        let mut messages: Vec<_> = self
+            .inner
+            .lock()
+            .unwrap()
+            .messages
+            .values()
+            .filter(|m| m["chat"]["id"].as_i64() == Some(chat))
+            .cloned()
+            .collect();
+        messages.sort_by_key(|m| m["message_id"].as_i64());
+        messages
+    }
+}
+
+async fn handle_request(
+    inner: Arc<Mutex<Inner>>,
+    req: Request<Body>,
+) -> Result<Response<Body>, Infallible> {
+    let path = req.uri().path().to_owned();
+    // Files are downloaded from `/file/bot<token>/<file_path>`
+    if path.starts_with("/file/") {
+        let delay = {
+            let mut inner = inner.lock().unwrap();
+            inner.sent.push(Sent {
+                method: "download".to_owned(),
+                body: json!(path),
+            });
+            inner.download_delay
+        };
+        tokio::time::sleep(delay).await;
+        return Ok(Response::new(Body::from(format!("content of {path}"))));
+    }
+    let method =
+        path.rsplit('/').next().unwrap_or_default().to_lowercase();
+    let is_json = req
+        .headers()
+        .get(hyper::header::CONTENT_TYPE)
+        .is_some_and(|c| c.as_bytes().starts_with(b"application/json"));
+    let bytes = to_bytes(req.into_body()).await.unwrap_or_default();
+    let body = if is_json {
+        serde_json::from_slice(&bytes).unwrap_or(Value::Null)
+    } else {
+        Value::Null
+    };
+
+    let response = {
+        let mut inner = inner.lock().unwrap();
+        inner.sent.push(Sent { method: method.clone(), body: body.clone() });
+        let chat = body["chat_id"].as_i64().unwrap_or_default();
+        // Multipart bodies have file ids as values of fields or in JSON
+        let raw = String::from_utf8_lossy(&bytes);
+        let dead_file = inner.dead_files.iter().any(|id| {
+            raw.contains(&format!("\r\n\r\n{id}\r\n"))
+                || raw.contains(&format!("\"{id}\""))
+        });
+        if method.starts_with("send") && dead_file {
+            json!({
+                "ok": false,
+                "error_code": 400,
+                "description":
+                    "Bad Request: wrong file identifier/HTTP URL specified",
+            })
+        } else if method.starts_with("send") && inner.blocked.contains(&chat) {
+            json!({
+                "ok": false,
+                "error_code": 403,
+                "description": "Forbidden: bot was blocked by the user",
+            })
+        } else {
+            match respond(&mut inner, &method, &body) {
+                Ok(result) => json!({ "ok": true, "result": result }),
+                Err(description) => json!({
+                    "ok": false,
+                    "error_code": 400,
+                    "description": description,
+                }),
+            }
+        }
+    };
+
+    Ok(Response::new(Body::from(response.to_string())))
+}
+
+/// Result of the Bot API method or an error description
+fn respond(
+    inner: &mut Inner,
+    method: &str,
+    body: &Value,
+) -> Result<Value, &'static str> {
+    Ok(match method {
+        "getme" => bot_user(),
+        "getchat" => {
+            let id = body["chat_id"].as_i64().unwrap_or_default();
+            inner.chats.get(&id).cloned().unwrap_or_else(|| {
+                json!({ "id": id, "type": "private", "first_name": "User" })
+            })
+        }
+        "getchatmember" => json!({
+            "status": "member",
+            "user": {
+                "id": body["user_id"],
+                "is_bot": false,
+                "first_name": "User",
+            },
+        }),
+        "getfile" => json!({
+            "file_id": body["file_id"],
+            "file_unique_id": body["file_id"],
+            "file_size": 1,
+            "file_path": "file",
+        }),
+        "sendmessage" => {
+            inner.last_message_id += 1;
+            let id = inner.last_message_id;
+            let chat_id = body["chat_id"].as_i64().unwrap_or_default();
+            let mut message = json!({
+                "message_id": id,
+                "date": chrono::Utc::now().timestamp(),
+                "chat": inner.chats.get(&chat_id).cloned().unwrap_or_else(
+                    || json!({ "id": chat_id, "type": "private" }),
+                ),
+                "from": bot_user(),
+                "text": body["text"],
+            });
+            if body["reply_markup"].get("inline_keyboard").is_some() {
+                message["reply_markup"] = body["reply_markup"].clone();
+            }
+            if let Some(reply_to) = body["reply_to_message_id"]
+                .as_i64()
+                .and_then(|id| inner.messages.get(&(id as i32)))
+            {
+                message["reply_to_message"] = reply_to.clone();
+            }
+            inner.messages.insert(id, message.clone());
+            message
+        }
+        "editmessagetext" | "editmessagereplymarkup" => {
+            let id = body["message_id"].as_i64().unwrap_or_default() as i32;
+            // Like Telegram, only allow editing bot's own messages
+            let message = inner
+                .messages
+                .get_mut(&id)
+                .ok_or("Bad Request: message can't be edited")?;
+            if let Some(text) = body.get("text") {
+                message["text"] = text.clone();
+            }
+            match body.get("reply_markup") {
+                Some(markup) => message["reply_markup"] = markup.clone(),
+                None => {
+                    message.as_object_mut().unwrap().remove("reply_markup");
+                }
+            }
+            message.clone()
+        }
+        "deletemessage" => {
+            let id = body["message_id"].as_i64().unwrap_or_default() as i32;
+            inner
+                .messages
+                .remove(&id)
+                .ok_or("Bad Request: message to delete not found")?;
+            json!(true)
+        }
+        "sendmediagroup" => json!([]),
+        // Multipart requests have no chat, these messages aren't stored
+        "sendvoice" | "sendvideonote" | "sendanimation" => {
+            inner.last_message_id += 1;
+            let id = inner.last_message_id;
+            let kind = match method {
+                "sendvoice" => "voice",
+                "sendvideonote" => "video_note",
+                _ => "animation",
+            };
+            let mut message = json!({
+                "message_id": id,
+                "date": chrono::Utc::now().timestamp(),
+                "chat": { "id": 0, "type": "private" },
+                "from": bot_user(),
+            });
+            // Uploaded files get new ids
+            message[kind] = json!({
+                "file_id": format!("uploaded{id}"),
+                "file_unique_id": format!("uploaded{id}"),
+                "file_size": 1,
+                "duration": 1,
+                "mime_type": "video/mp4",
+                "length": 1,
+                "width": 1,
+                "height": 1,
+            });
+            message
+        }
+        _ => json!(true),
+    })
+}
+
+pub fn bot_user() -> Value {
+    json!({
+        "id": 1,
+        "is_bot": true,
+        "first_name": "bvilovebot",
+        "username": "bvilovebot",
+        "can_join_groups": false,
+        "can_read_all_group_messages": false,
+        "supports_inline_queries": false,
+    })
+}
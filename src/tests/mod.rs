@@ -0,0 +1,302 @@
+//! End-to-end dialogue tests.
+//!
+//! The bot talks to [`fake_api::FakeApi`] instead of Telegram, and updates
+//! are fed straight into the dispatcher schema. The database is real: tests
+//! need an empty Postgres database in `TEST_DATABASE_URL` (it's wiped before
+//! every test) and are ignored by default, run them with
+//! `cargo test -- --ignored`.
+
+// TestEnv holds the database lock until the end of the test on purpose
+#![allow(clippy::significant_drop_tightening)]
+
+use std::sync::{
+    atomic::{AtomicI32, Ordering},
+    Arc, OnceLock,
+};
+
+use anyhow::{bail, Context};
+use migration::{Migrator, MigratorTrait};
+use serde_json::{json, Value};
+use teloxide::{
+    adaptors::throttle::Limits,
+    dispatching::UpdateHandler,
+    prelude::*,
+    types::{Me, Update},
+};
+use tokio::sync::{Mutex, MutexGuard};
+
+use crate::{db::Database, ranking::RankingWeights, Bot};
+
+mod dialogues;
+mod fake_api;
+
+pub use fake_api::{FakeApi, Sent};
+
+/// Tests share one database, so they must not run concurrently
+fn db_lock() -> &'static Mutex<()> {
+    static LOCK: OnceLock<Mutex<()>> = OnceLock::new();
+    LOCK.get_or_init(|| Mutex::new(()))
+}
+
+#[derive(Clone, Debug)]
+pub struct TestUser {
+    pub id: i64,
+    pub first_name: &'static str,
+    pub username: Option<&'static str>,
+}
+
+impl TestUser {
+    pub const fn new(id: i64, first_name: &'static str) -> Self {
+        Self { id, first_name, username: Some(first_name) }
+    }
+
+    fn user_json(&self) -> Value {
+        json!({
+            "id": self.id,
+            "is_bot": false,
+            "first_name": self.first_name,
+            "username": self.username,
+        })
+    }
+
+    fn chat_json(&self) -> Value {
+        json!({
+            "id": self.id,
+            "type": "private",
+            "first_name": self.first_name,
+            "username": self.username,
+        })
+    }
+}
+
+pub struct TestEnv {
+    pub api: FakeApi,
+    pub bot: Bot,
+    pub db: Arc<Database>,
+    db_url: String,
+    me: Me,
+    handler: UpdateHandler<anyhow::Error>,
+    last_update_id: AtomicI32,
+    _lock: MutexGuard<'static, ()>,
+}
+
+impl TestEnv {
+    pub async fn new() -> anyhow::Result<Self> {
+        let lock = db_lock().lock().await;
+
+        let db_url = std::env::var("TEST_DATABASE_URL")
+            .context("TEST_DATABASE_URL must be set to run dialogue tests")?;
+        let conn = sea_orm::Database::connect(&db_url).await?;
+        Migrator::fresh(&conn).await?;
+        conn.close().await?;
+        let db = Arc::new(Database::connect(db_url.clone()).await?);
+
+        let api = FakeApi::start();
+        let bot = teloxide::Bot::new("TOKEN").set_api_url(api.url()).throttle(
+            Limits {
+                messages_per_sec_chat: 1000,
+                messages_per_min_chat: 60000,
+                messages_per_min_channel: 60000,
+                messages_per_sec_overall: 1000,
+            },
+        );
+
+        Ok(Self {
+            api,
+            bot,
+            db,
+            db_url,
+            me: serde_json::from_value(fake_api::bot_user())?,
+            handler: crate::schema(),
+            last_update_id: AtomicI32::new(0),
+            _lock: lock,
+        })
+    }
+
+    /// Reconnect to the database and rebuild the dispatcher, as if the bot
+    /// was restarted
+    pub async fn restart(&mut self) -> anyhow::Result<()> {
+        self.db = Arc::new(Database::connect(self.db_url.clone()).await?);
+        self.handler = crate::schema();
+        Ok(())
+    }
+
+    /// Reconnect to the database with the given partner ranking
+    pub async fn set_ranking(
+        &mut self,
+        ranking: RankingWeights,
+    ) -> anyhow::Result<()> {
+        let db = Database::connect(self.db_url.clone()).await?;
+        self.db = Arc::new(db.with_ranking(ranking));
+        Ok(())
+    }
+
+    pub fn register(&self, user: &TestUser) {
+        self.api.add_chat(user.chat_json());
+    }
+
+    async fn dispatch(&self, update: Value) -> anyhow::Result<()> {
+        // Update fails to deserialize from a `Value`, only from a string
+        let update: Update = serde_json::from_str(&update.to_string())?;
+        let deps = dptree::deps![
+            self.bot.clone(),
+            self.db.clone(),
+            self.me.clone(),
+            update
+        ];
+        match self.handler.dispatch(deps).await {
+            std::ops::ControlFlow::Break(result) => result,
+            std::ops::ControlFlow::Continue(_) => bail!("update not handled"),
+        }
+    }
+
+    fn next_update_id(&self) -> i32 {
+        self.last_update_id.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    /// Send a text message from the user to the bot
+    pub async fn send_text(
+        &self,
+        user: &TestUser,
+        text: &str,
+    ) -> anyhow::Result<()> {
+        let update = json!({
+            "update_id": self.next_update_id(),
+            "message": {
+                "message_id": self.api.next_message_id(),
+                "date": chrono::Utc::now().timestamp(),
+                "chat": user.chat_json(),
+                "from": user.user_json(),
+                "text": text,
+            },
+        });
+        self.dispatch(update).await
+    }
+
+    /// Send a photo from the user to the bot, as a part of the album if
+    /// `album` is set
+    pub async fn send_photo(
+        &self,
+        user: &TestUser,
+        file_id: &str,
+        album: Option<&str>,
+    ) -> anyhow::Result<()> {
+        let mut media = json!({
+            "photo": [{
+                "file_id": file_id,
+                "file_unique_id": file_id,
+                "file_size": 1,
+                "width": 1,
+                "height": 1,
+            }],
+        });
+        if let Some(album) = album {
+            media["media_group_id"] = album.into();
+        }
+        self.send_media(user, media).await
+    }
+
+    /// Send a message with the media fields, e.g. `{"voice": {...}}`, from
+    /// the user to the bot
+    pub async fn send_media(
+        &self,
+        user: &TestUser,
+        media: Value,
+    ) -> anyhow::Result<()> {
+        let mut message = json!({
+            "message_id": self.api.next_message_id(),
+            "date": chrono::Utc::now().timestamp(),
+            "chat": user.chat_json(),
+            "from": user.user_json(),
+        });
+        for (key, value) in media.as_object().context("media isn't a map")? {
+            message[key] = value.clone();
+        }
+        let update = json!({
+            "update_id": self.next_update_id(),
+            "message": message,
+        });
+        self.dispatch(update).await
+    }
+
+    /// Press the inline button with the callback data on the latest bot's
+    /// message that has it
+    pub async fn press(
+        &self,
+        user: &TestUser,
+        data: &str,
+    ) -> anyhow::Result<()> {
+        let message = self
+            .api
+            .messages_to(user.id)
+            .into_iter()
+            .rev()
+            .find(|m| buttons(m).iter().any(|b| b == data))
+            .with_context(|| format!("no message with \"{data}\" button"))?;
+        self.press_on(user, message, data).await
+    }
+
+    /// Press a button of the message as it was, e.g. one another admin has
+    /// already pressed
+    pub async fn press_on(
+        &self,
+        user: &TestUser,
+        message: Value,
+        data: &str,
+    ) -> anyhow::Result<()> {
+        let update = json!({
+            "update_id": self.next_update_id(),
+            "callback_query": {
+                "id": format!("cq{}", self.next_update_id()),
+                "from": user.user_json(),
+                "message": message,
+                "chat_instance": user.id.to_string(),
+                "data": data,
+            },
+        });
+        self.dispatch(update).await
+    }
+
+    /// Texts of all messages the bot sent to the user, oldest first
+    pub fn texts_to(&self, user: &TestUser) -> Vec<String> {
+        self.api
+            .messages_to(user.id)
+            .iter()
+            .filter_map(|m| m["text"].as_str().map(ToOwned::to_owned))
+            .collect()
+    }
+
+    /// Callback data of all inline buttons of the latest bot's message that
+    /// has any
+    pub fn last_buttons(&self, user: &TestUser) -> Vec<String> {
+        self.api
+            .messages_to(user.id)
+            .iter()
+            .rev()
+            .map(buttons)
+            .find(|b| !b.is_empty())
+            .unwrap_or_default()
+    }
+
+    /// Requests with the given Bot API method sent to the chat
+    pub fn sent_to(&self, user: &TestUser, method: &str) -> Vec<Sent> {
+        self.api
+            .sent()
+            .into_iter()
+            .filter(|s| {
+                s.method == method.to_lowercase()
+                    && s.body["chat_id"].as_i64() == Some(user.id)
+            })
+            .collect()
+    }
+}
+
+fn buttons(message: &Value) -> Vec<String> {
+    message["reply_markup"]["inline_keyboard"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .flat_map(|row| row.as_array().into_iter().flatten())
+        .filter_map(|b| b["callback_data"].as_str().map(ToOwned::to_owned))
+        .collect()
+}
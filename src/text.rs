@@ -9,22 +9,49 @@ pub const GENDER_FILTER_MALE: &str = "Парня";
 pub const GENDER_FILTER_FEMALE: &str = "Девушку";
 pub const GENDER_FILTER_ANY: &str = "Не важно";
 
-pub const REQUEST_GRADE: &str = "В каком вы сейчас классе?\n Примечание: если \
-                                 вы, например, окончили 9-ый класс, но ещё не \
-                                 поступили в 10-ый - вы в 9-ом.";
+pub const REQUEST_GRADE: &str =
+    "В каком вы сейчас классе?\n Примечание: если вы, например, окончили \
+     9-ый класс, но ещё не поступили в 10-ый - вы в 9-ом.\n\nСтуденты, \
+     напишите курс, например «2 курс». Если вы уже не учитесь, напишите год \
+     окончания школы.";
+
+pub const REQUEST_GRADE_FILTER: &str =
+    "Люди из каких классов вам интересны? ±1 - на класс младше, ваш класс и \
+     на класс старше.";
+pub const SHOW_UNIVERSITY: &str = "Показывать студентов";
+pub const UNIVERSITY_SHOWN: &str = " Студенты вузов тоже.";
 
 pub const EDIT_SUBJECTS: &str = "Какие предметы вы ботаете? Нажмите на \
-                                 предмет, чтобы добавить или убрать его.";
+                                 предмет, чтобы добавить или убрать его, \
+                                 стрелки листают разделы.";
 
 pub const EDIT_PARTNER_SUBJECTS: &str =
     "Выберите предметы, хотя бы один из которых должен ботать тот, кого вы \
-     ищете. Нажмите на предмет, чтобы добавить или убрать его.";
+     ищете. Нажмите на предмет, чтобы добавить или убрать его, стрелки \
+     листают разделы.";
+
+pub const REQUEST_OLYMPIAD: &str =
+    "Добавьте олимпиады, в которых вы участвовали. Выберите олимпиаду или \
+     нажмите «Готово»";
+pub const REQUEST_OLYMPIAD_SUBJECT: &str = "По какому предмету?";
+pub const REQUEST_OLYMPIAD_LEVEL: &str =
+    "Какой этап (для всероссийской олимпиады) или уровень олимпиады?";
+pub const REQUEST_OLYMPIAD_YEAR: &str = "В каком году?";
+pub const REQUEST_DIPLOMA: &str = "Какой у вас результат?";
+pub const ACHIEVEMENTS_DONE: &str = "Готово";
+pub const ACHIEVEMENTS_CLEAR: &str = "Удалить все";
+pub const ACHIEVEMENTS_CLEARED: &str = "Все олимпиады удалены из анкеты";
+pub const REQUEST_ACHIEVEMENTS_FILTER: &str =
+    "Какие олимпиады должны быть у того, кого вы ищете?";
 
 pub const EDIT_ABOUT: &str =
     "Расскажите о себе: чем занимаетесь, кого хотите найти";
 pub const SUBJECTS_CONTINUE: &str = "Продолжить";
 pub const SUBJECTS_PARTNER_EMPTY: &str = "Не важно";
 pub const SUBJECTS_USER_EMPTY: &str = "Никакие";
+pub const SUBJECTS_SELECT_ALL: &str = "выбрать все";
+pub const SUBJECTS_UNSELECT_ALL: &str = "убрать все";
+pub const SUBJECTS_CLEAR: &str = "Очистить";
 
 pub const EDIT_LOCATION_FILTER: &str =
     "Где вы хотите искать людей?\nПо всей стране, в своём федеральном округе, \
@@ -33,7 +60,19 @@ pub const EDIT_LOCATION_FILTER: &str =
 pub const NO_CITY: &str =
     "Так как вы не указали свой город, мы будем искать людей по всей России";
 
-pub const REQUEST_SET_PHOTOS: &str = "Отправьте парочку своих фото или видео";
+pub const REQUEST_SET_PHOTOS: &str =
+    "Отправьте парочку своих фото или видео. Ещё можно записать голосовое о \
+     себе, кружок или добавить GIF.";
+pub const MEDIA_TOO_LONG: &str =
+    "Голосовые и кружки должны быть не длиннее минуты.";
+pub const MEDIA_TOO_BIG: &str = "Файл слишком большой, максимум — 20 МБ.";
+pub const MANAGE_PHOTOS: &str =
+    "Фото и видео показываются в анкете в этом порядке. ⬆ и ⬇ двигают фото, \
+     ⭐ делает его первым, 🗑 удаляет.";
+pub const PHOTOS_EMPTY: &str = "В анкете нет фото и видео.";
+pub const PHOTOS_ADD: &str = "Добавить";
+pub const PHOTOS_DONE: &str = "Готово";
+pub const PHOTOS_UPLOADED: &str = "Фото загружены.";
 
 pub const PROFILE_CREATION_STARTED: &str =
     "Начинаем создавать анкету, это не займёт у вас много времени.\nНе \
@@ -78,6 +117,12 @@ pub const SEND_LIKE: &str = "Введите сообщение, которое 
                              вместе с лайком.Отправить можно только текст.";
 
 pub const REQUEST_EDIT: &str = "Что вы хотите изменить?";
+pub const EDIT_DONE: &str = "Готово";
+pub const CONFIRM_EDIT: &str = "Сохранить изменения?";
+pub const EDIT_SAVED: &str = "Сохранено ✅";
+pub const EDIT_CANCELLED: &str = "Изменения отменены.";
+pub const EDIT_UNCHANGED: &str = "Ничего не изменилось.";
+pub const PROFILE_CREATED: &str = "Анкета создана ✅";
 
 pub const PLEASE_ALLOW_FORWARDING: &str =
     "Пожалуйста, создайте имя пользователя или разрешите пересылку своих \
@@ -86,5 +131,51 @@ pub const PLEASE_ALLOW_FORWARDING: &str =
 
 pub const PROFILE_ENABLED: &str = "Ваша анкета включена ✅";
 pub const PROFILE_DISABLED: &str = "Ваша анкета выключена ❌";
+pub const ENABLE_TO_DATE: &str =
+    "Ваша анкета выключена, включить её можно командой /enable";
 pub const SUBSCRIBE_TEXT: &str =
     "Пожалуйста, подпишитесь на наш канал https://t.me/bvilove";
+
+pub const NO_INCOMING_LIKES: &str = "Новых лайков пока нет.";
+pub const NO_MATCHES: &str =
+    "У вас пока нет взаимных лайков. Найти пару можно командой /date";
+pub const MATCH_URL_UNAVAILABLE: &str =
+    "\n\nНе получилось сделать ссылку на чат: пользователь скрыл свой \
+     аккаунт.";
+
+pub const USER_BLOCKED: &str =
+    "Пользователь заблокирован, вы больше не увидите друг друга 🚫";
+pub const USER_UNAVAILABLE: &str = "Этот пользователь больше недоступен.";
+
+pub const REQUEST_REPORT_REASON: &str = "Что не так с этой анкетой?";
+pub const REPORT_SENT: &str =
+    "Спасибо! Жалоба отправлена модераторам, они её скоро рассмотрят.";
+pub const PROFILE_BANNED: &str =
+    "Ваша анкета заблокирована модераторами за нарушение правил.";
+pub const PHOTOS_HIDDEN: &str =
+    "Модераторы скрыли ваши фото из-за нарушения правил. Загрузить новые \
+     можно командой /edit";
+pub const REPORT_ALREADY_RESOLVED: &str = "Жалоба уже рассмотрена";
+pub const NOT_MODERATOR: &str = "Решать жалобы могут только администраторы";
+pub const PROFILE_UNBANNED: &str =
+    "Ваша анкета разблокирована, включить её можно командой /enable";
+pub const USER_NOT_FOUND: &str = "Пользователь не найден";
+pub const BROADCAST_OPTED_OUT: &str =
+    "Вы отписались от рассылки, вернуть её можно командой /subscribe";
+pub const BROADCAST_OPTED_IN: &str = "Вы подписались на рассылку";
+pub const PROFILE_DEACTIVATED: &str =
+    "Вы давно не заходили, поэтому мы выключили вашу анкету. Включить её \
+     снова можно командой /enable";
+pub const FINISH_PROFILE: &str =
+    "Вы так и не заполнили анкету! Это займёт всего пару минут";
+pub const GRADE_CONFIRMED: &str = "Спасибо, класс подтверждён ✅";
+pub const GRADE_NOT_CONFIRMED: &str =
+    "Вы не подтвердили класс в новом учебном году, поэтому мы выключили \
+     вашу анкету. Включить её снова можно командой /enable";
+pub const REQUEST_EVENT: &str =
+    "На каком вы событии? Пока оно идёт, вас увидят все его участники, \
+     а город в анкете заменит город события.";
+pub const NO_EVENTS: &str = "Сейчас нет событий, на которых можно отметиться";
+pub const NOT_AT_EVENT: &str = "Я не на событии";
+pub const EVENT_LEFT: &str = "Вы больше не на событии";
+pub const EVENT_ENDED: &str = "Это событие уже закончилось";
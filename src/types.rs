@@ -1,18 +1,24 @@
-use std::{fmt::Display, str::FromStr};
+use std::{collections::BTreeSet, fmt::Display, str::FromStr};
 
-use anyhow::bail;
+use anyhow::{bail, Context};
 use bitflags::bitflags;
 use chrono::Datelike;
 use entities::{
+    events,
     sea_orm_active_enums::{self, Gender},
     users,
 };
 use itertools::Itertools;
 use sea_orm::ActiveValue;
+use serde::{Deserialize, Serialize};
 
-use crate::cities::UserCity;
+use crate::{
+    callbacks::EditField,
+    cities::UserCity,
+    olympiads::{Achievement, AchievementFilter},
+};
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum LocationFilter {
     City,
     Subject,
@@ -42,6 +48,18 @@ impl From<LocationFilter> for sea_orm_active_enums::LocationFilter {
     }
 }
 
+impl LocationFilter {
+    /// Name that doesn't depend on the city
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::City => "Только в своём городе",
+            Self::Subject => "В своём субъекте",
+            Self::County => "В своём федеральном округе",
+            Self::Country => "Вся Россия",
+        }
+    }
+}
+
 impl FromStr for LocationFilter {
     type Err = anyhow::Error;
 
@@ -69,7 +87,7 @@ impl FromStr for LocationFilter {
 }
 
 /// Gender of user
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum UserGender {
     Female,
     Male,
@@ -117,7 +135,7 @@ impl Display for UserGender {
 }
 
 /// Filter of partner's gender
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum GenderFilter {
     Female,
     Male,
@@ -183,7 +201,7 @@ impl From<GraduationYear> for i16 {
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Grade(i8);
 
 impl TryFrom<i8> for Grade {
@@ -204,31 +222,226 @@ impl Display for Grade {
     }
 }
 
+/// Year when the current academic year started, it starts on September 1
+pub fn academic_year() -> i16 {
+    let date = chrono::Local::now();
+    if date.month() < 9 {
+        date.year() as i16 - 1
+    } else {
+        date.year() as i16
+    }
+}
+
 impl From<Grade> for GraduationYear {
     fn from(grade: Grade) -> Self {
-        let date = chrono::Local::now();
+        Self(academic_year() + 1 + (11 - i16::from(grade.0)))
+    }
+}
+
+/// University course, bachelor's and specialist's degrees take up to six
+/// years
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Course(i8);
 
-        let year = if date.month() < 9 {
-            date.year() as i16 + (11 - i16::from(grade.0))
+impl Course {
+    pub const MAX: i8 = 6;
+}
+
+impl TryFrom<i8> for Course {
+    type Error = ();
+
+    fn try_from(value: i8) -> Result<Self, Self::Error> {
+        if (1..=Self::MAX).contains(&value) {
+            Ok(Self(value))
         } else {
-            date.year() as i16 + (11 - i16::from(grade.0)) + 1
-        };
+            Err(())
+        }
+    }
+}
 
-        Self(year)
+impl Display for Course {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("{} курс", self.0))
     }
 }
 
-impl From<GraduationYear> for Grade {
-    fn from(graduation_year: GraduationYear) -> Self {
-        let date = chrono::Local::now();
+/// Where the user studies. All stages are stored as the year of finishing
+/// school, so grade filters work across them: the first course is a year
+/// older than the eleventh grade.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Stage {
+    School(Grade),
+    University(Course),
+    /// Not a student anymore, finished school in the year
+    Graduate(i16),
+}
 
-        let grade = if date.month() < 9 {
-            11 - (graduation_year.0 - date.year() as i16)
+impl Stage {
+    pub fn new(graduation_year: i16, university: bool) -> Self {
+        let grade = 12 - (graduation_year - academic_year());
+        let course = grade - 11;
+        if grade <= 11 {
+            // Grades below the first only come from broken data
+            Self::School(Grade(grade.max(1) as i8))
+        } else if university && course <= i16::from(Course::MAX) {
+            Self::University(Course(course as i8))
         } else {
-            11 - (graduation_year.0 - date.year() as i16) + 1
-        };
+            Self::Graduate(graduation_year)
+        }
+    }
+
+    pub fn graduation_year(&self) -> i16 {
+        match self {
+            Self::School(grade) => GraduationYear::from(grade.clone()).0,
+            Self::University(course) => {
+                academic_year() + 1 - i16::from(course.0)
+            }
+            Self::Graduate(year) => *year,
+        }
+    }
+
+    pub const fn is_school(&self) -> bool {
+        matches!(self, Self::School(_))
+    }
+
+    pub const fn is_university(&self) -> bool {
+        matches!(self, Self::University(_))
+    }
+}
 
-        Self(grade as i8)
+impl From<&users::Model> for Stage {
+    fn from(user: &users::Model) -> Self {
+        Self::new(user.graduation_year, user.university)
+    }
+}
+
+/// Parsed from the grade (`10`), the course (`2 курс`) or, for those who
+/// don't study anymore, the year of finishing school (`2015`)
+impl FromStr for Stage {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().to_lowercase();
+        if let Some(course) = s.strip_suffix("курс") {
+            let course = course.trim().parse::<i8>()?;
+            return Course::try_from(course)
+                .map(Self::University)
+                .map_err(|()| anyhow::anyhow!("wrong course"));
+        }
+        let number = s.parse::<i16>()?;
+        if (1..=11).contains(&number) {
+            Ok(Self::School(Grade(number as i8)))
+        } else if (1950..=academic_year()).contains(&number) {
+            Ok(Self::Graduate(number))
+        } else {
+            bail!("wrong grade or year")
+        }
+    }
+}
+
+impl Display for Stage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::School(grade) => f.write_fmt(format_args!("{grade}")),
+            Self::University(course) => f.write_fmt(format_args!("{course}")),
+            Self::Graduate(year) => {
+                f.write_fmt(format_args!("выпуск {year} года"))
+            }
+        }
+    }
+}
+
+/// Grades of partners the user is looking for, relative to the user's grade
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GradeFilter {
+    stage: Stage,
+    /// How many grades older the partner can be
+    up: i16,
+    /// How many grades younger the partner can be
+    down: i16,
+}
+
+impl GradeFilter {
+    /// Delta that includes every grade
+    pub const ANY: i16 = 100;
+    /// (up, down) deltas offered to the user
+    pub const PRESETS: [(i16, i16); 5] =
+        [(0, 0), (1, 1), (2, 2), (Self::ANY, 0), (0, Self::ANY)];
+
+    pub const fn new(stage: Stage, up: i16, down: i16) -> Self {
+        Self { stage, up, down }
+    }
+
+    /// Button name of the preset
+    pub const fn preset_name(up: i16, down: i16) -> &'static str {
+        match (up, down) {
+            (0, 0) => "±0",
+            (1, 1) => "±1",
+            (2, 2) => "±2",
+            (Self::ANY, 0) => "Любые старше",
+            (0, Self::ANY) => "Любые младше",
+            _ => "Другое",
+        }
+    }
+}
+
+impl From<&users::Model> for GradeFilter {
+    fn from(user: &users::Model) -> Self {
+        Self::new(user.into(), user.grade_up_filter, user.grade_down_filter)
+    }
+}
+
+impl Display for GradeFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let grade = match &self.stage {
+            Stage::School(grade) => i16::from(grade.0),
+            Stage::University(course) => {
+                let course = i16::from(course.0);
+                let max = i16::from(Course::MAX);
+                // Older students are on a higher course
+                let highest = (course + self.up).min(max);
+                let lowest = (course - self.down).max(1);
+                return if lowest == highest {
+                    f.write_fmt(format_args!("только {lowest} курс"))
+                } else if highest == max && self.up >= Self::ANY {
+                    f.write_fmt(format_args!("{lowest} курс и старше"))
+                } else if lowest == 1 && self.down >= Self::ANY {
+                    f.write_fmt(format_args!("{highest} курс и младше"))
+                } else {
+                    f.write_fmt(format_args!("с {lowest} по {highest} курс"))
+                };
+            }
+            Stage::Graduate(year) => {
+                // Older graduates finished school earlier
+                let earliest = year - self.up;
+                let latest = (year + self.down).min(academic_year());
+                return if earliest == latest {
+                    f.write_fmt(format_args!("только выпуск {latest} года"))
+                } else if self.up >= Self::ANY {
+                    f.write_fmt(format_args!("выпуск {latest} года и старше"))
+                } else if self.down >= Self::ANY {
+                    f.write_fmt(format_args!(
+                        "выпуск {earliest} года и младше"
+                    ))
+                } else {
+                    f.write_fmt(format_args!(
+                        "выпуски с {earliest} по {latest} год"
+                    ))
+                };
+            }
+        };
+        let highest = (grade + self.up).min(11);
+        let lowest = (grade - self.down).max(1);
+
+        if lowest == highest {
+            f.write_fmt(format_args!("только {lowest} класс"))
+        } else if highest == 11 && self.up >= Self::ANY {
+            f.write_fmt(format_args!("{lowest} класс и старше"))
+        } else if lowest == 1 && self.down >= Self::ANY {
+            f.write_fmt(format_args!("{highest} класс и младше"))
+        } else {
+            f.write_fmt(format_args!("с {lowest} по {highest} класс"))
+        }
     }
 }
 
@@ -246,11 +459,27 @@ impl From<GraduationYear> for Grade {
 pub struct PublicProfile {
     name: String,
     gender: UserGender,
-    grade: Grade,
+    stage: Stage,
     subjects: UserSubjects,
     dating_purpose: DatingPurpose,
     city: UserCity,
     about: String,
+    achievements: Vec<Achievement>,
+    /// Badge of the event the user is at
+    event: Option<String>,
+}
+
+impl PublicProfile {
+    /// Olympiad results are stored separately from the user
+    pub fn with_achievements(mut self, achievements: Vec<Achievement>) -> Self {
+        self.achievements = achievements;
+        self
+    }
+
+    pub fn with_event(mut self, event: Option<&events::Model>) -> Self {
+        self.event = event.map(crate::events::badge);
+        self
+    }
 }
 
 impl TryFrom<&users::Model> for PublicProfile {
@@ -260,11 +489,13 @@ impl TryFrom<&users::Model> for PublicProfile {
         Ok(Self {
             name: value.name.clone(),
             gender: value.gender.clone().into(),
-            grade: GraduationYear::from(value.graduation_year).into(),
-            subjects: value.subjects.try_into()?,
+            stage: value.into(),
+            subjects: value.subjects.clone().try_into()?,
             dating_purpose: value.dating_purpose.try_into()?,
             city: value.city.try_into()?,
             about: value.about.clone(),
+            achievements: Vec::new(),
+            event: None,
         })
     }
 }
@@ -272,52 +503,76 @@ impl TryFrom<&users::Model> for PublicProfile {
 impl Display for PublicProfile {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.write_fmt(format_args!(
-            "{} {}, {}.\n🔎 Интересует: {}.\n📚 {}\n.🧭 {}.\n\n{}",
+            "{} {}, {}.\n🔎 Интересует: {}.\n📚 {}\n.🧭 {}.",
             self.gender,
             self.name,
-            self.grade,
+            self.stage,
             self.dating_purpose,
             self.subjects,
             self.city,
-            self.about
-        ))
+        ))?;
+        if let Some(event) = &self.event {
+            f.write_fmt(format_args!("\n{event}"))?;
+        }
+        for achievement in &self.achievements {
+            f.write_fmt(format_args!("\n🏆 {achievement}"))?;
+        }
+        f.write_fmt(format_args!("\n\n{}", self.about))
     }
 }
 
-bitflags! {
-    #[derive(Clone, Copy, Default, Debug, PartialEq, Eq, Hash)]
-    pub struct Subjects: i32 {
-        const Art = 1 << 0;
-        const Astronomy = 1 << 1;
-        const Biology = 1 << 2;
-        const Chemistry = 1 << 3;
-        const Chinese = 1 << 4;
-        const Ecology = 1 << 5;
-        const Economics = 1 << 6;
-        const English = 1 << 7;
-        const French = 1 << 8;
-        const Geography = 1 << 9;
-        const German = 1 << 10;
-        const History = 1 << 11;
-        const Informatics = 1 << 12;
-        const Italian = 1 << 13;
-        const Law = 1 << 14;
-        const Literature = 1 << 15;
-        const Math = 1 << 16;
-        const Physics = 1 << 17;
-        const Russian = 1 << 18;
-        const Safety = 1 << 19;
-        const Social = 1 << 20;
-        const Spanish = 1 << 21;
-        const Sport = 1 << 22;
-        const Technology = 1 << 23;
-    }
+/// School subject, the discriminant is the id stored in the database. New
+/// subjects take the next free id, ids of the existing ones must not change.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Serialize,
+    Deserialize,
+    strum::EnumIter,
+    strum::EnumString,
+    strum::FromRepr,
+)]
+#[repr(i32)]
+pub enum Subject {
+    Art = 0,
+    Astronomy = 1,
+    Biology = 2,
+    Chemistry = 3,
+    Chinese = 4,
+    Ecology = 5,
+    Economics = 6,
+    English = 7,
+    French = 8,
+    Geography = 9,
+    German = 10,
+    History = 11,
+    Informatics = 12,
+    Italian = 13,
+    Law = 14,
+    Literature = 15,
+    Math = 16,
+    Physics = 17,
+    Russian = 18,
+    Safety = 19,
+    Social = 20,
+    Spanish = 21,
+    Sport = 22,
+    Technology = 23,
 }
 
-impl Subjects {
-    /// Name of exactly one subject
-    pub const fn name(&self) -> std::result::Result<&'static str, ()> {
-        Ok(match *self {
+impl Subject {
+    pub fn all() -> impl Iterator<Item = Self> {
+        <Self as strum::IntoEnumIterator>::iter()
+    }
+
+    pub const fn name(self) -> &'static str {
+        match self {
             Self::Art => "Искусство 🎨",
             Self::Astronomy => "Астрономия 🌌",
             Self::Biology => "Биология 🔬",
@@ -342,8 +597,181 @@ impl Subjects {
             Self::Spanish => "Испанский 🇪🇸",
             Self::Sport => "Физкультура 🏐",
             Self::Technology => "Технология 🚜",
-            _ => return Err(()),
-        })
+        }
+    }
+}
+
+impl Subject {
+    pub const fn category(self) -> SubjectCategory {
+        match self {
+            Self::Art
+            | Self::Geography
+            | Self::History
+            | Self::Law
+            | Self::Literature
+            | Self::Social => SubjectCategory::Humanitarian,
+            Self::Astronomy
+            | Self::Chemistry
+            | Self::Economics
+            | Self::Informatics
+            | Self::Math
+            | Self::Physics => SubjectCategory::Technical,
+            Self::Chinese
+            | Self::English
+            | Self::French
+            | Self::German
+            | Self::Italian
+            | Self::Spanish => SubjectCategory::Languages,
+            Self::Biology
+            | Self::Ecology
+            | Self::Russian
+            | Self::Safety
+            | Self::Sport
+            | Self::Technology => SubjectCategory::Other,
+        }
+    }
+}
+
+/// Group of subjects, a page of the subjects keyboard
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SubjectCategory {
+    Humanitarian,
+    Technical,
+    Languages,
+    Other,
+}
+
+impl SubjectCategory {
+    /// Categories in the order of pages
+    pub const ALL: [Self; 4] =
+        [Self::Humanitarian, Self::Technical, Self::Languages, Self::Other];
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Humanitarian => "Гуманитарные",
+            Self::Technical => "Технические",
+            Self::Languages => "Языки",
+            Self::Other => "Другие",
+        }
+    }
+
+    /// Number of the page with the category
+    pub fn page(self) -> u64 {
+        Self::ALL.iter().position(|c| *c == self).unwrap_or_default() as u64
+    }
+
+    pub fn subjects(self) -> impl Iterator<Item = Subject> {
+        Subject::all().filter(move |s| s.category() == self)
+    }
+}
+
+impl TryFrom<i32> for Subject {
+    type Error = anyhow::Error;
+
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        Self::from_repr(value).context("unknown subject")
+    }
+}
+
+impl From<Subject> for i32 {
+    fn from(value: Subject) -> Self {
+        value as Self
+    }
+}
+
+/// Set of subjects, stored in the database as an array of ids
+#[derive(Clone, Default, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "SubjectsRepr")]
+pub struct Subjects(BTreeSet<Subject>);
+
+/// Serialized `Subjects`, dialogues saved by older versions have bitflags
+/// like `Art | Math`
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SubjectsRepr {
+    Set(BTreeSet<Subject>),
+    Bitflags(String),
+}
+
+impl TryFrom<SubjectsRepr> for Subjects {
+    type Error = strum::ParseError;
+
+    fn try_from(value: SubjectsRepr) -> Result<Self, Self::Error> {
+        match value {
+            SubjectsRepr::Set(subjects) => Ok(Self(subjects)),
+            SubjectsRepr::Bitflags(flags) => flags
+                .split('|')
+                .map(str::trim)
+                .filter(|flag| !flag.is_empty())
+                .map(str::parse)
+                .collect(),
+        }
+    }
+}
+
+impl Subjects {
+    pub fn contains(&self, subject: Subject) -> bool {
+        self.0.contains(&subject)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Add the subject if it isn't selected, remove it otherwise
+    pub fn toggle(&mut self, subject: Subject) {
+        if !self.0.remove(&subject) {
+            self.0.insert(subject);
+        }
+    }
+
+    /// Add the subjects if some of them aren't selected, remove them
+    /// otherwise
+    pub fn toggle_all(&mut self, subjects: impl Iterator<Item = Subject>) {
+        let subjects: Vec<_> = subjects.collect();
+        if subjects.iter().all(|s| self.0.contains(s)) {
+            for subject in &subjects {
+                self.0.remove(subject);
+            }
+        } else {
+            self.0.extend(subjects);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+}
+
+impl FromIterator<Subject> for Subjects {
+    fn from_iter<T: IntoIterator<Item = Subject>>(iter: T) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+impl TryFrom<Vec<i32>> for Subjects {
+    type Error = anyhow::Error;
+
+    fn try_from(value: Vec<i32>) -> Result<Self, Self::Error> {
+        value.into_iter().map(Subject::try_from).collect()
+    }
+}
+
+impl From<Subjects> for Vec<i32> {
+    fn from(value: Subjects) -> Self {
+        value.0.into_iter().map(i32::from).collect()
+    }
+}
+
+impl Display for Subjects {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let names = self
+            .0
+            .iter()
+            .map(|s| s.name())
+            .sorted_unstable_by_key(|n| n.to_lowercase())
+            .join(", ");
+        f.write_str(&names)
     }
 }
 
@@ -370,10 +798,18 @@ macro_rules! impl_display_bitflags {
     };
 }
 
-impl_display_bitflags! {Subjects}
-
 bitflags! {
-    #[derive(Clone, Copy, Default, Debug, PartialEq, Eq, Hash)]
+    #[derive(
+        Clone,
+        Copy,
+        Default,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
+        Serialize,
+        Deserialize,
+    )]
     pub struct DatingPurpose: i16 {
         const Friendship = 1 << 0;
         const Studies = 1 << 1;
@@ -413,12 +849,12 @@ impl From<DatingPurpose> for i16 {
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct UserSubjects(Subjects);
 
 impl Display for UserSubjects {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        if self.0.bits() == 0 {
+        if self.0.is_empty() {
             f.write_str("Ничего не ботает.")?;
         } else {
             f.write_fmt(format_args!("Ботает: {}", self.0))?;
@@ -428,21 +864,17 @@ impl Display for UserSubjects {
     }
 }
 
-impl TryFrom<i32> for UserSubjects {
+impl TryFrom<Vec<i32>> for UserSubjects {
     type Error = anyhow::Error;
 
-    fn try_from(value: i32) -> Result<Self, Self::Error> {
-        let Some(subjects) = Subjects::from_bits(value) else {
-            bail!("can't construct subjects from bits")
-        };
-
-        Ok(Self(subjects))
+    fn try_from(value: Vec<i32>) -> Result<Self, Self::Error> {
+        Ok(Self(value.try_into()?))
     }
 }
 
-impl From<UserSubjects> for i32 {
+impl From<UserSubjects> for Vec<i32> {
     fn from(value: UserSubjects) -> Self {
-        value.0.bits()
+        value.0.into()
     }
 }
 
@@ -458,12 +890,12 @@ impl From<Subjects> for UserSubjects {
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct SubjectsFilter(Subjects);
 
 impl Display for SubjectsFilter {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        if self.0.bits() == 0 {
+        if self.0.is_empty() {
             f.write_str("Вам не важно, что ботает другой человек.")?;
         } else {
             f.write_fmt(format_args!(
@@ -489,25 +921,21 @@ impl From<Subjects> for SubjectsFilter {
     }
 }
 
-impl TryFrom<i32> for SubjectsFilter {
+impl TryFrom<Vec<i32>> for SubjectsFilter {
     type Error = anyhow::Error;
 
-    fn try_from(value: i32) -> Result<Self, Self::Error> {
-        let Some(subjects) = Subjects::from_bits(value) else {
-            bail!("can't construct subjects from bits")
-        };
-
-        Ok(Self(subjects))
+    fn try_from(value: Vec<i32>) -> Result<Self, Self::Error> {
+        Ok(Self(value.try_into()?))
     }
 }
 
-impl From<SubjectsFilter> for i32 {
+impl From<SubjectsFilter> for Vec<i32> {
     fn from(value: SubjectsFilter) -> Self {
-        value.0.bits()
+        value.0.into()
     }
 }
 
-#[derive(Clone, Default, Debug, PartialEq, Eq)]
+#[derive(Clone, Default, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct UserSettings {
     pub id: i64,
     pub name: Option<String>,
@@ -515,7 +943,12 @@ pub struct UserSettings {
     pub gender_filter: Option<GenderFilter>,
     pub about: Option<String>,
     pub active: Option<bool>,
-    pub grade: Option<Grade>,
+    pub stage: Option<Stage>,
+    /// Academic year when the stage was entered
+    pub grade_confirmed: Option<i16>,
+    /// Whether a school student wants to see university students
+    pub show_university: Option<bool>,
+    pub achievement_filter: Option<AchievementFilter>,
     pub grade_up_filter: Option<i16>,
     pub grade_down_filter: Option<i16>,
     pub subjects: Option<UserSubjects>,
@@ -529,6 +962,7 @@ impl TryFrom<users::Model> for UserSettings {
     type Error = anyhow::Error;
 
     fn try_from(value: users::Model) -> Result<Self, Self::Error> {
+        let stage = Stage::from(&value);
         Ok(Self {
             id: value.id,
             name: Some(value.name),
@@ -536,7 +970,10 @@ impl TryFrom<users::Model> for UserSettings {
             gender_filter: Some(value.gender_filter.into()),
             about: Some(value.about),
             active: Some(value.active),
-            grade: Some(GraduationYear::from(value.graduation_year).into()),
+            stage: Some(stage),
+            grade_confirmed: None,
+            show_university: Some(value.show_university),
+            achievement_filter: Some(value.achievement_filter.into()),
             grade_up_filter: Some(value.grade_up_filter),
             grade_down_filter: Some(value.grade_down_filter),
             subjects: Some(value.subjects.try_into()?),
@@ -571,14 +1008,91 @@ impl UserSettings {
             about: convert!(self.about),
             active: convert!(self.active),
             last_activity: ActiveValue::NotSet,
-            graduation_year: convert!(self.grade.map(GraduationYear::from)),
+            graduation_year: convert!(self
+                .stage
+                .as_ref()
+                .map(Stage::graduation_year)),
+            grade_confirmed: convert!(self.grade_confirmed),
+            university: convert!(self.stage.as_ref().map(Stage::is_university)),
+            show_university: convert!(self.show_university),
+            achievement_filter: convert!(self.achievement_filter),
             grade_up_filter: convert!(self.grade_up_filter),
             grade_down_filter: convert!(self.grade_down_filter),
             subjects: convert!(self.subjects),
             subjects_filter: convert!(self.subjects_filter),
             dating_purpose: convert!(self.dating_purpose),
             city: convert!(self.city),
+            event_id: ActiveValue::NotSet,
             location_filter: convert!(self.location_filter),
+            last_likes_reminder: ActiveValue::NotSet,
+            last_reminded_like: ActiveValue::NotSet,
+            banned: ActiveValue::NotSet,
+            broadcast_opt_out: ActiveValue::NotSet,
+            created: ActiveValue::NotSet,
         }
     }
+
+    /// A "field: before → after" line for every field that differs in `new`
+    pub fn changes(&self, new: &Self) -> Vec<String> {
+        self.describe()
+            .into_iter()
+            .zip(new.describe())
+            .filter(|((_, before), (_, after))| before != after)
+            .map(|((field, before), (_, after))| {
+                format!("{}: {before} → {after}", field.label())
+            })
+            .collect()
+    }
+
+    /// Fields as the user sees them
+    fn describe(&self) -> [(EditField, String); 12] {
+        fn show<T: Display>(value: Option<T>) -> String {
+            value.map_or_else(|| "—".to_owned(), |v| v.to_string())
+        }
+        fn subjects(value: Option<Subjects>) -> String {
+            match value {
+                Some(subjects) if !subjects.is_empty() => subjects.to_string(),
+                _ => "ничего".to_owned(),
+            }
+        }
+
+        let grade_filter = self.stage.clone().map(|stage| {
+            let filter = GradeFilter::new(
+                stage,
+                self.grade_up_filter.unwrap_or_default(),
+                self.grade_down_filter.unwrap_or_default(),
+            );
+            if self.show_university == Some(true) {
+                format!("{filter}.{}", crate::text::UNIVERSITY_SHOWN)
+            } else {
+                filter.to_string()
+            }
+        });
+        [
+            (EditField::Name, show(self.name.as_ref())),
+            (EditField::Gender, show(self.gender.as_ref())),
+            (EditField::GenderFilter, show(self.gender_filter.as_ref())),
+            (EditField::Grade, show(self.stage.as_ref())),
+            (EditField::GradeFilter, show(grade_filter)),
+            (
+                EditField::Subjects,
+                subjects(self.subjects.clone().map(Into::into)),
+            ),
+            (
+                EditField::SubjectsFilter,
+                subjects(self.subjects_filter.clone().map(Into::into)),
+            ),
+            (EditField::DatingPurpose, show(self.dating_purpose)),
+            (EditField::City, show(self.city.as_ref())),
+            (
+                EditField::LocationFilter,
+                show(self.location_filter.as_ref().map(LocationFilter::name)),
+            ),
+            (EditField::About, show(self.about.as_ref())),
+            (
+                EditField::AchievementsFilter,
+                show(self.achievement_filter.as_ref()),
+            ),
+        ]
+    }
 }
@@ -1,5 +1,6 @@
 use anyhow::bail;
 use itertools::Itertools;
+use sea_orm::Iterable;
 use teloxide::{
     requests::Requester,
     types::{
@@ -8,75 +9,138 @@ use teloxide::{
 };
 
 use crate::{
-    callbacks::{Callback, UpdateBitflags},
+    callbacks::{Callback, EditField, PhotosAction, SubjectsAction},
     text,
-    types::{DatingPurpose, Subjects},
+    types::{DatingPurpose, GradeFilter, SubjectCategory, Subjects},
     Bot,
 };
 
+/// Buttons to move and delete each of the images, numbered from 1 like
+/// the captions of the album
+pub fn make_photos_keyboard(ids: &[i32]) -> InlineKeyboardMarkup {
+    let button = |text: String, action| {
+        InlineKeyboardButton::callback(
+            text,
+            Callback::ManagePhotos(action).to_string(),
+        )
+    };
+    let mut keyboard: Vec<Vec<_>> = ids
+        .iter()
+        .enumerate()
+        .map(|(i, &id)| {
+            let n = i + 1;
+            let mut row = Vec::new();
+            if i > 0 {
+                row.push(button(format!("{n} ⬆"), PhotosAction::Up(id)));
+            }
+            if n < ids.len() {
+                row.push(button(format!("{n} ⬇"), PhotosAction::Down(id)));
+            }
+            if i > 0 {
+                row.push(button(format!("{n} ⭐"), PhotosAction::First(id)));
+            }
+            row.push(button(format!("{n} 🗑"), PhotosAction::Delete(id)));
+            row
+        })
+        .collect();
+
+    let mut last_row = Vec::new();
+    if ids.len() < crate::photos::MAX_PHOTOS {
+        last_row
+            .push(button(text::PHOTOS_ADD.to_owned(), PhotosAction::Add));
+    }
+    last_row.push(button(text::PHOTOS_DONE.to_owned(), PhotosAction::Done));
+    keyboard.push(last_row);
+    InlineKeyboardMarkup::new(keyboard)
+}
+
 pub enum SubjectsKeyboardType {
     User,
     Partner,
 }
 
-// TODO: refactor to Trait?
+/// A button for every profile field, `done` adds the one to finish editing
+pub fn make_edit_keyboard(done: bool) -> InlineKeyboardMarkup {
+    let button = |text, field| {
+        InlineKeyboardButton::callback(text, Callback::Edit(field).to_string())
+    };
+    let mut keyboard: Vec<Vec<_>> = EditField::iter()
+        .map(|field| button(field.label(), Some(field)))
+        .chunks(2)
+        .into_iter()
+        .map(Iterator::collect)
+        .collect();
+    if done {
+        keyboard.push(vec![button(text::EDIT_DONE, None)]);
+    }
+    InlineKeyboardMarkup::new(keyboard)
+}
+
+/// Page of the subjects keyboard with the subjects of one category
 pub fn make_subjects_keyboard(
-    selected: Subjects,
+    selected: &Subjects,
+    page: u64,
     tp: &SubjectsKeyboardType,
 ) -> InlineKeyboardMarkup {
-    let mut keyboard: Vec<Vec<_>> = Subjects::all()
-        .into_iter()
-        .sorted_by(|first, other| {
-            first
-                .name()
-                .unwrap()
-                .to_lowercase()
-                .cmp(&other.name().unwrap().to_lowercase())
-        })
-        .map(|subject| {
-            InlineKeyboardButton::callback(
-                if selected.contains(subject) {
-                    format!("✅ {}", subject.name().unwrap())
-                } else {
-                    subject.name().unwrap().to_owned()
-                },
-                match tp {
-                    SubjectsKeyboardType::Partner => {
-                        Callback::SetSubjectsFilter(UpdateBitflags::Update(
-                            subject,
-                        ))
-                    }
-                    SubjectsKeyboardType::User => {
-                        Callback::SetSubjects(UpdateBitflags::Update(subject))
-                    }
-                }
-                .to_string(),
-            )
-        })
-        .chunks(3)
-        .into_iter()
-        .map(|row| row.collect())
-        .collect();
+    let callback = |action| match tp {
+        SubjectsKeyboardType::Partner => Callback::SetSubjectsFilter(action),
+        SubjectsKeyboardType::User => Callback::SetSubjects(action),
+    };
+    let categories = SubjectCategory::ALL;
+    let category = categories[usize::try_from(page)
+        .unwrap_or_default()
+        .min(categories.len() - 1)];
 
-    let (text, cont) = match tp {
-        SubjectsKeyboardType::Partner => (
-            if selected.is_empty() {
-                text::SUBJECTS_PARTNER_EMPTY
-            } else {
-                text::SUBJECTS_CONTINUE
-            },
-            Callback::SetSubjectsFilter(UpdateBitflags::Continue).to_string(),
-        ),
-        SubjectsKeyboardType::User => (
-            if selected.is_empty() {
-                text::SUBJECTS_USER_EMPTY
-            } else {
-                text::SUBJECTS_CONTINUE
-            },
-            Callback::SetSubjects(UpdateBitflags::Continue).to_string(),
-        ),
+    let whole_category = if category.subjects().all(|s| selected.contains(s))
+    {
+        format!("✅ {}: {}", category.name(), text::SUBJECTS_UNSELECT_ALL)
+    } else {
+        format!("{}: {}", category.name(), text::SUBJECTS_SELECT_ALL)
+    };
+    let mut keyboard = vec![vec![InlineKeyboardButton::callback(
+        whole_category,
+        callback(SubjectsAction::TogglePage(page)).to_string(),
+    )]];
+    keyboard.extend(
+        category
+            .subjects()
+            .sorted_by_key(|subject| subject.name().to_lowercase())
+            .map(|subject| {
+                InlineKeyboardButton::callback(
+                    if selected.contains(subject) {
+                        format!("✅ {}", subject.name())
+                    } else {
+                        subject.name().to_owned()
+                    },
+                    callback(SubjectsAction::Toggle(subject)).to_string(),
+                )
+            })
+            .chunks(3)
+            .into_iter()
+            .map(|row| row.collect()),
+    );
+    keyboard.push(make_pagination_buttons(
+        page,
+        categories.len() as u64,
+        |page| callback(SubjectsAction::Page(page)),
+    ));
+    // Clearing nothing wouldn't change the keyboard, Telegram rejects that
+    if !selected.is_empty() {
+        keyboard.push(vec![InlineKeyboardButton::callback(
+            text::SUBJECTS_CLEAR,
+            callback(SubjectsAction::Clear(page)).to_string(),
+        )]);
+    }
+
+    let text = match tp {
+        _ if !selected.is_empty() => text::SUBJECTS_CONTINUE,
+        SubjectsKeyboardType::Partner => text::SUBJECTS_PARTNER_EMPTY,
+        SubjectsKeyboardType::User => text::SUBJECTS_USER_EMPTY,
     };
-    keyboard.push(vec![InlineKeyboardButton::callback(text, cont)]);
+    keyboard.push(vec![InlineKeyboardButton::callback(
+        text,
+        callback(SubjectsAction::Continue).to_string(),
+    )]);
     InlineKeyboardMarkup::new(keyboard)
 }
 
@@ -109,71 +173,67 @@ pub fn make_dating_purpose_keyboard(
     InlineKeyboardMarkup::new(keyboard)
 }
 
-// fn make_subjects_keyboard(selected: Subjects) -> InlineKeyboardMarkup {
-//     let mut keyboard = Vec::new();
-
-//     macro_rules! add_subjects {
-//         ($type:expr, $subjects:expr) => {
-//             keyboard.push(vec![InlineKeyboardButton::callback($type,
-// $type)]);             keyboard.extend(
-//                 $subjects
-//                     .into_iter()
-//                     .map(|s| {
-//                         InlineKeyboardButton::callback(
-//                             if selected.contains(s) {
-//                                 format!("✅ {}", subject_name(s).unwrap())
-//                             } else {
-//                                 subject_name(s).unwrap().to_owned()
-//                             },
-//                             s.bits().to_string(),
-//                         )
-//                     })
-//                     .chunks(3)
-//                     .into_iter()
-//                     .map(|r| r.collect()),
-//             );
-//         };
-//     }
-
-//     add_subjects!(text::SUBJECTS_HUMANITARIAN, [
-//         Subjects::Art,
-//         Subjects::Geography,
-//         Subjects::History,
-//         Subjects::Law,
-//         Subjects::Literature,
-//         Subjects::Social
-//     ]);
-//     add_subjects!(text::SUBJECTS_TECHNICAL, [
-//         Subjects::Astronomy,
-//         Subjects::Chemistry,
-//         Subjects::Economics,
-//         Subjects::Informatics,
-//         Subjects::Math,
-//         Subjects::Physics,
-//     ]);
-//     add_subjects!(text::SUBJECTS_LANGUAGES, [
-//         Subjects::Chinese,
-//         Subjects::English,
-//         Subjects::French,
-//         Subjects::German,
-//         Subjects::Italian,
-//         Subjects::Spanish
-//     ]);
-//     add_subjects!(text::SUBJECTS_OTHER, [
-//         Subjects::Biology,
-//         Subjects::Ecology,
-//         Subjects::Russian,
-//         Subjects::Safety,
-//         Subjects::Sport,
-//         Subjects::Technology,
-//     ]);
-
-//     keyboard.push(vec![InlineKeyboardButton::callback(
-//         text::SUBJECTS_CONTINUE,
-//         text::SUBJECTS_CONTINUE,
-//     )]);
-//     InlineKeyboardMarkup::new(keyboard)
-// }
+/// Grade filter presets. School students also get a toggle for university
+/// students, `show_university` is its state.
+pub fn make_grade_filter_keyboard(
+    up: i16,
+    down: i16,
+    show_university: Option<bool>,
+) -> InlineKeyboardMarkup {
+    let mut keyboard: Vec<Vec<_>> = GradeFilter::PRESETS
+        .into_iter()
+        .map(|(preset_up, preset_down)| {
+            let name = GradeFilter::preset_name(preset_up, preset_down);
+            InlineKeyboardButton::callback(
+                if (preset_up, preset_down) == (up, down) {
+                    format!("✅ {name}")
+                } else {
+                    name.to_owned()
+                },
+                Callback::SetGradeFilter { up: preset_up, down: preset_down }
+                    .to_string(),
+            )
+        })
+        .chunks(3)
+        .into_iter()
+        .map(|row| row.collect())
+        .collect();
+
+    if let Some(show) = show_university {
+        keyboard.push(vec![InlineKeyboardButton::callback(
+            if show {
+                format!("✅ {}", text::SHOW_UNIVERSITY)
+            } else {
+                text::SHOW_UNIVERSITY.to_owned()
+            },
+            Callback::SetUniversityFilter { show: !show }.to_string(),
+        )]);
+    }
+
+    InlineKeyboardMarkup::new(keyboard)
+}
+
+/// "⬅" and "➡" buttons to the neighbouring pages, if they exist
+pub fn make_pagination_buttons(
+    page: u64,
+    count: u64,
+    callback: impl Fn(u64) -> Callback,
+) -> Vec<InlineKeyboardButton> {
+    let mut buttons = Vec::new();
+    if page > 0 {
+        buttons.push(InlineKeyboardButton::callback(
+            "⬅",
+            callback(page - 1).to_string(),
+        ));
+    }
+    if page + 1 < count {
+        buttons.push(InlineKeyboardButton::callback(
+            "➡",
+            callback(page + 1).to_string(),
+        ));
+    }
+    buttons
+}
 
 pub async fn user_url(bot: &Bot, id: i64) -> anyhow::Result<Option<url::Url>> {
     let ChatKind::Private(private) = bot.get_chat(ChatId(id)).await?.kind else {